@@ -1,14 +1,17 @@
 use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 
-#[derive(Clone, Debug, PartialEq)]
+/// An asset is identified by its id, display_name is metadata not used to compare.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Asset {
     pub id: String,
     pub display_name: String,
 }
 
 /// Asset is an ERC-20, we will work with f64 + decimal shift instead of U256 for ease of use.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Erc20 {
     pub asset: Asset,
     pub erc20_address: Address,
@@ -31,15 +34,36 @@ impl Erc20 {
     }
 }
 
+impl PartialEq for Asset {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Asset {}
+
 impl Hash for Asset {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id.hash(state)
     }
 }
 
+impl PartialOrd for Asset {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Asset {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn asset_new() {
@@ -47,6 +71,18 @@ mod tests {
         assert_eq!(asset.id, String::from("ETH"));
     }
 
+    #[test]
+    fn asset_identity_by_id() {
+        let asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let same_asset = Asset::new(String::from("ETH"), String::from("Wrapped Ether"));
+        assert_eq!(asset, same_asset);
+        let mut assets = HashSet::new();
+        assets.insert(asset.clone());
+        assert!(!assets.insert(same_asset));
+        let lusd = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+        assert!(asset < lusd);
+    }
+
     #[test]
     fn erc20_new() {
         let asset = Asset::new(String::from("ETH"), String::from("Ether"));
@@ -0,0 +1,298 @@
+use crate::asset::{Asset, Erc20};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AssetRegistryError {
+    #[error("Config can't be read ({0})")]
+    ConfigReadIncorrect(String),
+    #[error("Config can't be parsed ({0})")]
+    ConfigParseIncorrect(String),
+    #[error("Asset {0} is defined more than once")]
+    AssetIdDuplicate(String),
+    #[error("Asset {0} has more than one token on chain {1}")]
+    AssetChainDuplicate(String, u64),
+    #[error("Token {1:?} on chain {0} is used by more than one asset")]
+    TokenAddressDuplicate(u64, Address),
+    #[error("Token symbol {1} on chain {0} is used by more than one asset")]
+    TokenSymbolDuplicate(u64, String),
+}
+
+/// WrappedNative is the native coin of a chain, its token is the wrapped one (ETH as WETH)
+/// as the native coin has no contract.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    Stablecoin,
+    Volatile,
+    WrappedNative,
+}
+
+/// Token of an asset on one chain.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub chain_id: u64,
+    pub address: Address,
+    pub decimals: usize,
+    pub symbol: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetConfig {
+    pub id: String,
+    pub display_name: String,
+    pub kind: AssetKind,
+    #[serde(default)]
+    pub tokens: Vec<TokenMetadata>,
+}
+
+/// Assets and their tokens by chain, see config/assets.toml.
+/// Deserialized through new, so a registry is always validated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "AssetRegistryRaw")]
+pub struct AssetRegistry {
+    assets: Vec<AssetConfig>,
+}
+
+#[derive(Deserialize)]
+struct AssetRegistryRaw {
+    assets: Vec<AssetConfig>,
+}
+
+impl TryFrom<AssetRegistryRaw> for AssetRegistry {
+    type Error = AssetRegistryError;
+
+    fn try_from(raw: AssetRegistryRaw) -> Result<Self, Self::Error> {
+        AssetRegistry::new(raw.assets)
+    }
+}
+
+impl AssetRegistry {
+    pub fn new(assets: Vec<AssetConfig>) -> Result<Self, AssetRegistryError> {
+        let registry = Self { assets };
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    pub fn from_toml_str(config: &str) -> Result<Self, AssetRegistryError> {
+        let raw: AssetRegistryRaw = toml::from_str(config)
+            .map_err(|e| AssetRegistryError::ConfigParseIncorrect(e.to_string()))?;
+        AssetRegistry::new(raw.assets)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, AssetRegistryError> {
+        let config = fs::read_to_string(path)
+            .map_err(|e| AssetRegistryError::ConfigReadIncorrect(e.to_string()))?;
+        AssetRegistry::from_toml_str(&config)
+    }
+
+    pub fn assets(&self) -> &Vec<AssetConfig> {
+        &self.assets
+    }
+
+    /// Ids, tokens by chain, addresses and symbols by chain should be unique.
+    pub fn validate(&self) -> Result<(), AssetRegistryError> {
+        for (index, asset) in self.assets.iter().enumerate() {
+            let others = &self.assets[index + 1..];
+            if others.iter().any(|i| i.id == asset.id) {
+                return Err(AssetRegistryError::AssetIdDuplicate(asset.id.clone()));
+            }
+            for (token_index, token) in asset.tokens.iter().enumerate() {
+                let is_same_chain = asset.tokens[token_index + 1..]
+                    .iter()
+                    .any(|i| i.chain_id == token.chain_id);
+                if is_same_chain {
+                    return Err(AssetRegistryError::AssetChainDuplicate(
+                        asset.id.clone(),
+                        token.chain_id,
+                    ));
+                }
+                let other_tokens = others
+                    .iter()
+                    .flat_map(|i| &i.tokens)
+                    .filter(|i| i.chain_id == token.chain_id);
+                for other_token in other_tokens {
+                    if other_token.address == token.address {
+                        return Err(AssetRegistryError::TokenAddressDuplicate(
+                            token.chain_id,
+                            token.address,
+                        ));
+                    }
+                    if other_token.symbol == token.symbol {
+                        return Err(AssetRegistryError::TokenSymbolDuplicate(
+                            token.chain_id,
+                            token.symbol.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn asset(&self, id: &str) -> Option<Asset> {
+        self._asset_config(id).map(AssetConfig::asset)
+    }
+
+    pub fn kind(&self, id: &str) -> Option<&AssetKind> {
+        self._asset_config(id).map(|i| &i.kind)
+    }
+
+    pub fn token(&self, id: &str, chain_id: u64) -> Option<&TokenMetadata> {
+        self._asset_config(id)?
+            .tokens
+            .iter()
+            .find(|i| i.chain_id == chain_id)
+    }
+
+    pub fn erc20(&self, id: &str, chain_id: u64) -> Option<Erc20> {
+        let token = self.token(id, chain_id)?;
+        Some(Erc20::new(self.asset(id)?, token.address, token.decimals))
+    }
+
+    pub fn asset_by_address(&self, chain_id: u64, address: &Address) -> Option<Asset> {
+        self._asset_config_by_token(|i| i.chain_id == chain_id && &i.address == address)
+    }
+
+    pub fn asset_by_symbol(&self, chain_id: u64, symbol: &str) -> Option<Asset> {
+        self._asset_config_by_token(|i| i.chain_id == chain_id && i.symbol == symbol)
+    }
+
+    fn _asset_config(&self, id: &str) -> Option<&AssetConfig> {
+        self.assets.iter().find(|i| i.id == id)
+    }
+
+    fn _asset_config_by_token(&self, predicate: impl Fn(&TokenMetadata) -> bool) -> Option<Asset> {
+        self.assets
+            .iter()
+            .find(|i| i.tokens.iter().any(&predicate))
+            .map(AssetConfig::asset)
+    }
+}
+
+impl AssetConfig {
+    pub fn asset(&self) -> Asset {
+        Asset::new(self.id.clone(), self.display_name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _registry_new() -> AssetRegistry {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../config/assets.toml");
+        AssetRegistry::from_file(path).unwrap()
+    }
+
+    fn _token_new(chain_id: u64, address: Address, symbol: &str) -> TokenMetadata {
+        TokenMetadata {
+            chain_id,
+            address,
+            decimals: 18,
+            symbol: String::from(symbol),
+        }
+    }
+
+    fn _asset_config_new(id: &str, tokens: Vec<TokenMetadata>) -> AssetConfig {
+        AssetConfig {
+            id: String::from(id),
+            display_name: String::from(id),
+            kind: AssetKind::Volatile,
+            tokens,
+        }
+    }
+
+    #[test]
+    fn asset_registry_from_file() {
+        let registry = _registry_new();
+        let asset = registry.asset("LUSD").unwrap();
+        assert_eq!(asset.display_name, String::from("Liquity USD"));
+        assert_eq!(registry.kind("LUSD"), Some(&AssetKind::Stablecoin));
+        let erc20 = registry.erc20("ETH", 1).unwrap();
+        assert_eq!(erc20.decimal_shift, 18);
+        assert!(registry.erc20("ETH", 10).is_none());
+
+        let address = registry.token("ETH", 1).unwrap().address;
+        assert_eq!(registry.asset_by_address(1, &address).unwrap().id, "ETH");
+        assert_eq!(registry.asset_by_symbol(1, "WETH").unwrap().id, "ETH");
+        assert!(registry.asset_by_symbol(10, "WETH").is_none());
+
+        let registry = AssetRegistry::from_file("/not/a/file.toml");
+        assert!(registry.is_err());
+    }
+
+    #[test]
+    fn asset_registry_from_toml_str_incorrect() {
+        let registry = AssetRegistry::from_toml_str(
+            r#"
+            [[assets]]
+            id = "ETH"
+            display_name = "Ether"
+            kind = "unknown"
+            "#,
+        );
+        assert!(matches!(
+            registry,
+            Err(AssetRegistryError::ConfigParseIncorrect(_))
+        ));
+        let config = r#"
+            [[assets]]
+            id = "ETH"
+            display_name = "Ether"
+            kind = "wrapped_native"
+
+            [[assets]]
+            id = "ETH"
+            display_name = "Ether"
+            kind = "wrapped_native"
+            "#;
+        assert_eq!(
+            AssetRegistry::from_toml_str(config),
+            Err(AssetRegistryError::AssetIdDuplicate(String::from("ETH")))
+        );
+        // deserializing skips no validation
+        assert!(toml::from_str::<AssetRegistry>(config).is_err());
+    }
+
+    #[test]
+    fn asset_registry_new_duplicate() {
+        let address = Address::random();
+        let registry = AssetRegistry::new(vec![
+            _asset_config_new("ETH", vec![]),
+            _asset_config_new("ETH", vec![]),
+        ]);
+        assert_eq!(
+            registry.unwrap_err(),
+            AssetRegistryError::AssetIdDuplicate(String::from("ETH"))
+        );
+        let registry = AssetRegistry::new(vec![_asset_config_new(
+            "ETH",
+            vec![
+                _token_new(1, address, "WETH"),
+                _token_new(1, Address::random(), "ETH"),
+            ],
+        )]);
+        assert_eq!(
+            registry.unwrap_err(),
+            AssetRegistryError::AssetChainDuplicate(String::from("ETH"), 1)
+        );
+        let registry = AssetRegistry::new(vec![
+            _asset_config_new("ETH", vec![_token_new(1, address, "WETH")]),
+            _asset_config_new("STETH", vec![_token_new(1, address, "STETH")]),
+        ]);
+        assert_eq!(
+            registry.unwrap_err(),
+            AssetRegistryError::TokenAddressDuplicate(1, address)
+        );
+        // same address on another chain is another token
+        let registry = AssetRegistry::new(vec![
+            _asset_config_new("ETH", vec![_token_new(1, address, "WETH")]),
+            _asset_config_new("STETH", vec![_token_new(10, address, "WETH")]),
+        ]);
+        assert!(registry.is_ok());
+    }
+}
@@ -0,0 +1,456 @@
+use crate::order::{MarketOrder, OrderError};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Relative float error tolerated between the filled, sent and parent quantities.
+const QUANTITY_TOLERANCE: f64 = 1e-9;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ExecutionError {
+    #[error("Order error {0}")]
+    Order(OrderError),
+    #[error("Duration should be greater than 0({0})")]
+    DurationShouldBeGtZero(u64),
+    #[error("Slice count should be greater than 0({0})")]
+    SliceCountShouldBeGtZero(u32),
+    #[error("Participation rate should be between 0 and 1({0})")]
+    ParticipationRateIncorrect(f64),
+    #[error("Visible quantity should be greater than 0({0})")]
+    VisibleQuantityShouldBeGtZero(f64),
+    #[error("Execution is not active ({0:?})")]
+    ExecutionNotActive(ExecutionStatus),
+    #[error("Quantity should be greater than 0({0})")]
+    QuantityShouldBeGtZero(f64),
+    #[error("Quantity is greater than the quantity sent and not filled ({0} > {1})")]
+    QuantityGtQuantityPending(f64, f64),
+    #[error("Filled, sent and parent quantities should be increasing ({0}, {1}, {2})")]
+    QuantitiesIncorrect(f64, f64, f64),
+}
+
+/// How a parent order is sliced into child orders.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExecutionAlgorithm {
+    /// Same quantity sent at slice_count regular times over duration (ms).
+    Twap { duration: u64, slice_count: u32 },
+    /// Each child order is participation_rate of the market volume since the last one.
+    VolumeParticipation { participation_rate: f64 },
+    /// A child order of visible_quantity is sent once the previous one is filled.
+    Iceberg { visible_quantity: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExecutionStatus {
+    Active,
+    Completed,
+    Cancelled,
+}
+
+/// Slicing of a parent order, child quantities are sent, then filled or released.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ExecutionPlanRaw")]
+pub struct ExecutionPlan {
+    pub parent_order: MarketOrder,
+    pub algorithm: ExecutionAlgorithm,
+    pub start_time: u64,
+    pub sent_quantity: f64,
+    pub filled_quantity: f64,
+    pub child_order_count: u32,
+    pub status: ExecutionStatus,
+}
+
+/// ExecutionPlan as deserialized, checked by ExecutionPlan::new and its quantities.
+#[derive(Deserialize)]
+struct ExecutionPlanRaw {
+    parent_order: MarketOrder,
+    algorithm: ExecutionAlgorithm,
+    start_time: u64,
+    sent_quantity: f64,
+    filled_quantity: f64,
+    child_order_count: u32,
+    status: ExecutionStatus,
+}
+
+impl ExecutionAlgorithm {
+    pub fn validate(&self) -> Result<(), ExecutionError> {
+        match self {
+            ExecutionAlgorithm::Twap {
+                duration,
+                slice_count,
+            } => {
+                if *duration == 0 {
+                    return Err(ExecutionError::DurationShouldBeGtZero(*duration));
+                }
+                if *slice_count == 0 {
+                    return Err(ExecutionError::SliceCountShouldBeGtZero(*slice_count));
+                }
+            }
+            ExecutionAlgorithm::VolumeParticipation { participation_rate } => {
+                if *participation_rate <= 0f64 || *participation_rate > 1f64 {
+                    return Err(ExecutionError::ParticipationRateIncorrect(
+                        *participation_rate,
+                    ));
+                }
+            }
+            ExecutionAlgorithm::Iceberg { visible_quantity } => {
+                if *visible_quantity <= 0f64 {
+                    return Err(ExecutionError::VisibleQuantityShouldBeGtZero(
+                        *visible_quantity,
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<ExecutionPlanRaw> for ExecutionPlan {
+    type Error = ExecutionError;
+
+    fn try_from(raw: ExecutionPlanRaw) -> Result<Self, Self::Error> {
+        let mut plan = ExecutionPlan::new(raw.parent_order, raw.algorithm, raw.start_time)?;
+        let tolerance = plan._tolerance();
+        let is_quantities_correct = raw.filled_quantity >= 0f64
+            && raw.filled_quantity <= raw.sent_quantity + tolerance
+            && raw.sent_quantity <= plan.parent_order.quantity_sell + tolerance;
+        if !is_quantities_correct {
+            return Err(ExecutionError::QuantitiesIncorrect(
+                raw.filled_quantity,
+                raw.sent_quantity,
+                plan.parent_order.quantity_sell,
+            ));
+        }
+        plan.sent_quantity = raw.sent_quantity;
+        plan.filled_quantity = raw.filled_quantity;
+        plan.child_order_count = raw.child_order_count;
+        plan.status = raw.status;
+        Ok(plan)
+    }
+}
+
+impl ExecutionPlan {
+    pub fn new(
+        parent_order: MarketOrder,
+        algorithm: ExecutionAlgorithm,
+        start_time: u64,
+    ) -> Result<Self, ExecutionError> {
+        parent_order.validate().map_err(ExecutionError::Order)?;
+        algorithm.validate()?;
+        Ok(Self {
+            parent_order,
+            algorithm,
+            start_time,
+            sent_quantity: 0f64,
+            filled_quantity: 0f64,
+            child_order_count: 0,
+            status: ExecutionStatus::Active,
+        })
+    }
+
+    /// Quantity of the parent order not sent yet.
+    pub fn quantity_left(&self) -> f64 {
+        (self.parent_order.quantity_sell - self.sent_quantity).max(0f64)
+    }
+
+    /// Quantity sent in child orders and not filled yet.
+    pub fn pending_quantity(&self) -> f64 {
+        self.sent_quantity - self.filled_quantity
+    }
+
+    /// Filled part of the parent order, between 0 and 1.
+    pub fn progress(&self) -> f64 {
+        self.filled_quantity / self.parent_order.quantity_sell
+    }
+
+    /// Child order to send at time, market_volume is the volume in asset sell
+    /// traded since the last call (only used by volume participation).
+    pub fn next_child_order(&mut self, time: u64, market_volume: f64) -> Option<MarketOrder> {
+        if self.status != ExecutionStatus::Active {
+            return None;
+        }
+        let quantity_left = self.quantity_left();
+        if quantity_left <= self._tolerance() {
+            return None;
+        }
+        let quantity = match &self.algorithm {
+            ExecutionAlgorithm::Twap {
+                duration,
+                slice_count,
+            } => {
+                let elapsed = time.saturating_sub(self.start_time) as u128;
+                let slice_due_count = ((elapsed * *slice_count as u128 / *duration as u128) + 1)
+                    .min(*slice_count as u128) as u32;
+                // Once every slice is sent, released quantity is sent again as a catch-up slice
+                let is_every_slice_sent = self.child_order_count >= *slice_count;
+                if self.child_order_count >= slice_due_count && !is_every_slice_sent {
+                    return None;
+                }
+                quantity_left / (slice_count.saturating_sub(self.child_order_count)).max(1) as f64
+            }
+            ExecutionAlgorithm::VolumeParticipation { participation_rate } => {
+                (market_volume * participation_rate).min(quantity_left)
+            }
+            ExecutionAlgorithm::Iceberg { visible_quantity } => {
+                if self.pending_quantity() > self._tolerance() {
+                    return None;
+                }
+                visible_quantity.min(quantity_left)
+            }
+        };
+        if quantity <= 0f64 {
+            return None;
+        }
+        self.sent_quantity += quantity;
+        self.child_order_count += 1;
+        Some(MarketOrder::new(
+            self.parent_order.asset_sell.clone(),
+            self.parent_order.asset_buy.clone(),
+            quantity,
+        ))
+    }
+
+    /// Records quantity of a child order filled, child orders sent before a cancel
+    /// can still be filled but the plan stays cancelled.
+    pub fn fill(&mut self, quantity: f64) -> Result<(), ExecutionError> {
+        if self.status == ExecutionStatus::Completed {
+            return Err(ExecutionError::ExecutionNotActive(self.status.clone()));
+        }
+        self._check_pending_quantity(quantity)?;
+        self.filled_quantity += quantity;
+        let is_completed = self.status == ExecutionStatus::Active
+            && self.quantity_left() <= self._tolerance()
+            && self.pending_quantity() <= self._tolerance();
+        if is_completed {
+            self.status = ExecutionStatus::Completed;
+        }
+        Ok(())
+    }
+
+    /// Gives back quantity of a child order not filled (rejected, expired) to be sent again.
+    pub fn release(&mut self, quantity: f64) -> Result<(), ExecutionError> {
+        self._check_pending_quantity(quantity)?;
+        self.sent_quantity -= quantity;
+        Ok(())
+    }
+
+    /// Cancels the remainder, returns the quantity not sent.
+    pub fn cancel(&mut self) -> Result<f64, ExecutionError> {
+        if self.status != ExecutionStatus::Active {
+            return Err(ExecutionError::ExecutionNotActive(self.status.clone()));
+        }
+        self.status = ExecutionStatus::Cancelled;
+        Ok(self.quantity_left())
+    }
+
+    fn _check_pending_quantity(&self, quantity: f64) -> Result<(), ExecutionError> {
+        if quantity <= 0f64 {
+            return Err(ExecutionError::QuantityShouldBeGtZero(quantity));
+        }
+        let pending_quantity = self.pending_quantity();
+        if quantity > pending_quantity + self._tolerance() {
+            return Err(ExecutionError::QuantityGtQuantityPending(
+                quantity,
+                pending_quantity,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Float error tolerated on the quantities, relative to the parent quantity.
+    fn _tolerance(&self) -> f64 {
+        self.parent_order.quantity_sell * QUANTITY_TOLERANCE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+
+    fn _parent_order_new(quantity_sell: f64) -> MarketOrder {
+        MarketOrder::new(
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+            quantity_sell,
+        )
+    }
+
+    #[test]
+    fn execution_plan_new_incorrect() {
+        let plan = ExecutionPlan::new(
+            _parent_order_new(10f64),
+            ExecutionAlgorithm::Twap {
+                duration: 1_000,
+                slice_count: 0,
+            },
+            0,
+        );
+        assert!(plan.is_err());
+        assert_eq!(
+            plan.unwrap_err(),
+            ExecutionError::SliceCountShouldBeGtZero(0)
+        );
+        let plan = ExecutionPlan::new(
+            _parent_order_new(0f64),
+            ExecutionAlgorithm::Iceberg {
+                visible_quantity: 1f64,
+            },
+            0,
+        );
+        assert_eq!(
+            plan.unwrap_err(),
+            ExecutionError::Order(OrderError::QuantitySellShouldBeGtZero(0f64))
+        );
+    }
+
+    #[test]
+    fn execution_plan_twap() {
+        let mut plan = ExecutionPlan::new(
+            _parent_order_new(10f64),
+            ExecutionAlgorithm::Twap {
+                duration: 1_000,
+                slice_count: 4,
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            plan.next_child_order(0, 0f64).unwrap().quantity_sell,
+            2.5f64
+        );
+        assert!(plan.next_child_order(100, 0f64).is_none());
+        assert_eq!(
+            plan.next_child_order(250, 0f64).unwrap().quantity_sell,
+            2.5f64
+        );
+        // late: the two due slices are sent one by one
+        assert_eq!(
+            plan.next_child_order(900, 0f64).unwrap().quantity_sell,
+            2.5f64
+        );
+        assert_eq!(
+            plan.next_child_order(900, 0f64).unwrap().quantity_sell,
+            2.5f64
+        );
+        assert!(plan.next_child_order(2_000, 0f64).is_none());
+        assert!(plan.fill(10f64).is_ok());
+        assert_eq!(plan.status, ExecutionStatus::Completed);
+        assert_eq!(plan.progress(), 1f64);
+    }
+
+    #[test]
+    fn execution_plan_volume_participation() {
+        let mut plan = ExecutionPlan::new(
+            _parent_order_new(10f64),
+            ExecutionAlgorithm::VolumeParticipation {
+                participation_rate: 0.1f64,
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(plan.next_child_order(0, 40f64).unwrap().quantity_sell, 4f64);
+        assert!(plan.next_child_order(1, 0f64).is_none());
+        assert_eq!(
+            plan.next_child_order(2, 100f64).unwrap().quantity_sell,
+            6f64
+        );
+        assert_eq!(plan.quantity_left(), 0f64);
+    }
+
+    #[test]
+    fn execution_plan_iceberg_release_and_cancel() {
+        let mut plan = ExecutionPlan::new(
+            _parent_order_new(10f64),
+            ExecutionAlgorithm::Iceberg {
+                visible_quantity: 3f64,
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(plan.next_child_order(0, 0f64).unwrap().quantity_sell, 3f64);
+        assert!(plan.next_child_order(1, 0f64).is_none());
+        assert!(plan.fill(2f64).is_ok());
+        assert_eq!(
+            plan.fill(2f64).unwrap_err(),
+            ExecutionError::QuantityGtQuantityPending(2f64, 1f64)
+        );
+        assert!(plan.release(1f64).is_ok());
+        assert_eq!(plan.next_child_order(2, 0f64).unwrap().quantity_sell, 3f64);
+        assert_eq!(plan.progress(), 0.2f64);
+        assert_eq!(plan.cancel().unwrap(), 5f64);
+        assert_eq!(plan.status, ExecutionStatus::Cancelled);
+        assert!(plan.next_child_order(3, 0f64).is_none());
+        assert!(plan.cancel().is_err());
+        // the child order sent before the cancel is filled, the plan stays cancelled
+        assert!(plan.fill(3f64).is_ok());
+        assert_eq!(plan.status, ExecutionStatus::Cancelled);
+    }
+
+    #[test]
+    fn execution_plan_twap_release_after_last_slice() {
+        let mut plan = ExecutionPlan::new(
+            _parent_order_new(2f64),
+            ExecutionAlgorithm::Twap {
+                duration: 1_000,
+                slice_count: 2,
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(plan.next_child_order(0, 0f64).unwrap().quantity_sell, 1f64);
+        assert_eq!(
+            plan.next_child_order(500, 0f64).unwrap().quantity_sell,
+            1f64
+        );
+        assert!(plan.fill(1f64).is_ok());
+        assert!(plan.release(1f64).is_ok());
+        assert_eq!(plan.quantity_left(), 1f64);
+
+        assert_eq!(
+            plan.next_child_order(1_000, 0f64).unwrap().quantity_sell,
+            1f64
+        );
+        assert!(plan.fill(1f64).is_ok());
+        assert_eq!(plan.status, ExecutionStatus::Completed);
+        assert_eq!(
+            plan.fill(1f64).unwrap_err(),
+            ExecutionError::ExecutionNotActive(ExecutionStatus::Completed)
+        );
+    }
+
+    #[test]
+    fn execution_plan_float_error() {
+        let mut plan = ExecutionPlan::new(
+            _parent_order_new(1e9f64),
+            ExecutionAlgorithm::Twap {
+                duration: 1_000,
+                slice_count: 3,
+            },
+            0,
+        )
+        .unwrap();
+        let mut sent_quantity = 0f64;
+        for time in [0, 400, 800] {
+            sent_quantity += plan.next_child_order(time, 0f64).unwrap().quantity_sell;
+        }
+        // the fills add up to more than the slices by a float error
+        assert!(plan.fill(sent_quantity * (1f64 + 1e-12)).is_ok());
+        assert_eq!(plan.status, ExecutionStatus::Completed);
+    }
+
+    #[test]
+    fn execution_plan_twap_late() {
+        let mut plan = ExecutionPlan::new(
+            _parent_order_new(10f64),
+            ExecutionAlgorithm::Twap {
+                duration: 1_000,
+                slice_count: 4,
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            plan.next_child_order(u64::MAX, 0f64).unwrap().quantity_sell,
+            2.5f64
+        );
+    }
+}
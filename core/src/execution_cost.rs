@@ -0,0 +1,224 @@
+use crate::asset::Asset;
+use crate::order::MarketOrder;
+use crate::order_lifecycle::Fill;
+use crate::price_impact::PriceImpact;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ExecutionCostError {
+    #[error("Fee rate should be between 0 and 1({0})")]
+    FeeRateIncorrect(f64),
+    #[error("Fixed cost can't be negative({0})")]
+    FixedCostIsNegative(f64),
+    #[error("Gas price can't be negative({0})")]
+    GasPriceIsNegative(f64),
+    #[error("Gas asset price can't be negative({0})")]
+    GasAssetPriceIsNegative(f64),
+}
+
+/// Cost of a trade in quote asset, each category kept apart to be reported.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ExecutionCost {
+    pub exchange_fee: f64,
+    pub fixed_cost: f64,
+    pub gas_cost: f64,
+}
+
+/// Cost of trading a quote_notional value, in quote asset.
+pub trait CostModel {
+    fn cost(&self, quote_notional: f64) -> ExecutionCost;
+
+    /// Cost of a fill of the order, fill price is asset buy by asset sell.
+    fn fill_cost(&self, order: &MarketOrder, fill: &Fill, quote_asset: &Asset) -> ExecutionCost {
+        let quote_notional = if &order.asset_sell == quote_asset {
+            fill.quantity
+        } else {
+            fill.quantity * fill.price
+        };
+        self.cost(quote_notional)
+    }
+
+    /// Cost expected before sending the order, from its expected price impact.
+    fn estimate(
+        &self,
+        order: &MarketOrder,
+        price_impact: &PriceImpact,
+        quote_asset: &Asset,
+    ) -> ExecutionCost {
+        let quote_notional = if &order.asset_sell == quote_asset {
+            price_impact.quantity_sell
+        } else {
+            price_impact.quantity_buy
+        };
+        self.cost(quote_notional)
+    }
+}
+
+/// Percentage fee of the exchange or AMM (0.003 = 0.3%), a fixed cost by trade
+/// and gas_price * gas_units paid in the gas asset, converted with gas_asset_price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "FeeAndGasCostModelRaw")]
+pub struct FeeAndGasCostModel {
+    pub fee_rate: f64,
+    pub fixed_cost: f64,
+    pub gas_price: f64,
+    pub gas_units: u64,
+    pub gas_asset_price: f64,
+}
+
+/// FeeAndGasCostModel as deserialized, checked by FeeAndGasCostModel::new.
+#[derive(Deserialize)]
+struct FeeAndGasCostModelRaw {
+    fee_rate: f64,
+    fixed_cost: f64,
+    gas_price: f64,
+    gas_units: u64,
+    gas_asset_price: f64,
+}
+
+impl ExecutionCost {
+    pub fn new(exchange_fee: f64, fixed_cost: f64, gas_cost: f64) -> Self {
+        Self {
+            exchange_fee,
+            fixed_cost,
+            gas_cost,
+        }
+    }
+
+    pub fn total(&self) -> f64 {
+        self.exchange_fee + self.fixed_cost + self.gas_cost
+    }
+
+    pub fn add(&mut self, cost: &ExecutionCost) {
+        self.exchange_fee += cost.exchange_fee;
+        self.fixed_cost += cost.fixed_cost;
+        self.gas_cost += cost.gas_cost;
+    }
+
+    /// Total in asset buy, as recorded in Fill::fee.
+    pub fn fill_fee(&self, order: &MarketOrder, fill: &Fill, quote_asset: &Asset) -> f64 {
+        if &order.asset_buy == quote_asset {
+            self.total()
+        } else {
+            self.total() * fill.price
+        }
+    }
+}
+
+impl FeeAndGasCostModel {
+    pub fn new(
+        fee_rate: f64,
+        fixed_cost: f64,
+        gas_price: f64,
+        gas_units: u64,
+        gas_asset_price: f64,
+    ) -> Result<Self, ExecutionCostError> {
+        if !(0f64..=1f64).contains(&fee_rate) {
+            return Err(ExecutionCostError::FeeRateIncorrect(fee_rate));
+        }
+        if fixed_cost < 0f64 {
+            return Err(ExecutionCostError::FixedCostIsNegative(fixed_cost));
+        }
+        if gas_price < 0f64 {
+            return Err(ExecutionCostError::GasPriceIsNegative(gas_price));
+        }
+        if gas_asset_price < 0f64 {
+            return Err(ExecutionCostError::GasAssetPriceIsNegative(gas_asset_price));
+        }
+        Ok(Self {
+            fee_rate,
+            fixed_cost,
+            gas_price,
+            gas_units,
+            gas_asset_price,
+        })
+    }
+}
+
+impl CostModel for FeeAndGasCostModel {
+    fn cost(&self, quote_notional: f64) -> ExecutionCost {
+        ExecutionCost::new(
+            quote_notional * self.fee_rate,
+            self.fixed_cost,
+            self.gas_price * self.gas_units as f64 * self.gas_asset_price,
+        )
+    }
+}
+
+impl TryFrom<FeeAndGasCostModelRaw> for FeeAndGasCostModel {
+    type Error = ExecutionCostError;
+
+    fn try_from(raw: FeeAndGasCostModelRaw) -> Result<Self, Self::Error> {
+        FeeAndGasCostModel::new(
+            raw.fee_rate,
+            raw.fixed_cost,
+            raw.gas_price,
+            raw.gas_units,
+            raw.gas_asset_price,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _cost_model_new() -> FeeAndGasCostModel {
+        // 0.3% fee, 1 LUSD by trade, 20 gwei * 150_000 gas at 2_000 LUSD by ETH
+        FeeAndGasCostModel::new(0.003f64, 1f64, 20e-9f64, 150_000, 2_000f64).unwrap()
+    }
+
+    fn _eth() -> Asset {
+        Asset::new(String::from("ETH"), String::from("Ether"))
+    }
+
+    fn _lusd() -> Asset {
+        Asset::new(String::from("LUSD"), String::from("Liquity USD"))
+    }
+
+    #[test]
+    fn fee_and_gas_cost_model_new_incorrect() {
+        let model = FeeAndGasCostModel::new(1.5f64, 1f64, 20e-9f64, 150_000, 2_000f64);
+        assert!(model.is_err());
+        assert_eq!(
+            model.unwrap_err(),
+            ExecutionCostError::FeeRateIncorrect(1.5f64)
+        );
+    }
+
+    #[test]
+    fn fee_and_gas_cost_model_cost() {
+        let model = _cost_model_new();
+        let cost = model.cost(10_000f64);
+        assert_eq!(cost.exchange_fee, 30f64);
+        assert_eq!(cost.fixed_cost, 1f64);
+        assert!((cost.gas_cost - 6f64).abs() < 1e-9);
+        assert!((cost.total() - 37f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fee_and_gas_cost_model_fill_cost() {
+        let model = FeeAndGasCostModel::new(0.003f64, 1f64, 0f64, 0, 0f64).unwrap();
+        let order = MarketOrder::new(_eth(), _lusd(), 2f64);
+        let fill = Fill::new(2_000f64, 2f64, 0f64, 100).unwrap();
+        let cost = model.fill_cost(&order, &fill, &_lusd());
+        assert_eq!(cost, ExecutionCost::new(12f64, 1f64, 0f64));
+        assert_eq!(cost.fill_fee(&order, &fill, &_lusd()), 13f64);
+
+        let order = MarketOrder::new(_lusd(), _eth(), 4_000f64);
+        let fill = Fill::new(0.0005f64, 4_000f64, 0f64, 100).unwrap();
+        let cost = model.fill_cost(&order, &fill, &_lusd());
+        assert_eq!(cost, ExecutionCost::new(12f64, 1f64, 0f64));
+        assert_eq!(cost.fill_fee(&order, &fill, &_lusd()), 13f64 * 0.0005f64);
+    }
+
+    #[test]
+    fn execution_cost_add() {
+        let mut cost = ExecutionCost::default();
+        cost.add(&ExecutionCost::new(1f64, 2f64, 3f64));
+        cost.add(&ExecutionCost::new(1f64, 2f64, 3f64));
+        assert_eq!(cost, ExecutionCost::new(2f64, 4f64, 6f64));
+        assert_eq!(cost.total(), 12f64);
+    }
+}
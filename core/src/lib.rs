@@ -1,7 +1,19 @@
 pub mod asset;
+pub mod asset_registry;
+pub mod execution;
+pub mod execution_cost;
 pub mod market;
 pub mod mul_div;
 pub mod order;
+pub mod order_lifecycle;
+pub mod order_netting;
+pub mod pair;
+pub mod performance;
+pub mod price_graph;
+pub mod price_impact;
+pub mod tax_lot;
+pub mod value_at_risk;
+pub mod wire;
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
@@ -1,7 +1,9 @@
 use ethers::types::U64;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "TickRaw")]
 pub struct Tick {
     pub price: U64,
     pub time: u64,
@@ -11,6 +13,17 @@ pub struct Tick {
     pub variance: Option<U64>,
 }
 
+/// Tick as deserialized, checked by Tick::new.
+#[derive(Deserialize)]
+struct TickRaw {
+    price: U64,
+    time: u64,
+    volume: U64,
+    is_up: bool,
+    moving_average: Option<U64>,
+    variance: Option<U64>,
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum TickError {
     #[error("Price should be greater than 0({0})")]
@@ -43,6 +56,37 @@ impl Tick {
             variance,
         })
     }
+
+    /// Price as f64, shifted by the decimals of the quote asset.
+    pub fn price_as_f64(&self, decimal_shift: usize) -> f64 {
+        self.price.as_u64() as f64 / 10f64.powi(decimal_shift as i32)
+    }
+
+    /// Standard deviation of the returns by tick. The variance is the one of the price levels
+    /// over the window_tick_count ticks of the moving average, converted as for a random walk:
+    /// sqrt(variance * 6n / (n² - 1)) / moving average. None without moving average or variance.
+    pub fn return_volatility(&self, window_tick_count: usize) -> Option<f64> {
+        let moving_average = self.moving_average?.as_u64() as f64;
+        let variance = self.variance?.as_u64() as f64;
+        let n = window_tick_count as f64;
+        let return_variance = variance * 6f64 * n / (n * n - 1f64);
+        Some(return_variance.sqrt() / moving_average)
+    }
+}
+
+impl TryFrom<TickRaw> for Tick {
+    type Error = TickError;
+
+    fn try_from(raw: TickRaw) -> Result<Self, Self::Error> {
+        Tick::new(
+            raw.price,
+            raw.time,
+            raw.volume,
+            raw.is_up,
+            raw.moving_average,
+            raw.variance,
+        )
+    }
 }
 
 #[derive(Error, Debug, PartialEq)]
@@ -56,8 +100,11 @@ pub enum HlocError {
     },
     #[error("Duration for HLOC should be greater than 0({0})")]
     DurationShouldBeGtZero(u64),
+    #[error("High should be greater or equal to low ({0} < {1})")]
+    HighLtLow(U64, U64),
 }
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "HlocRaw")]
 pub struct Hloc {
     pub high: U64,
     pub low: U64,
@@ -67,6 +114,17 @@ pub struct Hloc {
     pub volume: U64,
 }
 
+/// Hloc as deserialized, checked by Hloc::new.
+#[derive(Deserialize)]
+struct HlocRaw {
+    high: U64,
+    low: U64,
+    open: U64,
+    close: U64,
+    time: u64,
+    volume: U64,
+}
+
 impl Hloc {
     pub fn new(
         high: U64,
@@ -84,6 +142,9 @@ impl Hloc {
                 close,
             });
         }
+        if high < low {
+            return Err(HlocError::HighLtLow(high, low));
+        }
         Ok(Self {
             high,
             low,
@@ -148,6 +209,14 @@ impl Hloc {
     }
 }
 
+impl TryFrom<HlocRaw> for Hloc {
+    type Error = HlocError;
+
+    fn try_from(raw: HlocRaw) -> Result<Self, Self::Error> {
+        Hloc::new(raw.high, raw.low, raw.open, raw.close, raw.time, raw.volume)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -173,6 +242,38 @@ mod tests {
         assert_eq!(tick.variance, Some(U64::from(50) * U64::exp10(6)));
     }
 
+    #[test]
+    fn tick_price_as_f64() {
+        let tick = Tick::new(
+            U64::from(1_250) * U64::exp10(5),
+            100,
+            U64::from(10) * U64::exp10(6),
+            true,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(tick.price_as_f64(6), 125f64);
+    }
+
+    #[test]
+    fn tick_return_volatility() {
+        // 2 ticks moving of 40 have a variance of 400, volatility is 40 / 1_000 = 4%
+        let tick = Tick::new(
+            U64::from(1_000),
+            100,
+            U64::one(),
+            true,
+            Some(U64::from(1_000)),
+            Some(U64::from(400)),
+        )
+        .unwrap();
+        assert!((tick.return_volatility(2).unwrap() - 0.04f64).abs() < 1e-12);
+
+        let tick = Tick::new(U64::from(1_000), 100, U64::one(), true, None, None).unwrap();
+        assert_eq!(tick.return_volatility(2), None);
+    }
+
     #[test]
     fn hloc_new() {
         let hloc = Hloc::new(
@@ -192,4 +293,21 @@ mod tests {
         assert_eq!(hloc.time, 500);
         assert_eq!(hloc.volume, U64::from(12) * U64::exp10(6));
     }
+
+    #[test]
+    fn hloc_new_high_lt_low() {
+        let hloc = Hloc::new(
+            U64::from(900),
+            U64::from(1_100),
+            U64::from(1_000),
+            U64::from(1_000),
+            500,
+            U64::one(),
+        );
+        assert!(hloc.is_err());
+        assert_eq!(
+            hloc.unwrap_err(),
+            HlocError::HighLtLow(U64::from(900), U64::from(1_100))
+        );
+    }
 }
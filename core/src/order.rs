@@ -1,37 +1,618 @@
 use crate::asset::Asset;
+use crate::market::Tick;
 use ethers::types::U64;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OrderError {
+    #[error("Quantity sell should be greater than 0({0})")]
+    QuantitySellShouldBeGtZero(f64),
+    #[error("Quantity sell should be finite({0})")]
+    QuantitySellNotFinite(f64),
+    #[error("Asset sell and asset buy should be different({0})")]
+    SameAssetSellAndBuy(String),
+    #[error("Trigger price should be greater than 0({0})")]
+    TriggerPriceShouldBeGtZero(U64),
+    #[error("Trail distance should be greater than 0({0})")]
+    TrailDistanceShouldBeGtZero(U64),
+    #[error("Order side {0:?} doesn't match the market order direction on {1}")]
+    SideIncorrect(OrderSide, String),
+}
 
 /// Sell a given quantity of asset A for the best price available of asset B.
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct MarketOrder {
     pub asset_sell: Asset,
     pub asset_buy: Asset,
-    pub quantity_sell: U64,
+    pub quantity_sell: f64,
+}
+
+/// Side of the order for the asset priced by the ticks.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Serialize, Deserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// Send the market order once the price is at least as good as limit_price,
+/// lower or equal to buy, greater or equal to sell.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "LimitOrderRaw")]
+pub struct LimitOrder {
+    pub market_order: MarketOrder,
+    pub priced_asset: Asset,
+    pub side: OrderSide,
+    pub limit_price: U64,
+}
+
+/// Send the market order once the price move against the position up to stop_price,
+/// lower or equal to sell, greater or equal to buy.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "StopLossOrderRaw")]
+pub struct StopLossOrder {
+    pub market_order: MarketOrder,
+    pub priced_asset: Asset,
+    pub side: OrderSide,
+    pub stop_price: U64,
+}
+
+/// Send the market order once the price move in favor of the position up to take_profit_price,
+/// greater or equal to sell, lower or equal to buy.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "TakeProfitOrderRaw")]
+pub struct TakeProfitOrder {
+    pub market_order: MarketOrder,
+    pub priced_asset: Asset,
+    pub side: OrderSide,
+    pub take_profit_price: U64,
+}
+
+/// Stop loss following the best price seen, highest to sell and lowest to buy,
+/// sent when the price move back by trail_distance from it.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "TrailingStopOrderRaw")]
+pub struct TrailingStopOrder {
+    pub market_order: MarketOrder,
+    pub priced_asset: Asset,
+    pub side: OrderSide,
+    pub trail_distance: U64,
+    pub best_price: Option<U64>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Order {
+    Market(MarketOrder),
+    Limit(LimitOrder),
+    StopLoss(StopLossOrder),
+    TakeProfit(TakeProfitOrder),
+    TrailingStop(TrailingStopOrder),
+}
+
+/// LimitOrder as deserialized, checked by LimitOrder::new.
+#[derive(Deserialize)]
+struct LimitOrderRaw {
+    market_order: MarketOrder,
+    priced_asset: Asset,
+    side: OrderSide,
+    limit_price: U64,
+}
+
+/// StopLossOrder as deserialized, checked by StopLossOrder::new.
+#[derive(Deserialize)]
+struct StopLossOrderRaw {
+    market_order: MarketOrder,
+    priced_asset: Asset,
+    side: OrderSide,
+    stop_price: U64,
+}
+
+/// TakeProfitOrder as deserialized, checked by TakeProfitOrder::new.
+#[derive(Deserialize)]
+struct TakeProfitOrderRaw {
+    market_order: MarketOrder,
+    priced_asset: Asset,
+    side: OrderSide,
+    take_profit_price: U64,
+}
+
+/// TrailingStopOrder as deserialized, checked by TrailingStopOrder::new.
+#[derive(Deserialize)]
+struct TrailingStopOrderRaw {
+    market_order: MarketOrder,
+    priced_asset: Asset,
+    side: OrderSide,
+    trail_distance: U64,
+    best_price: Option<U64>,
 }
 
 impl MarketOrder {
-    pub fn new(asset_sell: Asset, asset_buy: Asset, quantity_sell: U64) -> Self {
+    pub fn new(asset_sell: Asset, asset_buy: Asset, quantity_sell: f64) -> Self {
         Self {
             asset_sell,
             asset_buy,
             quantity_sell,
         }
     }
+
+    pub fn validate(&self) -> Result<(), OrderError> {
+        if !self.quantity_sell.is_finite() {
+            return Err(OrderError::QuantitySellNotFinite(self.quantity_sell));
+        }
+        if self.quantity_sell <= 0f64 {
+            return Err(OrderError::QuantitySellShouldBeGtZero(self.quantity_sell));
+        }
+        if self.asset_sell == self.asset_buy {
+            return Err(OrderError::SameAssetSellAndBuy(self.asset_sell.id.clone()));
+        }
+        Ok(())
+    }
+}
+
+impl OrderSide {
+    /// Buy when the market order buys priced_asset, sell when it sells it.
+    pub fn of(market_order: &MarketOrder, priced_asset: &Asset) -> Option<OrderSide> {
+        match priced_asset {
+            i if i == &market_order.asset_buy => Some(OrderSide::Buy),
+            i if i == &market_order.asset_sell => Some(OrderSide::Sell),
+            _ => None,
+        }
+    }
+}
+
+/// The market order of a trigger order should be valid and its direction
+/// on the priced asset should match the side.
+fn _validate_side(
+    market_order: &MarketOrder,
+    priced_asset: &Asset,
+    side: &OrderSide,
+) -> Result<(), OrderError> {
+    market_order.validate()?;
+    if OrderSide::of(market_order, priced_asset).as_ref() != Some(side) {
+        return Err(OrderError::SideIncorrect(
+            side.clone(),
+            priced_asset.id.clone(),
+        ));
+    }
+    Ok(())
+}
+
+impl LimitOrder {
+    pub fn new(
+        market_order: MarketOrder,
+        priced_asset: Asset,
+        side: OrderSide,
+        limit_price: U64,
+    ) -> Result<Self, OrderError> {
+        let order = Self {
+            market_order,
+            priced_asset,
+            side,
+            limit_price,
+        };
+        order.validate()?;
+        Ok(order)
+    }
+
+    pub fn validate(&self) -> Result<(), OrderError> {
+        _validate_side(&self.market_order, &self.priced_asset, &self.side)?;
+        if self.limit_price.is_zero() {
+            return Err(OrderError::TriggerPriceShouldBeGtZero(self.limit_price));
+        }
+        Ok(())
+    }
+
+    /// price is the price of priced_asset in the other asset of the market order.
+    pub fn is_triggered(&self, price: U64) -> bool {
+        match self.side {
+            OrderSide::Buy => price <= self.limit_price,
+            OrderSide::Sell => price >= self.limit_price,
+        }
+    }
+}
+
+impl StopLossOrder {
+    pub fn new(
+        market_order: MarketOrder,
+        priced_asset: Asset,
+        side: OrderSide,
+        stop_price: U64,
+    ) -> Result<Self, OrderError> {
+        let order = Self {
+            market_order,
+            priced_asset,
+            side,
+            stop_price,
+        };
+        order.validate()?;
+        Ok(order)
+    }
+
+    pub fn validate(&self) -> Result<(), OrderError> {
+        _validate_side(&self.market_order, &self.priced_asset, &self.side)?;
+        if self.stop_price.is_zero() {
+            return Err(OrderError::TriggerPriceShouldBeGtZero(self.stop_price));
+        }
+        Ok(())
+    }
+
+    /// price is the price of priced_asset in the other asset of the market order.
+    pub fn is_triggered(&self, price: U64) -> bool {
+        match self.side {
+            OrderSide::Buy => price >= self.stop_price,
+            OrderSide::Sell => price <= self.stop_price,
+        }
+    }
+}
+
+impl TakeProfitOrder {
+    pub fn new(
+        market_order: MarketOrder,
+        priced_asset: Asset,
+        side: OrderSide,
+        take_profit_price: U64,
+    ) -> Result<Self, OrderError> {
+        let order = Self {
+            market_order,
+            priced_asset,
+            side,
+            take_profit_price,
+        };
+        order.validate()?;
+        Ok(order)
+    }
+
+    pub fn validate(&self) -> Result<(), OrderError> {
+        _validate_side(&self.market_order, &self.priced_asset, &self.side)?;
+        if self.take_profit_price.is_zero() {
+            return Err(OrderError::TriggerPriceShouldBeGtZero(
+                self.take_profit_price,
+            ));
+        }
+        Ok(())
+    }
+
+    /// price is the price of priced_asset in the other asset of the market order.
+    pub fn is_triggered(&self, price: U64) -> bool {
+        match self.side {
+            OrderSide::Buy => price <= self.take_profit_price,
+            OrderSide::Sell => price >= self.take_profit_price,
+        }
+    }
+}
+
+impl TrailingStopOrder {
+    pub fn new(
+        market_order: MarketOrder,
+        priced_asset: Asset,
+        side: OrderSide,
+        trail_distance: U64,
+    ) -> Result<Self, OrderError> {
+        let order = Self {
+            market_order,
+            priced_asset,
+            side,
+            trail_distance,
+            best_price: None,
+        };
+        order.validate()?;
+        Ok(order)
+    }
+
+    pub fn validate(&self) -> Result<(), OrderError> {
+        _validate_side(&self.market_order, &self.priced_asset, &self.side)?;
+        if self.trail_distance.is_zero() {
+            return Err(OrderError::TrailDistanceShouldBeGtZero(self.trail_distance));
+        }
+        Ok(())
+    }
+
+    /// Follow the best price then check if the price moved back by the trail distance,
+    /// price is the price of priced_asset in the other asset of the market order.
+    pub fn update(&mut self, price: U64) -> bool {
+        let best_price = match (&self.side, self.best_price) {
+            (_, None) => price,
+            (OrderSide::Buy, Some(best_price)) => best_price.min(price),
+            (OrderSide::Sell, Some(best_price)) => best_price.max(price),
+        };
+        self.best_price = Some(best_price);
+
+        match self.side {
+            OrderSide::Buy => price >= best_price.saturating_add(self.trail_distance),
+            OrderSide::Sell => price.saturating_add(self.trail_distance) <= best_price,
+        }
+    }
+}
+
+impl TryFrom<LimitOrderRaw> for LimitOrder {
+    type Error = OrderError;
+
+    fn try_from(raw: LimitOrderRaw) -> Result<Self, Self::Error> {
+        LimitOrder::new(
+            raw.market_order,
+            raw.priced_asset,
+            raw.side,
+            raw.limit_price,
+        )
+    }
+}
+
+impl TryFrom<StopLossOrderRaw> for StopLossOrder {
+    type Error = OrderError;
+
+    fn try_from(raw: StopLossOrderRaw) -> Result<Self, Self::Error> {
+        StopLossOrder::new(raw.market_order, raw.priced_asset, raw.side, raw.stop_price)
+    }
+}
+
+impl TryFrom<TakeProfitOrderRaw> for TakeProfitOrder {
+    type Error = OrderError;
+
+    fn try_from(raw: TakeProfitOrderRaw) -> Result<Self, Self::Error> {
+        TakeProfitOrder::new(
+            raw.market_order,
+            raw.priced_asset,
+            raw.side,
+            raw.take_profit_price,
+        )
+    }
+}
+
+impl TryFrom<TrailingStopOrderRaw> for TrailingStopOrder {
+    type Error = OrderError;
+
+    fn try_from(raw: TrailingStopOrderRaw) -> Result<Self, Self::Error> {
+        let mut order = TrailingStopOrder::new(
+            raw.market_order,
+            raw.priced_asset,
+            raw.side,
+            raw.trail_distance,
+        )?;
+        order.best_price = raw.best_price;
+        Ok(order)
+    }
+}
+
+impl Order {
+    pub fn market_order(&self) -> &MarketOrder {
+        match self {
+            Order::Market(order) => order,
+            Order::Limit(order) => &order.market_order,
+            Order::StopLoss(order) => &order.market_order,
+            Order::TakeProfit(order) => &order.market_order,
+            Order::TrailingStop(order) => &order.market_order,
+        }
+    }
+
+    /// The market order to send if the tick trigger the order, a market order is always triggered.
+    /// The tick carries no pair, it should be a price of the priced asset of the order
+    /// in the other asset of its market order, which the caller has to ensure.
+    pub fn check_trigger(&mut self, tick: &Tick) -> Option<MarketOrder> {
+        let is_triggered = match self {
+            Order::Market(_) => true,
+            Order::Limit(order) => order.is_triggered(tick.price),
+            Order::StopLoss(order) => order.is_triggered(tick.price),
+            Order::TakeProfit(order) => order.is_triggered(tick.price),
+            Order::TrailingStop(order) => order.update(tick.price),
+        };
+        if !is_triggered {
+            return None;
+        }
+        Some(self.market_order().clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn _market_order_new() -> MarketOrder {
+        MarketOrder::new(
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+            2f64,
+        )
+    }
+
+    fn _market_order_buy_new() -> MarketOrder {
+        MarketOrder::new(
+            Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            4_000f64,
+        )
+    }
+
+    fn _priced_asset() -> Asset {
+        Asset::new(String::from("ETH"), String::from("Ether"))
+    }
+
+    fn _tick_new(price: u64) -> Tick {
+        Tick::new(U64::from(price), 0, U64::one(), true, None, None).unwrap()
+    }
+
     #[test]
     fn market_order_new() {
         let order = MarketOrder::new(
             Asset::new(String::from("LUSD"), String::from("Liquity USD")),
             Asset::new(String::from("ETH"), String::from("Ether")),
-            U64::from(1_900) * U64::exp10(6),
+            1_900f64,
         );
         assert_eq!(order.asset_sell.id, "LUSD");
         assert_eq!(order.asset_buy.id, "ETH");
-        assert_eq!(order.quantity_sell, U64::from(1_900) * U64::exp10(6));
+        assert_eq!(order.quantity_sell, 1_900f64);
+    }
+
+    #[test]
+    fn market_order_validate() {
+        let mut order = _market_order_new();
+        assert!(order.validate().is_ok());
+
+        order.quantity_sell = 0f64;
+        assert_eq!(
+            order.validate().unwrap_err(),
+            OrderError::QuantitySellShouldBeGtZero(0f64)
+        );
+
+        order.quantity_sell = f64::NAN;
+        assert!(matches!(
+            order.validate().unwrap_err(),
+            OrderError::QuantitySellNotFinite(_)
+        ));
+        order.quantity_sell = f64::INFINITY;
+        assert_eq!(
+            order.validate().unwrap_err(),
+            OrderError::QuantitySellNotFinite(f64::INFINITY)
+        );
+
+        order.quantity_sell = 2f64;
+        order.asset_buy = order.asset_sell.clone();
+        assert_eq!(
+            order.validate().unwrap_err(),
+            OrderError::SameAssetSellAndBuy(String::from("ETH"))
+        );
+    }
+
+    #[test]
+    fn limit_order_new_incorrect() {
+        let order = LimitOrder::new(
+            _market_order_new(),
+            _priced_asset(),
+            OrderSide::Sell,
+            U64::zero(),
+        );
+        assert!(order.is_err());
+        assert_eq!(
+            order.unwrap_err(),
+            OrderError::TriggerPriceShouldBeGtZero(U64::zero())
+        );
+    }
+
+    #[test]
+    fn limit_order_new_side_incorrect() {
+        let order = LimitOrder::new(
+            _market_order_new(),
+            _priced_asset(),
+            OrderSide::Buy,
+            U64::from(2_000),
+        );
+        assert_eq!(
+            order.unwrap_err(),
+            OrderError::SideIncorrect(OrderSide::Buy, String::from("ETH"))
+        );
+
+        let order = StopLossOrder::new(
+            _market_order_new(),
+            Asset::new(String::from("BTC"), String::from("Bitcoin")),
+            OrderSide::Sell,
+            U64::from(1_800),
+        );
+        assert_eq!(
+            order.unwrap_err(),
+            OrderError::SideIncorrect(OrderSide::Sell, String::from("BTC"))
+        );
+    }
+
+    #[test]
+    fn limit_order_check_trigger() {
+        let mut order = Order::Limit(
+            LimitOrder::new(
+                _market_order_new(),
+                _priced_asset(),
+                OrderSide::Sell,
+                U64::from(2_000),
+            )
+            .unwrap(),
+        );
+        assert!(order.check_trigger(&_tick_new(1_999)).is_none());
+        let market_order = order.check_trigger(&_tick_new(2_000));
+        assert!(market_order.is_some());
+        assert_eq!(market_order.unwrap(), _market_order_new());
+
+        let order = LimitOrder::new(
+            _market_order_buy_new(),
+            _priced_asset(),
+            OrderSide::Buy,
+            U64::from(2_000),
+        )
+        .unwrap();
+        assert!(order.is_triggered(U64::from(1_999)));
+        assert!(!order.is_triggered(U64::from(2_001)));
+    }
+
+    #[test]
+    fn stop_loss_order_check_trigger() {
+        let mut order = Order::StopLoss(
+            StopLossOrder::new(
+                _market_order_new(),
+                _priced_asset(),
+                OrderSide::Sell,
+                U64::from(1_800),
+            )
+            .unwrap(),
+        );
+        assert!(order.check_trigger(&_tick_new(1_900)).is_none());
+        assert!(order.check_trigger(&_tick_new(1_750)).is_some());
+    }
+
+    #[test]
+    fn take_profit_order_check_trigger() {
+        let mut order = Order::TakeProfit(
+            TakeProfitOrder::new(
+                _market_order_new(),
+                _priced_asset(),
+                OrderSide::Sell,
+                U64::from(2_500),
+            )
+            .unwrap(),
+        );
+        assert!(order.check_trigger(&_tick_new(2_400)).is_none());
+        assert!(order.check_trigger(&_tick_new(2_600)).is_some());
+    }
+
+    #[test]
+    fn trailing_stop_order_check_trigger() {
+        let mut order = Order::TrailingStop(
+            TrailingStopOrder::new(
+                _market_order_new(),
+                _priced_asset(),
+                OrderSide::Sell,
+                U64::from(100),
+            )
+            .unwrap(),
+        );
+        assert!(order.check_trigger(&_tick_new(2_000)).is_none());
+        assert!(order.check_trigger(&_tick_new(2_300)).is_none());
+        assert!(order.check_trigger(&_tick_new(2_201)).is_none());
+        assert!(order.check_trigger(&_tick_new(2_200)).is_some());
+
+        let mut order = TrailingStopOrder::new(
+            _market_order_buy_new(),
+            _priced_asset(),
+            OrderSide::Buy,
+            U64::from(100),
+        )
+        .unwrap();
+        assert!(!order.update(U64::from(2_000)));
+        assert!(!order.update(U64::from(1_800)));
+        assert!(order.update(U64::from(1_900)));
+        assert_eq!(order.best_price, Some(U64::from(1_800)));
+
+        // trail distance over the best price doesn't overflow
+        let mut order = TrailingStopOrder::new(
+            _market_order_buy_new(),
+            _priced_asset(),
+            OrderSide::Buy,
+            U64::MAX,
+        )
+        .unwrap();
+        assert!(!order.update(U64::from(2_000)));
+        let mut order = TrailingStopOrder::new(
+            _market_order_new(),
+            _priced_asset(),
+            OrderSide::Sell,
+            U64::MAX,
+        )
+        .unwrap();
+        assert!(!order.update(U64::from(2_000)));
     }
 }
@@ -0,0 +1,436 @@
+use crate::order::Order;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Relative float error tolerated between the fills and the order quantity.
+const QUANTITY_TOLERANCE: f64 = 1e-9;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OrderLifecycleError {
+    #[error("Order can't go from {0:?} to {1:?}")]
+    TransitionIncorrect(OrderStatus, OrderStatus),
+    #[error("Fill quantity should be greater than 0({0})")]
+    FillQuantityShouldBeGtZero(f64),
+    #[error("Fill price should be greater than 0({0})")]
+    FillPriceShouldBeGtZero(f64),
+    #[error("Fill fee can't be negative({0})")]
+    FillFeeIsNegative(f64),
+    #[error("Fill quantity is greater than the quantity left ({0} > {1})")]
+    FillQuantityGtQuantityLeft(f64, f64),
+    #[error("Order status {0:?} doesn't match the filled quantity ({1} of {2})")]
+    StatusFilledQuantityIncorrect(OrderStatus, f64, f64),
+    #[error("Updated time is before the created time ({0} < {1})")]
+    UpdatedTimeLtCreatedTime(u64, u64),
+    #[error("Time is before the last update of the order ({0} < {1})")]
+    TimeLtUpdatedTime(u64, u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderStatus {
+    Created,
+    Submitted,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected(String),
+    Expired,
+}
+
+/// Execution of a part of an order, quantity is sold at price (asset buy by asset sell),
+/// fee is paid in asset buy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "FillRaw")]
+pub struct Fill {
+    pub price: f64,
+    pub quantity: f64,
+    pub fee: f64,
+    pub time: u64,
+}
+
+/// Fill as deserialized, checked by Fill::new.
+#[derive(Deserialize)]
+struct FillRaw {
+    price: f64,
+    quantity: f64,
+    fee: f64,
+    time: u64,
+}
+
+/// An order with its id, status and fills, the same for backtests and on-chain executions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TrackedOrderRaw")]
+pub struct TrackedOrder {
+    pub id: u64,
+    pub created_time: u64,
+    pub updated_time: u64,
+    pub order: Order,
+    pub status: OrderStatus,
+    pub fills: Vec<Fill>,
+}
+
+/// TrackedOrder as deserialized, its status checked against its fills.
+#[derive(Deserialize)]
+struct TrackedOrderRaw {
+    id: u64,
+    created_time: u64,
+    updated_time: u64,
+    order: Order,
+    status: OrderStatus,
+    fills: Vec<Fill>,
+}
+
+impl Fill {
+    pub fn new(
+        price: f64,
+        quantity: f64,
+        fee: f64,
+        time: u64,
+    ) -> Result<Self, OrderLifecycleError> {
+        if price <= 0f64 {
+            return Err(OrderLifecycleError::FillPriceShouldBeGtZero(price));
+        }
+        if quantity <= 0f64 {
+            return Err(OrderLifecycleError::FillQuantityShouldBeGtZero(quantity));
+        }
+        if fee < 0f64 {
+            return Err(OrderLifecycleError::FillFeeIsNegative(fee));
+        }
+        Ok(Self {
+            price,
+            quantity,
+            fee,
+            time,
+        })
+    }
+
+    /// Quantity of asset buy received once the fee is paid.
+    pub fn quantity_buy(&self) -> f64 {
+        self.quantity * self.price - self.fee
+    }
+}
+
+impl TryFrom<FillRaw> for Fill {
+    type Error = OrderLifecycleError;
+
+    fn try_from(raw: FillRaw) -> Result<Self, Self::Error> {
+        Fill::new(raw.price, raw.quantity, raw.fee, raw.time)
+    }
+}
+
+impl OrderStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled
+                | OrderStatus::Cancelled
+                | OrderStatus::Rejected(_)
+                | OrderStatus::Expired
+        )
+    }
+}
+
+impl TrackedOrder {
+    pub fn new(id: u64, created_time: u64, order: Order) -> Self {
+        Self {
+            id,
+            created_time,
+            updated_time: created_time,
+            order,
+            status: OrderStatus::Created,
+            fills: Vec::new(),
+        }
+    }
+
+    pub fn filled_quantity(&self) -> f64 {
+        self.fills.iter().map(|fill| fill.quantity).sum()
+    }
+
+    pub fn quantity_left(&self) -> f64 {
+        if self.status == OrderStatus::Filled {
+            return 0f64;
+        }
+        (self.order.market_order().quantity_sell - self.filled_quantity()).max(0f64)
+    }
+
+    /// Average price of the fills weighted by their quantity.
+    pub fn average_price(&self) -> Option<f64> {
+        let filled_quantity = self.filled_quantity();
+        if filled_quantity == 0f64 {
+            return None;
+        }
+        let value: f64 = self
+            .fills
+            .iter()
+            .map(|fill| fill.price * fill.quantity)
+            .sum();
+        Some(value / filled_quantity)
+    }
+
+    pub fn fee(&self) -> f64 {
+        self.fills.iter().map(|fill| fill.fee).sum()
+    }
+
+    pub fn submit(&mut self, time: u64) -> Result<(), OrderLifecycleError> {
+        self.transition(OrderStatus::Submitted, time)
+    }
+
+    /// A fill within the float tolerance of the quantity left fills the order,
+    /// its quantity is then clamped to the quantity left.
+    pub fn fill(&mut self, mut fill: Fill) -> Result<(), OrderLifecycleError> {
+        let quantity_left = self.quantity_left();
+        let tolerance = self.order.market_order().quantity_sell * QUANTITY_TOLERANCE;
+        if fill.quantity > quantity_left + tolerance {
+            return Err(OrderLifecycleError::FillQuantityGtQuantityLeft(
+                fill.quantity,
+                quantity_left,
+            ));
+        }
+
+        let status = if (fill.quantity - quantity_left).abs() <= tolerance {
+            fill.quantity = fill.quantity.min(quantity_left);
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+        self.transition(status, fill.time)?;
+        self.fills.push(fill);
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, time: u64) -> Result<(), OrderLifecycleError> {
+        self.transition(OrderStatus::Cancelled, time)
+    }
+
+    pub fn reject(&mut self, reason: String, time: u64) -> Result<(), OrderLifecycleError> {
+        self.transition(OrderStatus::Rejected(reason), time)
+    }
+
+    pub fn expire(&mut self, time: u64) -> Result<(), OrderLifecycleError> {
+        self.transition(OrderStatus::Expired, time)
+    }
+
+    fn transition(&mut self, status: OrderStatus, time: u64) -> Result<(), OrderLifecycleError> {
+        if time < self.updated_time {
+            return Err(OrderLifecycleError::TimeLtUpdatedTime(
+                time,
+                self.updated_time,
+            ));
+        }
+        let is_allowed = matches!(
+            (&self.status, &status),
+            (
+                OrderStatus::Created,
+                OrderStatus::Submitted
+                    | OrderStatus::Cancelled
+                    | OrderStatus::Rejected(_)
+                    | OrderStatus::Expired,
+            ) | (OrderStatus::Submitted, OrderStatus::Rejected(_))
+                | (
+                    OrderStatus::Submitted | OrderStatus::PartiallyFilled,
+                    OrderStatus::PartiallyFilled
+                        | OrderStatus::Filled
+                        | OrderStatus::Cancelled
+                        | OrderStatus::Expired,
+                )
+        );
+        if !is_allowed {
+            return Err(OrderLifecycleError::TransitionIncorrect(
+                self.status.clone(),
+                status,
+            ));
+        }
+
+        self.status = status;
+        self.updated_time = time;
+        Ok(())
+    }
+}
+
+impl TryFrom<TrackedOrderRaw> for TrackedOrder {
+    type Error = OrderLifecycleError;
+
+    /// Orders not sent yet or rejected have no fill, a partially filled order has fills
+    /// under its quantity and a filled one fills within the float tolerance of its quantity.
+    fn try_from(raw: TrackedOrderRaw) -> Result<Self, Self::Error> {
+        if raw.updated_time < raw.created_time {
+            return Err(OrderLifecycleError::UpdatedTimeLtCreatedTime(
+                raw.updated_time,
+                raw.created_time,
+            ));
+        }
+        let quantity = raw.order.market_order().quantity_sell;
+        let tolerance = quantity * QUANTITY_TOLERANCE;
+        let filled_quantity: f64 = raw.fills.iter().map(|fill| fill.quantity).sum();
+        let is_filled = (filled_quantity - quantity).abs() <= tolerance;
+        let is_status_correct = filled_quantity <= quantity + tolerance
+            && match raw.status {
+                OrderStatus::Created | OrderStatus::Submitted | OrderStatus::Rejected(_) => {
+                    raw.fills.is_empty()
+                }
+                OrderStatus::PartiallyFilled => !raw.fills.is_empty() && !is_filled,
+                OrderStatus::Filled => is_filled,
+                OrderStatus::Cancelled | OrderStatus::Expired => !is_filled,
+            };
+        if !is_status_correct {
+            return Err(OrderLifecycleError::StatusFilledQuantityIncorrect(
+                raw.status,
+                filled_quantity,
+                quantity,
+            ));
+        }
+        Ok(Self {
+            id: raw.id,
+            created_time: raw.created_time,
+            updated_time: raw.updated_time,
+            order: raw.order,
+            status: raw.status,
+            fills: raw.fills,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::order::MarketOrder;
+
+    fn _tracked_order_new() -> TrackedOrder {
+        let order = Order::Market(MarketOrder::new(
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+            2f64,
+        ));
+        TrackedOrder::new(1, 100, order)
+    }
+
+    #[test]
+    fn tracked_order_new() {
+        let order = _tracked_order_new();
+        assert_eq!(order.id, 1);
+        assert_eq!(order.status, OrderStatus::Created);
+        assert_eq!(order.quantity_left(), 2f64);
+        assert!(order.average_price().is_none());
+    }
+
+    #[test]
+    fn fill_new_incorrect() {
+        let fill = Fill::new(0f64, 1f64, 0f64, 100);
+        assert!(fill.is_err());
+        assert_eq!(
+            fill.unwrap_err(),
+            OrderLifecycleError::FillPriceShouldBeGtZero(0f64)
+        );
+
+        let fill = Fill::new(2_000f64, 1f64, -1f64, 100);
+        assert!(fill.is_err());
+        assert_eq!(
+            fill.unwrap_err(),
+            OrderLifecycleError::FillFeeIsNegative(-1f64)
+        );
+    }
+
+    #[test]
+    fn tracked_order_fill() {
+        let mut order = _tracked_order_new();
+        assert!(order.submit(110).is_ok());
+
+        let result = order.fill(Fill::new(2_000f64, 0.5f64, 1f64, 120).unwrap());
+        assert!(result.is_ok());
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert_eq!(order.quantity_left(), 1.5f64);
+
+        let result = order.fill(Fill::new(1_900f64, 2f64, 1f64, 130).unwrap());
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            OrderLifecycleError::FillQuantityGtQuantityLeft(2f64, 1.5f64)
+        );
+
+        let result = order.fill(Fill::new(1_900f64, 1.5f64, 3f64, 140).unwrap());
+        assert!(result.is_ok());
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.updated_time, 140);
+        assert_eq!(order.average_price(), Some(1_925f64));
+        assert_eq!(order.fee(), 4f64);
+        assert!(order.status.is_terminal());
+    }
+
+    #[test]
+    fn tracked_order_fill_float_error() {
+        let mut order = TrackedOrder::new(
+            1,
+            100,
+            Order::Market(MarketOrder::new(
+                Asset::new(String::from("ETH"), String::from("Ether")),
+                Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+                0.3f64,
+            )),
+        );
+        order.submit(110).unwrap();
+        order
+            .fill(Fill::new(2_000f64, 0.1f64, 0f64, 120).unwrap())
+            .unwrap();
+        let result = order.fill(Fill::new(2_000f64, 0.2f64, 0f64, 130).unwrap());
+        assert!(result.is_ok());
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.quantity_left(), 0f64);
+        assert!(order.fills[1].quantity <= 0.2f64);
+    }
+
+    #[test]
+    fn tracked_order_transition_incorrect() {
+        let mut order = _tracked_order_new();
+        let result = order.fill(Fill::new(2_000f64, 1f64, 0f64, 120).unwrap());
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            OrderLifecycleError::TransitionIncorrect(
+                OrderStatus::Created,
+                OrderStatus::PartiallyFilled
+            )
+        );
+
+        assert!(order.cancel(130).is_ok());
+        assert_eq!(order.status, OrderStatus::Cancelled);
+        assert!(order.submit(140).is_err());
+    }
+
+    #[test]
+    fn tracked_order_reject_expire() {
+        let mut order = _tracked_order_new();
+        order.submit(110).unwrap();
+        assert!(order.reject(String::from("Slippage too high"), 120).is_ok());
+        assert_eq!(
+            order.status,
+            OrderStatus::Rejected(String::from("Slippage too high"))
+        );
+
+        let mut order = _tracked_order_new();
+        order.submit(110).unwrap();
+        order
+            .fill(Fill::new(2_000f64, 1f64, 0f64, 120).unwrap())
+            .unwrap();
+        assert!(order.reject(String::from("Too late"), 130).is_err());
+        assert!(order.expire(130).is_ok());
+        assert_eq!(order.status, OrderStatus::Expired);
+
+        let mut order = _tracked_order_new();
+        assert!(order.expire(110).is_ok());
+        assert_eq!(order.status, OrderStatus::Expired);
+    }
+
+    #[test]
+    fn tracked_order_time_lt_updated_time() {
+        let mut order = _tracked_order_new();
+        order.submit(110).unwrap();
+        let result = order.fill(Fill::new(2_000f64, 1f64, 0f64, 105).unwrap());
+        assert_eq!(
+            result.unwrap_err(),
+            OrderLifecycleError::TimeLtUpdatedTime(105, 110)
+        );
+        assert_eq!(order.status, OrderStatus::Submitted);
+        assert!(order.fills.is_empty());
+        assert!(order.cancel(110).is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::asset::Asset;
+use crate::order::MarketOrder;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Relative float error under which opposing orders are crossed.
+const QUANTITY_TOLERANCE: f64 = 1e-9;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OrderNettingError {
+    #[error("Need a reference price to net opposing orders of {0}/{1}")]
+    ReferencePriceNone(String, String),
+    #[error("Reference price should be greater than 0({0})")]
+    ReferencePriceShouldBeGtZero(f64),
+    #[error("Quantity sell should be finite and can't be negative({0})")]
+    QuantitySellIncorrect(f64),
+}
+
+/// Price of the base asset in quote asset, used to net opposing orders on the pair.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ReferencePriceRaw")]
+pub struct ReferencePrice {
+    pub base: Asset,
+    pub quote: Asset,
+    pub price: f64,
+}
+
+/// ReferencePrice as deserialized, checked by ReferencePrice::new.
+#[derive(Deserialize)]
+struct ReferencePriceRaw {
+    base: Asset,
+    quote: Asset,
+    price: f64,
+}
+
+/// Minimal orders to execute, origins[i] is the index of the orders netted in orders[i],
+/// crossed is the index of the orders netted to nothing, against each other or of zero quantity.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct OrderBatch {
+    pub orders: Vec<MarketOrder>,
+    pub origins: Vec<Vec<usize>>,
+    pub crossed: Vec<usize>,
+}
+
+impl ReferencePrice {
+    pub fn new(base: Asset, quote: Asset, price: f64) -> Result<Self, OrderNettingError> {
+        if price <= 0f64 {
+            return Err(OrderNettingError::ReferencePriceShouldBeGtZero(price));
+        }
+        Ok(Self { base, quote, price })
+    }
+}
+
+impl TryFrom<ReferencePriceRaw> for ReferencePrice {
+    type Error = OrderNettingError;
+
+    fn try_from(raw: ReferencePriceRaw) -> Result<Self, Self::Error> {
+        ReferencePrice::new(raw.base, raw.quote, raw.price)
+    }
+}
+
+/// Merge same direction orders and net opposing ones by asset pair,
+/// the batch keep the order of first appearance of each pair.
+pub fn net_market_orders(
+    orders: &[MarketOrder],
+    reference_prices: &[ReferencePrice],
+) -> Result<OrderBatch, OrderNettingError> {
+    if let Some(order) = orders
+        .iter()
+        .find(|i| !(i.quantity_sell >= 0f64 && i.quantity_sell.is_finite()))
+    {
+        return Err(OrderNettingError::QuantitySellIncorrect(
+            order.quantity_sell,
+        ));
+    }
+    let mut pairs: Vec<(&Asset, &Asset, Vec<usize>)> = Vec::new();
+    for (i, order) in orders.iter().enumerate() {
+        let pair = pairs.iter_mut().find(|(a, b, _)| {
+            (*a == &order.asset_sell && *b == &order.asset_buy)
+                || (*a == &order.asset_buy && *b == &order.asset_sell)
+        });
+        match pair {
+            Some((_, _, origins)) => origins.push(i),
+            None => pairs.push((&order.asset_sell, &order.asset_buy, vec![i])),
+        }
+    }
+
+    let mut batch = OrderBatch::default();
+    for (asset_a, asset_b, origins) in pairs {
+        let mut a_sell_quantity = 0f64;
+        let mut b_sell_quantity = 0f64;
+        for i in &origins {
+            let order = &orders[*i];
+            if &order.asset_sell == asset_a {
+                a_sell_quantity += order.quantity_sell;
+            } else {
+                b_sell_quantity += order.quantity_sell;
+            }
+        }
+
+        let net_order = match (a_sell_quantity > 0f64, b_sell_quantity > 0f64) {
+            (false, false) => None,
+            (true, false) => Some(MarketOrder::new(
+                asset_a.clone(),
+                asset_b.clone(),
+                a_sell_quantity,
+            )),
+            (false, true) => Some(MarketOrder::new(
+                asset_b.clone(),
+                asset_a.clone(),
+                b_sell_quantity,
+            )),
+            (true, true) => {
+                // Net in asset b, a_price is the price of asset a in asset b
+                let a_price = reference_price(reference_prices, asset_a, asset_b)?;
+                let b_net_quantity = a_sell_quantity * a_price - b_sell_quantity;
+                let tolerance = (a_sell_quantity * a_price + b_sell_quantity) * QUANTITY_TOLERANCE;
+                match b_net_quantity {
+                    i if i > tolerance => Some(MarketOrder::new(
+                        asset_a.clone(),
+                        asset_b.clone(),
+                        i / a_price,
+                    )),
+                    i if i < -tolerance => {
+                        Some(MarketOrder::new(asset_b.clone(), asset_a.clone(), i.abs()))
+                    }
+                    _ => None,
+                }
+            }
+        };
+
+        match net_order {
+            Some(order) => {
+                batch.orders.push(order);
+                batch.origins.push(origins);
+            }
+            None => batch.crossed.extend(origins),
+        }
+    }
+    Ok(batch)
+}
+
+fn reference_price(
+    reference_prices: &[ReferencePrice],
+    base: &Asset,
+    quote: &Asset,
+) -> Result<f64, OrderNettingError> {
+    for reference_price in reference_prices {
+        if &reference_price.base == base && &reference_price.quote == quote {
+            return Ok(reference_price.price);
+        }
+        if &reference_price.base == quote && &reference_price.quote == base {
+            return Ok(1f64 / reference_price.price);
+        }
+    }
+    Err(OrderNettingError::ReferencePriceNone(
+        base.id.clone(),
+        quote.id.clone(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _eth() -> Asset {
+        Asset::new(String::from("ETH"), String::from("Ether"))
+    }
+
+    fn _lusd() -> Asset {
+        Asset::new(String::from("LUSD"), String::from("Liquity USD"))
+    }
+
+    fn _btc() -> Asset {
+        Asset::new(String::from("BTC"), String::from("Bitcoin"))
+    }
+
+    #[test]
+    fn reference_price_new() {
+        let reference_price = ReferencePrice::new(_eth(), _lusd(), 0f64);
+        assert!(reference_price.is_err());
+        assert_eq!(
+            reference_price.unwrap_err(),
+            OrderNettingError::ReferencePriceShouldBeGtZero(0f64)
+        );
+    }
+
+    #[test]
+    fn net_market_orders_merge() {
+        let orders = vec![
+            MarketOrder::new(_lusd(), _eth(), 1_000f64),
+            MarketOrder::new(_lusd(), _btc(), 500f64),
+            MarketOrder::new(_lusd(), _eth(), 2_000f64),
+        ];
+        let batch = net_market_orders(&orders, &[]);
+        assert!(batch.is_ok());
+        let batch = batch.unwrap();
+
+        assert_eq!(
+            batch.orders,
+            vec![
+                MarketOrder::new(_lusd(), _eth(), 3_000f64),
+                MarketOrder::new(_lusd(), _btc(), 500f64),
+            ]
+        );
+        assert_eq!(batch.origins, vec![vec![0, 2], vec![1]]);
+        assert!(batch.crossed.is_empty());
+    }
+
+    #[test]
+    fn net_market_orders_opposing() {
+        let reference_prices = vec![ReferencePrice::new(_eth(), _lusd(), 2_000f64).unwrap()];
+        let orders = vec![
+            MarketOrder::new(_lusd(), _eth(), 3_000f64),
+            MarketOrder::new(_eth(), _lusd(), 1f64),
+        ];
+        let batch = net_market_orders(&orders, &reference_prices);
+        assert!(batch.is_ok());
+        let batch = batch.unwrap();
+        assert_eq!(
+            batch.orders,
+            vec![MarketOrder::new(_lusd(), _eth(), 1_000f64)]
+        );
+        assert_eq!(batch.origins, vec![vec![0, 1]]);
+
+        let orders = vec![
+            MarketOrder::new(_lusd(), _eth(), 1_000f64),
+            MarketOrder::new(_eth(), _lusd(), 2f64),
+        ];
+        let batch = net_market_orders(&orders, &reference_prices).unwrap();
+        assert_eq!(
+            batch.orders,
+            vec![MarketOrder::new(_eth(), _lusd(), 1.5f64)]
+        );
+    }
+
+    #[test]
+    fn net_market_orders_crossed() {
+        let reference_prices = vec![ReferencePrice::new(_eth(), _lusd(), 2_000f64).unwrap()];
+        let orders = vec![
+            MarketOrder::new(_eth(), _lusd(), 1f64),
+            MarketOrder::new(_lusd(), _eth(), 2_000f64),
+        ];
+        let batch = net_market_orders(&orders, &reference_prices);
+        assert!(batch.is_ok());
+        let batch = batch.unwrap();
+        assert!(batch.orders.is_empty());
+        assert_eq!(batch.crossed, vec![0, 1]);
+
+        // 0.1 + 0.2 ETH is not exactly 0.3 ETH
+        let orders = vec![
+            MarketOrder::new(_eth(), _lusd(), 0.1f64),
+            MarketOrder::new(_eth(), _lusd(), 0.2f64),
+            MarketOrder::new(_lusd(), _eth(), 600f64),
+        ];
+        let batch = net_market_orders(&orders, &reference_prices).unwrap();
+        assert!(batch.orders.is_empty());
+        assert_eq!(batch.crossed, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn net_market_orders_zero_quantity() {
+        let orders = vec![
+            MarketOrder::new(_lusd(), _eth(), 0f64),
+            MarketOrder::new(_lusd(), _btc(), 500f64),
+        ];
+        let batch = net_market_orders(&orders, &[]).unwrap();
+        assert_eq!(
+            batch.orders,
+            vec![MarketOrder::new(_lusd(), _btc(), 500f64)]
+        );
+        assert_eq!(batch.origins, vec![vec![1]]);
+        assert_eq!(batch.crossed, vec![0]);
+    }
+
+    #[test]
+    fn net_market_orders_quantity_sell_incorrect() {
+        let orders = vec![MarketOrder::new(_lusd(), _eth(), -1f64)];
+        assert_eq!(
+            net_market_orders(&orders, &[]).unwrap_err(),
+            OrderNettingError::QuantitySellIncorrect(-1f64)
+        );
+
+        let orders = vec![MarketOrder::new(_lusd(), _eth(), f64::NAN)];
+        assert!(matches!(
+            net_market_orders(&orders, &[]).unwrap_err(),
+            OrderNettingError::QuantitySellIncorrect(_)
+        ));
+    }
+
+    #[test]
+    fn net_market_orders_reference_price_none() {
+        let orders = vec![
+            MarketOrder::new(_eth(), _lusd(), 1f64),
+            MarketOrder::new(_lusd(), _eth(), 2_000f64),
+        ];
+        let batch = net_market_orders(&orders, &[]);
+        assert!(batch.is_err());
+        assert_eq!(
+            batch.unwrap_err(),
+            OrderNettingError::ReferencePriceNone(String::from("ETH"), String::from("LUSD"))
+        );
+    }
+}
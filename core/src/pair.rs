@@ -0,0 +1,376 @@
+use crate::asset::Asset;
+use crate::market::{Hloc, HlocError, Tick};
+use ethers::types::U64;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PairError {
+    #[error("Quote asset should not be the base asset {0}")]
+    SameAssetBaseAndQuote(String),
+    #[error("Pair should be {0} but is {1}")]
+    PairIncorrect(String, String),
+    #[error("Price should be greater than 0({0})")]
+    PriceShouldBeGtZero(U64),
+    #[error("Asset should be {0} but is {1}")]
+    AssetIncorrect(String, String),
+}
+
+/// Asset known at compile time, a pair of two markers has its direction in its type.
+pub trait AssetMarker: Clone + PartialEq {
+    /// Id of the asset, None for any asset.
+    const ID: Option<&'static str>;
+}
+
+/// Any asset, the pair direction is only checked at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct AnyAsset;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Eth;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Lusd;
+
+/// Base asset priced in quote asset, ETH/LUSD is the LUSD price of one ETH.
+/// Pair<Eth, Lusd> and Pair<Lusd, Eth> are different types, Pair is any pair.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(
+    try_from = "PairRaw",
+    bound(deserialize = "B: AssetMarker, Q: AssetMarker")
+)]
+pub struct Pair<B = AnyAsset, Q = AnyAsset> {
+    base: Asset,
+    quote: Asset,
+    #[serde(skip)]
+    direction: PhantomData<(B, Q)>,
+}
+
+/// Pair as deserialized, checked by Pair::new and with_direction.
+#[derive(Deserialize)]
+struct PairRaw {
+    base: Asset,
+    quote: Asset,
+}
+
+/// Price of a pair as U64 with decimals, 1_250_000 with 3 decimals is 1_250.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    try_from = "PriceRaw<B, Q>",
+    bound(serialize = "", deserialize = "B: AssetMarker, Q: AssetMarker")
+)]
+pub struct Price<B = AnyAsset, Q = AnyAsset> {
+    pub pair: Pair<B, Q>,
+    pub value: U64,
+    pub decimals: usize,
+}
+
+/// Price as deserialized, checked by Price::new.
+#[derive(Deserialize)]
+#[serde(bound = "B: AssetMarker, Q: AssetMarker")]
+struct PriceRaw<B, Q> {
+    pair: Pair<B, Q>,
+    value: U64,
+    decimals: usize,
+}
+
+/// Ticks of one pair, their price has price_decimals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "", deserialize = "B: AssetMarker, Q: AssetMarker"))]
+pub struct TickSeries<B = AnyAsset, Q = AnyAsset> {
+    pub pair: Pair<B, Q>,
+    pub price_decimals: usize,
+    pub ticks: Vec<Tick>,
+}
+
+/// Hlocs of one pair, their prices have price_decimals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "", deserialize = "B: AssetMarker, Q: AssetMarker"))]
+pub struct HlocSeries<B = AnyAsset, Q = AnyAsset> {
+    pub pair: Pair<B, Q>,
+    pub price_decimals: usize,
+    pub hlocs: Vec<Hloc>,
+}
+
+impl AssetMarker for AnyAsset {
+    const ID: Option<&'static str> = None;
+}
+
+impl AssetMarker for Eth {
+    const ID: Option<&'static str> = Some("ETH");
+}
+
+impl AssetMarker for Lusd {
+    const ID: Option<&'static str> = Some("LUSD");
+}
+
+impl Pair {
+    pub fn new(base: Asset, quote: Asset) -> Result<Self, PairError> {
+        if base == quote {
+            return Err(PairError::SameAssetBaseAndQuote(base.id));
+        }
+        Ok(Self {
+            base,
+            quote,
+            direction: PhantomData,
+        })
+    }
+}
+
+impl<B: AssetMarker, Q: AssetMarker> Pair<B, Q> {
+    pub fn base(&self) -> &Asset {
+        &self.base
+    }
+
+    pub fn quote(&self) -> &Asset {
+        &self.quote
+    }
+
+    /// This pair typed with the B2/Q2 direction, error if the assets are not the markers ones.
+    pub fn with_direction<B2: AssetMarker, Q2: AssetMarker>(
+        &self,
+    ) -> Result<Pair<B2, Q2>, PairError> {
+        for (id, asset) in [(B2::ID, &self.base), (Q2::ID, &self.quote)] {
+            if let Some(id) = id.filter(|i| *i != asset.id) {
+                return Err(PairError::AssetIncorrect(
+                    String::from(id),
+                    asset.id.clone(),
+                ));
+            }
+        }
+        Ok(Pair {
+            base: self.base.clone(),
+            quote: self.quote.clone(),
+            direction: PhantomData,
+        })
+    }
+
+    /// Same pair without its direction type, checked at runtime from there.
+    pub fn untyped(&self) -> Pair {
+        Pair {
+            base: self.base.clone(),
+            quote: self.quote.clone(),
+            direction: PhantomData,
+        }
+    }
+
+    /// ETH/LUSD gives LUSD/ETH.
+    pub fn inverse(&self) -> Pair<Q, B> {
+        Pair {
+            base: self.quote.clone(),
+            quote: self.base.clone(),
+            direction: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.base.id, self.quote.id)
+    }
+
+    /// Error unless pair is this pair, in the same direction.
+    pub fn check(&self, pair: &Pair<B, Q>) -> Result<(), PairError> {
+        if self.base != pair.base || self.quote != pair.quote {
+            return Err(PairError::PairIncorrect(self.name(), pair.name()));
+        }
+        Ok(())
+    }
+}
+
+impl<B: AssetMarker, Q: AssetMarker> TryFrom<PairRaw> for Pair<B, Q> {
+    type Error = PairError;
+
+    fn try_from(raw: PairRaw) -> Result<Self, Self::Error> {
+        Pair::new(raw.base, raw.quote)?.with_direction()
+    }
+}
+
+impl<B: AssetMarker, Q: AssetMarker> Price<B, Q> {
+    pub fn new(pair: Pair<B, Q>, value: U64, decimals: usize) -> Result<Self, PairError> {
+        if value.is_zero() {
+            return Err(PairError::PriceShouldBeGtZero(value));
+        }
+        Ok(Self {
+            pair,
+            value,
+            decimals,
+        })
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.value.as_u64() as f64 / 10f64.powi(self.decimals as i32)
+    }
+
+    /// Quote asset quantity worth base_quantity.
+    pub fn quote_quantity(&self, base_quantity: f64) -> f64 {
+        base_quantity * self.as_f64()
+    }
+
+    /// Base asset quantity worth quote_quantity.
+    pub fn base_quantity(&self, quote_quantity: f64) -> f64 {
+        quote_quantity / self.as_f64()
+    }
+
+    /// This price if it is a price of pair.
+    pub fn of_pair(&self, pair: &Pair<B, Q>) -> Result<&Self, PairError> {
+        pair.check(&self.pair)?;
+        Ok(self)
+    }
+}
+
+impl<B: AssetMarker, Q: AssetMarker> TryFrom<PriceRaw<B, Q>> for Price<B, Q> {
+    type Error = PairError;
+
+    fn try_from(raw: PriceRaw<B, Q>) -> Result<Self, Self::Error> {
+        Price::new(raw.pair, raw.value, raw.decimals)
+    }
+}
+
+impl<B: AssetMarker, Q: AssetMarker> TickSeries<B, Q> {
+    pub fn new(pair: Pair<B, Q>, price_decimals: usize, ticks: Vec<Tick>) -> Self {
+        Self {
+            pair,
+            price_decimals,
+            ticks,
+        }
+    }
+
+    /// Ticks if the series is of pair.
+    pub fn ticks_of_pair(&self, pair: &Pair<B, Q>) -> Result<&Vec<Tick>, PairError> {
+        pair.check(&self.pair)?;
+        Ok(&self.ticks)
+    }
+
+    pub fn price(&self, index: usize) -> Option<Price<B, Q>> {
+        self.ticks.get(index).map(|i| Price {
+            pair: self.pair.clone(),
+            value: i.price,
+            decimals: self.price_decimals,
+        })
+    }
+
+    pub fn last_price(&self) -> Option<Price<B, Q>> {
+        self.price(self.ticks.len().checked_sub(1)?)
+    }
+
+    pub fn to_hloc_series(&self, duration_ms: u64) -> Result<HlocSeries<B, Q>, HlocError> {
+        Ok(HlocSeries::new(
+            self.pair.clone(),
+            self.price_decimals,
+            Hloc::from_tick_vec(self.ticks.clone(), duration_ms)?,
+        ))
+    }
+}
+
+impl<B: AssetMarker, Q: AssetMarker> HlocSeries<B, Q> {
+    pub fn new(pair: Pair<B, Q>, price_decimals: usize, hlocs: Vec<Hloc>) -> Self {
+        Self {
+            pair,
+            price_decimals,
+            hlocs,
+        }
+    }
+
+    /// Hlocs if the series is of pair.
+    pub fn hlocs_of_pair(&self, pair: &Pair<B, Q>) -> Result<&Vec<Hloc>, PairError> {
+        pair.check(&self.pair)?;
+        Ok(&self.hlocs)
+    }
+
+    pub fn close_price(&self, index: usize) -> Option<Price<B, Q>> {
+        self.hlocs.get(index).map(|i| Price {
+            pair: self.pair.clone(),
+            value: i.close,
+            decimals: self.price_decimals,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _eth_lusd() -> Pair {
+        Pair::new(
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+        )
+        .unwrap()
+    }
+
+    fn _tick_new(price: u64, time: u64) -> Tick {
+        Tick::new(U64::from(price), time, U64::one(), true, None, None).unwrap()
+    }
+
+    #[test]
+    fn pair_new() {
+        let pair = _eth_lusd();
+        assert_eq!(pair.name(), String::from("ETH/LUSD"));
+        assert_eq!(pair.inverse().name(), String::from("LUSD/ETH"));
+        let pair = Pair::new(pair.base.clone(), pair.base);
+        assert!(pair.is_err());
+        assert_eq!(
+            pair.unwrap_err(),
+            PairError::SameAssetBaseAndQuote(String::from("ETH"))
+        );
+    }
+
+    #[test]
+    fn pair_with_direction() {
+        let pair: Pair<Eth, Lusd> = _eth_lusd().with_direction().unwrap();
+        let inverse_pair: Pair<Lusd, Eth> = pair.inverse();
+        assert_eq!(inverse_pair.name(), String::from("LUSD/ETH"));
+        assert_eq!(pair.untyped(), _eth_lusd());
+        assert_eq!(
+            _eth_lusd().with_direction::<Lusd, Eth>(),
+            Err(PairError::AssetIncorrect(
+                String::from("LUSD"),
+                String::from("ETH")
+            ))
+        );
+        assert!(_eth_lusd().with_direction::<Eth, AnyAsset>().is_ok());
+
+        let price = Price::new(pair.clone(), U64::from(1_250), 0).unwrap();
+        let series = TickSeries::new(pair, 0, vec![_tick_new(1_250, 0)]);
+        let last_price: Price<Eth, Lusd> = series.last_price().unwrap();
+        assert_eq!(last_price, price);
+        let json = serde_json::to_string(&series).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TickSeries<Eth, Lusd>>(&json).unwrap(),
+            series
+        );
+    }
+
+    #[test]
+    fn price_of_pair() {
+        let price = Price::new(_eth_lusd(), U64::from(1_250_000), 3).unwrap();
+        assert_eq!(price.as_f64(), 1_250f64);
+        assert_eq!(price.quote_quantity(2f64), 2_500f64);
+        assert_eq!(price.base_quantity(2_500f64), 2f64);
+        assert!(price.of_pair(&_eth_lusd()).is_ok());
+        assert_eq!(
+            price.of_pair(&_eth_lusd().inverse()),
+            Err(PairError::PairIncorrect(
+                String::from("LUSD/ETH"),
+                String::from("ETH/LUSD")
+            ))
+        );
+    }
+
+    #[test]
+    fn tick_series_to_hloc_series() {
+        let ticks = vec![
+            _tick_new(1_000, 0),
+            _tick_new(1_200, 10),
+            _tick_new(900, 20),
+        ];
+        let series = TickSeries::new(_eth_lusd(), 0, ticks);
+        assert_eq!(series.last_price().unwrap().value, U64::from(900));
+        assert!(series.ticks_of_pair(&_eth_lusd().inverse()).is_err());
+
+        let hloc_series = series.to_hloc_series(10).unwrap();
+        assert_eq!(hloc_series.pair, _eth_lusd());
+        assert_eq!(hloc_series.close_price(0).unwrap().value, U64::from(1_000));
+        assert!(hloc_series.hlocs_of_pair(&_eth_lusd()).is_ok());
+    }
+}
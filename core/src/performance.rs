@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use thiserror::Error;
+
+const YEAR_MILLISECONDS: f64 = 365f64 * 86_400_000f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PerformanceError {
+    #[error("Equity curve should have at least 2 values({0})")]
+    EquityCurveTooShort(usize),
+    #[error("Equity value can't be negative({0})")]
+    EquityValueIsNegative(f64),
+    #[error("Equity curve time should be increasing ({0} >= {1})")]
+    TimeNotIncreasing(u64, u64),
+    #[error("Benchmark curve length is not the equity curve one ({0} != {1})")]
+    BenchmarkLengthIncorrect(usize, usize),
+    #[error("Traded notional can't be negative({0})")]
+    TradedNotionalIsNegative(f64),
+    #[error("{0} is out of its range({1})")]
+    MetricOutOfRange(String, f64),
+}
+
+/// Equity curve against a benchmark curve, on the same times. Captures are the
+/// equity returns over the benchmark returns on periods the benchmark goes up or down.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "BenchmarkComparisonRaw")]
+pub struct BenchmarkComparison {
+    pub excess_return: f64,
+    pub tracking_error: f64,
+    pub information_ratio: f64,
+    pub beta: f64,
+    pub upside_capture: f64,
+    pub downside_capture: f64,
+}
+
+/// Performance of an equity curve (time in ms, portfolio value), ratios are annualized
+/// from the average period, max_drawdown_duration is in ms.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "PerformanceReportRaw")]
+pub struct PerformanceReport {
+    pub period_returns: Vec<f64>,
+    pub total_return: f64,
+    pub cagr: f64,
+    pub volatility: f64,
+    pub sharpe: f64,
+    pub sortino: f64,
+    pub calmar: f64,
+    pub max_drawdown: f64,
+    pub max_drawdown_duration: u64,
+    pub win_rate: f64,
+    pub turnover: f64,
+    pub benchmark: Option<BenchmarkComparison>,
+}
+
+/// BenchmarkComparison as deserialized, the curves being gone only the tracking error is checked.
+#[derive(Deserialize)]
+struct BenchmarkComparisonRaw {
+    excess_return: f64,
+    tracking_error: f64,
+    information_ratio: f64,
+    beta: f64,
+    upside_capture: f64,
+    downside_capture: f64,
+}
+
+/// PerformanceReport as deserialized, the curve being gone only its ranges are checked.
+#[derive(Deserialize)]
+struct PerformanceReportRaw {
+    period_returns: Vec<f64>,
+    total_return: f64,
+    cagr: f64,
+    volatility: f64,
+    sharpe: f64,
+    sortino: f64,
+    calmar: f64,
+    max_drawdown: f64,
+    max_drawdown_duration: u64,
+    win_rate: f64,
+    turnover: f64,
+    benchmark: Option<BenchmarkComparison>,
+}
+
+impl PerformanceReport {
+    /// traded_notional is the value of all orders executed, risk_free_rate is yearly.
+    pub fn new(
+        equity_curve: &[(u64, f64)],
+        traded_notional: f64,
+        risk_free_rate: f64,
+        benchmark_curve: Option<&[(u64, f64)]>,
+    ) -> Result<Self, PerformanceError> {
+        validate_equity_curve(equity_curve)?;
+        if traded_notional < 0f64 {
+            return Err(PerformanceError::TradedNotionalIsNegative(traded_notional));
+        }
+        let period_returns = period_returns(equity_curve);
+        let periods_by_year = periods_by_year(equity_curve);
+        let first_value = equity_curve[0].1;
+        let last_value = equity_curve[equity_curve.len() - 1].1;
+        let years =
+            (equity_curve[equity_curve.len() - 1].0 - equity_curve[0].0) as f64 / YEAR_MILLISECONDS;
+
+        let total_return = growth(first_value, last_value);
+        let cagr = (1f64 + total_return).powf(1f64 / years) - 1f64;
+        let yearly_mean_return = mean(&period_returns) * periods_by_year;
+        let volatility = standard_deviation(&period_returns) * periods_by_year.sqrt();
+        let period_risk_free_rate = risk_free_rate / periods_by_year;
+        let downside_deviation = (period_returns
+            .iter()
+            .map(|i| (i - period_risk_free_rate).min(0f64).powi(2))
+            .sum::<f64>()
+            / period_returns.len() as f64)
+            .sqrt()
+            * periods_by_year.sqrt();
+        let (max_drawdown, max_drawdown_duration) = max_drawdown(equity_curve);
+        let win_count = period_returns.iter().filter(|i| **i > 0f64).count();
+        let mean_value =
+            equity_curve.iter().map(|(_, i)| i).sum::<f64>() / equity_curve.len() as f64;
+
+        let benchmark = match benchmark_curve {
+            Some(i) => Some(BenchmarkComparison::new(equity_curve, i)?),
+            None => None,
+        };
+        Ok(Self {
+            total_return,
+            cagr,
+            volatility,
+            sharpe: ratio(yearly_mean_return - risk_free_rate, volatility),
+            sortino: ratio(yearly_mean_return - risk_free_rate, downside_deviation),
+            calmar: ratio(cagr, max_drawdown),
+            max_drawdown,
+            max_drawdown_duration,
+            win_rate: win_count as f64 / period_returns.len() as f64,
+            turnover: ratio(traded_notional, mean_value),
+            period_returns,
+            benchmark,
+        })
+    }
+}
+
+impl BenchmarkComparison {
+    pub fn new(
+        equity_curve: &[(u64, f64)],
+        benchmark_curve: &[(u64, f64)],
+    ) -> Result<Self, PerformanceError> {
+        validate_equity_curve(equity_curve)?;
+        validate_equity_curve(benchmark_curve)?;
+        if equity_curve.len() != benchmark_curve.len() {
+            return Err(PerformanceError::BenchmarkLengthIncorrect(
+                benchmark_curve.len(),
+                equity_curve.len(),
+            ));
+        }
+        let returns = period_returns(equity_curve);
+        let benchmark_returns = period_returns(benchmark_curve);
+        let periods_by_year = periods_by_year(equity_curve);
+        let active_returns: Vec<f64> = returns
+            .iter()
+            .zip(&benchmark_returns)
+            .map(|(i, j)| i - j)
+            .collect();
+
+        let total_return = |i: &[(u64, f64)]| growth(i[0].1, i[i.len() - 1].1);
+        let tracking_error = standard_deviation(&active_returns) * periods_by_year.sqrt();
+        let (mean_return, mean_benchmark_return) = (mean(&returns), mean(&benchmark_returns));
+        let covariance = returns
+            .iter()
+            .zip(&benchmark_returns)
+            .map(|(i, j)| (i - mean_return) * (j - mean_benchmark_return))
+            .sum::<f64>();
+        let benchmark_variance = benchmark_returns
+            .iter()
+            .map(|j| (j - mean_benchmark_return).powi(2))
+            .sum::<f64>();
+        let capture = |is_up: bool| {
+            let (sum, benchmark_sum) = returns
+                .iter()
+                .zip(&benchmark_returns)
+                .filter(|(_, j)| (**j > 0f64 && is_up) || (**j < 0f64 && !is_up))
+                .fold((0f64, 0f64), |(sum, benchmark_sum), (i, j)| {
+                    (sum + i, benchmark_sum + j)
+                });
+            ratio(sum, benchmark_sum)
+        };
+        Ok(Self {
+            excess_return: total_return(equity_curve) - total_return(benchmark_curve),
+            tracking_error,
+            information_ratio: ratio(mean(&active_returns) * periods_by_year, tracking_error),
+            beta: ratio(covariance, benchmark_variance),
+            upside_capture: capture(true),
+            downside_capture: capture(false),
+        })
+    }
+}
+
+impl TryFrom<BenchmarkComparisonRaw> for BenchmarkComparison {
+    type Error = PerformanceError;
+
+    fn try_from(raw: BenchmarkComparisonRaw) -> Result<Self, Self::Error> {
+        validate_metric("tracking_error", raw.tracking_error, 0f64..=f64::INFINITY)?;
+        Ok(Self {
+            excess_return: raw.excess_return,
+            tracking_error: raw.tracking_error,
+            information_ratio: raw.information_ratio,
+            beta: raw.beta,
+            upside_capture: raw.upside_capture,
+            downside_capture: raw.downside_capture,
+        })
+    }
+}
+
+impl TryFrom<PerformanceReportRaw> for PerformanceReport {
+    type Error = PerformanceError;
+
+    /// Returns can't lose more than everything, deviations and turnover can't be negative,
+    /// drawdown and win rate are fractions.
+    fn try_from(raw: PerformanceReportRaw) -> Result<Self, Self::Error> {
+        for period_return in &raw.period_returns {
+            validate_metric("period_return", *period_return, -1f64..=f64::INFINITY)?;
+        }
+        validate_metric("total_return", raw.total_return, -1f64..=f64::INFINITY)?;
+        validate_metric("cagr", raw.cagr, -1f64..=f64::INFINITY)?;
+        validate_metric("volatility", raw.volatility, 0f64..=f64::INFINITY)?;
+        validate_metric("max_drawdown", raw.max_drawdown, 0f64..=1f64)?;
+        validate_metric("win_rate", raw.win_rate, 0f64..=1f64)?;
+        validate_metric("turnover", raw.turnover, 0f64..=f64::INFINITY)?;
+        Ok(Self {
+            period_returns: raw.period_returns,
+            total_return: raw.total_return,
+            cagr: raw.cagr,
+            volatility: raw.volatility,
+            sharpe: raw.sharpe,
+            sortino: raw.sortino,
+            calmar: raw.calmar,
+            max_drawdown: raw.max_drawdown,
+            max_drawdown_duration: raw.max_drawdown_duration,
+            win_rate: raw.win_rate,
+            turnover: raw.turnover,
+            benchmark: raw.benchmark,
+        })
+    }
+}
+
+/// NaN is never in range.
+fn validate_metric(
+    name: &str,
+    value: f64,
+    range: RangeInclusive<f64>,
+) -> Result<(), PerformanceError> {
+    if !range.contains(&value) {
+        return Err(PerformanceError::MetricOutOfRange(
+            String::from(name),
+            value,
+        ));
+    }
+    Ok(())
+}
+
+fn validate_equity_curve(equity_curve: &[(u64, f64)]) -> Result<(), PerformanceError> {
+    if equity_curve.len() < 2 {
+        return Err(PerformanceError::EquityCurveTooShort(equity_curve.len()));
+    }
+    if let Some((_, i)) = equity_curve.iter().find(|(_, i)| *i < 0f64) {
+        return Err(PerformanceError::EquityValueIsNegative(*i));
+    }
+    if let Some(i) = equity_curve.windows(2).find(|i| i[0].0 >= i[1].0) {
+        return Err(PerformanceError::TimeNotIncreasing(i[0].0, i[1].0));
+    }
+    Ok(())
+}
+
+fn period_returns(equity_curve: &[(u64, f64)]) -> Vec<f64> {
+    equity_curve
+        .windows(2)
+        .map(|i| growth(i[0].1, i[1].1))
+        .collect()
+}
+
+/// Return from one value to the next, 0 once nothing is left.
+fn growth(from: f64, to: f64) -> f64 {
+    if from == 0f64 {
+        return 0f64;
+    }
+    to / from - 1f64
+}
+
+fn periods_by_year(equity_curve: &[(u64, f64)]) -> f64 {
+    let duration = (equity_curve[equity_curve.len() - 1].0 - equity_curve[0].0) as f64;
+    YEAR_MILLISECONDS * (equity_curve.len() - 1) as f64 / duration
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Sample standard deviation, 0 under 2 values.
+fn standard_deviation(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0f64;
+    }
+    let mean = mean(values);
+    (values.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0f64 {
+        return 0f64;
+    }
+    numerator / denominator
+}
+
+/// Largest loss from a peak and longest time spent under a peak.
+fn max_drawdown(equity_curve: &[(u64, f64)]) -> (f64, u64) {
+    let (mut peak_time, mut peak_value) = equity_curve[0];
+    let mut max_drawdown = 0f64;
+    let mut max_drawdown_duration = 0u64;
+    for (time, value) in equity_curve {
+        if *value >= peak_value {
+            (peak_time, peak_value) = (*time, *value);
+        } else {
+            max_drawdown = max_drawdown.max(1f64 - value / peak_value);
+            max_drawdown_duration = max_drawdown_duration.max(time - peak_time);
+        }
+    }
+    (max_drawdown, max_drawdown_duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400_000;
+
+    fn _equity_curve_new(values: &[f64]) -> Vec<(u64, f64)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, j)| (i as u64 * DAY, *j))
+            .collect()
+    }
+
+    #[test]
+    fn performance_report_new_incorrect() {
+        let report = PerformanceReport::new(&_equity_curve_new(&[100f64]), 0f64, 0f64, None);
+        assert!(report.is_err());
+        assert_eq!(
+            report.unwrap_err(),
+            PerformanceError::EquityCurveTooShort(1)
+        );
+        let report = PerformanceReport::new(&[(1, 100f64), (1, 110f64)], 0f64, 0f64, None);
+        assert_eq!(
+            report.unwrap_err(),
+            PerformanceError::TimeNotIncreasing(1, 1)
+        );
+        let report = PerformanceReport::new(&_equity_curve_new(&[100f64, -1f64]), 0f64, 0f64, None);
+        assert_eq!(
+            report.unwrap_err(),
+            PerformanceError::EquityValueIsNegative(-1f64)
+        );
+    }
+
+    #[test]
+    fn performance_report_new_total_loss() {
+        let equity_curve = _equity_curve_new(&[100f64, 50f64, 0f64, 0f64]);
+        let report = PerformanceReport::new(&equity_curve, 100f64, 0f64, None).unwrap();
+        assert_eq!(report.period_returns, vec![-0.5f64, -1f64, 0f64]);
+        assert_eq!(report.total_return, -1f64);
+        assert_eq!(report.cagr, -1f64);
+        assert_eq!(report.max_drawdown, 1f64);
+        assert_eq!(report.max_drawdown_duration, 3 * DAY);
+        assert_eq!(report.win_rate, 0f64);
+        assert!(report.turnover.is_finite());
+        assert!(report.sharpe.is_finite());
+    }
+
+    #[test]
+    fn performance_report_new() {
+        let equity_curve = _equity_curve_new(&[100f64, 110f64, 99f64, 99f64, 121f64]);
+        let report = PerformanceReport::new(&equity_curve, 200f64, 0f64, None).unwrap();
+        assert_eq!(report.period_returns.len(), 4);
+        assert!((report.total_return - 0.21f64).abs() < 1e-12);
+        assert!((report.max_drawdown - 0.1f64).abs() < 1e-12);
+        assert_eq!(report.max_drawdown_duration, 2 * DAY);
+        assert_eq!(report.win_rate, 0.5f64);
+        assert!((report.turnover - 200f64 / 105.8f64).abs() < 1e-12);
+        // 21% in 4 days
+        let cagr = 1.21f64.powf(365f64 / 4f64) - 1f64;
+        assert!((report.cagr - cagr).abs() / cagr < 1e-9);
+        assert!(report.volatility > 0f64);
+        assert!(report.sharpe > 0f64);
+        assert!(report.sortino > report.sharpe);
+        assert!(report.benchmark.is_none());
+    }
+
+    #[test]
+    fn benchmark_comparison_new() {
+        let equity_curve = _equity_curve_new(&[100f64, 110f64, 121f64]);
+        let benchmark_curve = _equity_curve_new(&[100f64, 105f64, 110.25f64]);
+        let comparison = BenchmarkComparison::new(&equity_curve, &benchmark_curve).unwrap();
+        assert!((comparison.excess_return - 0.1075f64).abs() < 1e-12);
+        // returns don't change so there is no tracking error
+        assert!(comparison.tracking_error.abs() < 1e-12);
+        assert_eq!(comparison.beta, 0f64);
+
+        let benchmark_curve = _equity_curve_new(&[100f64, 105f64]);
+        let comparison = BenchmarkComparison::new(&equity_curve, &benchmark_curve);
+        assert_eq!(
+            comparison.unwrap_err(),
+            PerformanceError::BenchmarkLengthIncorrect(2, 3)
+        );
+    }
+
+    #[test]
+    fn performance_report_beta() {
+        let equity_curve = _equity_curve_new(&[100f64, 120f64, 108f64, 129.6f64]);
+        let benchmark_curve = _equity_curve_new(&[100f64, 110f64, 104.5f64, 114.95f64]);
+        let report =
+            PerformanceReport::new(&equity_curve, 0f64, 0f64, Some(&benchmark_curve)).unwrap();
+        let comparison = report.benchmark.unwrap();
+        assert!((comparison.beta - 2f64).abs() < 1e-9);
+        assert!((comparison.upside_capture - 2f64).abs() < 1e-9);
+        assert!((comparison.downside_capture - 2f64).abs() < 1e-9);
+        assert!(comparison.tracking_error > 0f64);
+    }
+}
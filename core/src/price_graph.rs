@@ -0,0 +1,261 @@
+use crate::asset::Asset;
+use crate::pair::{AssetMarker, Pair, PairError, Price};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PriceGraphError {
+    #[error("Price should be greater than 0({0})")]
+    PriceShouldBeGtZero(f64),
+    #[error("Pair error {0}")]
+    Pair(PairError),
+    #[error("No route to price {0} in {1}")]
+    RouteNone(String, String),
+    #[error("Route to price {0} in {1} only uses quotes older than the max age")]
+    RouteStale(String, String),
+}
+
+/// price of one pair base asset in pair quote asset at time (ms).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "QuoteRaw")]
+pub struct Quote {
+    pub pair: Pair,
+    pub price: f64,
+    pub time: u64,
+}
+
+/// Quote as deserialized, checked by Quote::new.
+#[derive(Deserialize)]
+struct QuoteRaw {
+    pair: Pair,
+    price: f64,
+    time: u64,
+}
+
+/// Quotes of asset pairs, a rate between two assets goes through the fewest pairs,
+/// each used both ways, with quotes at most max_age (ms) old.
+/// Any max_age and quote list is valid, each quote being checked by Quote::new on deserialization.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceGraph {
+    pub max_age: u64,
+    pub quotes: Vec<Quote>,
+}
+
+impl Quote {
+    pub fn new(base: Asset, quote: Asset, price: f64, time: u64) -> Result<Self, PriceGraphError> {
+        if price <= 0f64 {
+            return Err(PriceGraphError::PriceShouldBeGtZero(price));
+        }
+        Ok(Self {
+            pair: Pair::new(base, quote).map_err(PriceGraphError::Pair)?,
+            price,
+            time,
+        })
+    }
+
+    pub fn from_price<B: AssetMarker, Q: AssetMarker>(
+        price: &Price<B, Q>,
+        time: u64,
+    ) -> Result<Self, PriceGraphError> {
+        Quote::new(
+            price.pair.base().clone(),
+            price.pair.quote().clone(),
+            price.as_f64(),
+            time,
+        )
+    }
+}
+
+impl TryFrom<QuoteRaw> for Quote {
+    type Error = PriceGraphError;
+
+    fn try_from(raw: QuoteRaw) -> Result<Self, Self::Error> {
+        Quote::new(
+            raw.pair.base().clone(),
+            raw.pair.quote().clone(),
+            raw.price,
+            raw.time,
+        )
+    }
+}
+
+impl PriceGraph {
+    pub fn new(max_age: u64) -> Self {
+        Self {
+            max_age,
+            quotes: vec![],
+        }
+    }
+
+    pub fn add_quote(&mut self, quote: Quote) {
+        self.quotes.push(quote);
+    }
+
+    /// Price of one from asset in to asset at time.
+    pub fn rate(&self, from: &Asset, to: &Asset, time: u64) -> Result<f64, PriceGraphError> {
+        if from == to {
+            return Ok(1f64);
+        }
+        let quotes = self._last_quotes(time);
+        let fresh_quotes: Vec<&Quote> = quotes
+            .iter()
+            .filter(|i| time - i.time <= self.max_age)
+            .cloned()
+            .collect();
+        if let Some(rate) = route_rate(&fresh_quotes, from, to) {
+            return Ok(rate);
+        }
+        if route_rate(&quotes, from, to).is_some() {
+            return Err(PriceGraphError::RouteStale(from.id.clone(), to.id.clone()));
+        }
+        Err(PriceGraphError::RouteNone(from.id.clone(), to.id.clone()))
+    }
+
+    /// Value of the holdings (asset, quantity) in the reporting asset at time.
+    pub fn value(
+        &self,
+        holdings: &[(Asset, f64)],
+        reporting_asset: &Asset,
+        time: u64,
+    ) -> Result<f64, PriceGraphError> {
+        holdings
+            .iter()
+            .map(|(asset, quantity)| Ok(quantity * self.rate(asset, reporting_asset, time)?))
+            .sum()
+    }
+
+    /// Last quote at time of each pair.
+    fn _last_quotes(&self, time: u64) -> Vec<&Quote> {
+        let mut quotes: Vec<&Quote> = vec![];
+        for quote in self.quotes.iter().filter(|i| i.time <= time) {
+            let same_pair = quotes
+                .iter()
+                .position(|i| i.pair == quote.pair || i.pair == quote.pair.inverse());
+            match same_pair {
+                Some(i) if quotes[i].time <= quote.time => quotes[i] = quote,
+                Some(_) => {}
+                None => quotes.push(quote),
+            }
+        }
+        quotes
+    }
+}
+
+/// Breadth first search from the from asset, multiplying the rates along the way.
+fn route_rate(quotes: &[&Quote], from: &Asset, to: &Asset) -> Option<f64> {
+    let mut visited: Vec<(&Asset, f64)> = vec![(from, 1f64)];
+    let mut next_index = 0;
+    while next_index < visited.len() {
+        let (asset, rate) = visited[next_index];
+        next_index += 1;
+        for quote in quotes {
+            let edge = match asset {
+                i if i == quote.pair.base() => Some((quote.pair.quote(), quote.price)),
+                i if i == quote.pair.quote() => Some((quote.pair.base(), 1f64 / quote.price)),
+                _ => None,
+            };
+            if let Some((next_asset, price)) = edge {
+                if next_asset == to {
+                    return Some(rate * price);
+                }
+                if !visited.iter().any(|(i, _)| i == &next_asset) {
+                    visited.push((next_asset, rate * price));
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+
+    fn _asset(id: &str) -> Asset {
+        Asset::new(String::from(id), String::from(id))
+    }
+
+    fn _price_graph_new() -> PriceGraph {
+        let mut price_graph = PriceGraph::new(1_000);
+        price_graph.add_quote(Quote::new(_asset("ETH"), _asset("LUSD"), 2_000f64, 0).unwrap());
+        price_graph.add_quote(Quote::new(_asset("LUSD"), _asset("USD"), 0.99f64, 0).unwrap());
+        price_graph.add_quote(Quote::new(_asset("EUR"), _asset("USD"), 1.1f64, 0).unwrap());
+        price_graph
+    }
+
+    #[test]
+    fn quote_new_incorrect() {
+        let quote = Quote::new(_asset("ETH"), _asset("ETH"), 1f64, 0);
+        assert!(quote.is_err());
+        assert_eq!(
+            quote.unwrap_err(),
+            PriceGraphError::Pair(PairError::SameAssetBaseAndQuote(String::from("ETH")))
+        );
+        let price = Price::new(
+            Pair::new(_asset("ETH"), _asset("LUSD")).unwrap(),
+            U64::from(2_000_000),
+            3,
+        )
+        .unwrap();
+        let quote = Quote::from_price(&price, 10).unwrap();
+        assert_eq!(quote.price, 2_000f64);
+    }
+
+    #[test]
+    fn price_graph_rate() {
+        let price_graph = _price_graph_new();
+        assert_eq!(
+            price_graph.rate(&_asset("ETH"), &_asset("LUSD"), 0),
+            Ok(2_000f64)
+        );
+        assert_eq!(
+            price_graph.rate(&_asset("LUSD"), &_asset("ETH"), 0),
+            Ok(1f64 / 2_000f64)
+        );
+        let rate = price_graph.rate(&_asset("ETH"), &_asset("EUR"), 0).unwrap();
+        assert!((rate - 2_000f64 * 0.99f64 / 1.1f64).abs() < 1e-9);
+        assert_eq!(
+            price_graph.rate(&_asset("ETH"), &_asset("BTC"), 0),
+            Err(PriceGraphError::RouteNone(
+                String::from("ETH"),
+                String::from("BTC")
+            ))
+        );
+    }
+
+    #[test]
+    fn price_graph_rate_stale_and_last_quote() {
+        let mut price_graph = _price_graph_new();
+        assert_eq!(
+            price_graph.rate(&_asset("ETH"), &_asset("USD"), 2_000),
+            Err(PriceGraphError::RouteStale(
+                String::from("ETH"),
+                String::from("USD")
+            ))
+        );
+        price_graph.add_quote(Quote::new(_asset("LUSD"), _asset("ETH"), 0.0004f64, 1_500).unwrap());
+        price_graph.add_quote(Quote::new(_asset("LUSD"), _asset("USD"), 1f64, 1_500).unwrap());
+        assert_eq!(
+            price_graph.rate(&_asset("ETH"), &_asset("USD"), 2_000),
+            Ok(2_500f64)
+        );
+        // quotes after time are not used
+        assert_eq!(
+            price_graph.rate(&_asset("ETH"), &_asset("LUSD"), 500),
+            Ok(2_000f64)
+        );
+    }
+
+    #[test]
+    fn price_graph_value() {
+        let price_graph = _price_graph_new();
+        let holdings = vec![
+            (_asset("ETH"), 1f64),
+            (_asset("LUSD"), 1_000f64),
+            (_asset("USD"), 20f64),
+        ];
+        let value = price_graph.value(&holdings, &_asset("USD"), 0).unwrap();
+        assert!((value - (1_980f64 + 990f64 + 20f64)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::asset::Asset;
+use crate::order::MarketOrder;
+use ethers::types::U64;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Most price levels walked to estimate one order, a bigger order fails instead of looping for ever.
+pub const MAX_LEVEL_COUNT: usize = 100_000;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PriceImpactError {
+    #[error("Price increment should be greater than 0({0})")]
+    PriceIncrementShouldBeGtZero(U64),
+    #[error("Limit volume by tick should be greater than 0({0})")]
+    LimitVolumeByTickShouldBeGtZero(U64),
+    #[error("Current price should be greater than price increment ({0} > {1})")]
+    CurrentPriceCantBeLessThanIncrement(U64, U64),
+    #[error("Order should sell or buy the base asset {0}")]
+    OrderBaseAssetNone(String),
+    #[error("Quantity sell should be greater than 0({0})")]
+    QuantitySellShouldBeGtZero(f64),
+    #[error("Quantity sell should be a finite number({0})")]
+    QuantitySellNotFinite(f64),
+    #[error("Order need more than {0} price levels")]
+    LevelCountGtMax(usize),
+    #[error("Price overflow walking the depth up from {0}")]
+    PriceOverflow(U64),
+    #[error("Price reached the price increment walking the depth down from {0}")]
+    PriceFloorReached(U64),
+    #[error("Quantity sell doesn't fit in U64 once shifted by the volume decimals({0})")]
+    QuantitySellOverflow(f64),
+}
+
+/// Liquidity resting around the price, the same model the simulation use to move the price.
+/// limit_volume_by_tick is available at the current price, each next price_increment
+/// offer limit_volume_change_by_tick more.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "MarketDepthRaw")]
+pub struct MarketDepth {
+    pub price_increment: U64,
+    pub limit_volume_by_tick: U64,
+    pub limit_volume_change_by_tick: U64,
+}
+
+/// MarketDepth as deserialized, checked by MarketDepth::new.
+#[derive(Deserialize)]
+struct MarketDepthRaw {
+    price_increment: U64,
+    limit_volume_by_tick: U64,
+    limit_volume_change_by_tick: U64,
+}
+
+/// Expected execution of a market order, prices are in quote asset by base asset.
+/// slippage is how much worse the average price is than the reference price (0.01 = 1%).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceImpact {
+    pub reference_price: f64,
+    pub average_price: f64,
+    pub worst_price: f64,
+    pub slippage: f64,
+    pub quantity_sell: f64,
+    pub quantity_buy: f64,
+}
+
+impl MarketDepth {
+    pub fn new(
+        price_increment: U64,
+        limit_volume_by_tick: U64,
+        limit_volume_change_by_tick: U64,
+    ) -> Result<Self, PriceImpactError> {
+        if price_increment.is_zero() {
+            return Err(PriceImpactError::PriceIncrementShouldBeGtZero(
+                price_increment,
+            ));
+        }
+        if limit_volume_by_tick.is_zero() {
+            return Err(PriceImpactError::LimitVolumeByTickShouldBeGtZero(
+                limit_volume_by_tick,
+            ));
+        }
+        Ok(Self {
+            price_increment,
+            limit_volume_by_tick,
+            limit_volume_change_by_tick,
+        })
+    }
+
+    /// Price and volume of each level consumed by a market volume, price stop before going under
+    /// the price increment or before overflowing so the volume may not be fully consumed.
+    pub fn walk(&self, current_price: U64, market_volume: U64, is_buy: bool) -> Vec<(U64, U64)> {
+        self._walk(current_price, market_volume, is_buy, usize::MAX)
+    }
+
+    fn _walk(
+        &self,
+        current_price: U64,
+        market_volume: U64,
+        is_buy: bool,
+        max_level_count: usize,
+    ) -> Vec<(U64, U64)> {
+        let mut levels: Vec<(U64, U64)> = Vec::new();
+        let mut market_volume_left = market_volume;
+        let mut limit_volume_left = self.limit_volume_by_tick;
+        let mut current_price = current_price;
+
+        while market_volume_left > U64::zero()
+            && current_price > self.price_increment
+            && levels.len() < max_level_count
+        {
+            let is_liquidity_consumed = market_volume_left > limit_volume_left;
+            let volume = market_volume_left.min(limit_volume_left);
+            market_volume_left -= volume;
+
+            levels.push((current_price, volume));
+            if is_liquidity_consumed {
+                let next_price = if is_buy {
+                    current_price.checked_add(self.price_increment)
+                } else {
+                    current_price.checked_sub(self.price_increment)
+                };
+                match next_price {
+                    Some(price) => current_price = price,
+                    None => break,
+                }
+                limit_volume_left =
+                    limit_volume_left.saturating_add(self.limit_volume_change_by_tick);
+            }
+        }
+        levels
+    }
+
+    /// Expected fill of the order, selling the base asset walk the depth down,
+    /// buying it spend the quote asset while walking the depth up.
+    /// An order the depth can't fill in MAX_LEVEL_COUNT levels is an error.
+    pub fn estimate(
+        &self,
+        order: &MarketOrder,
+        base_asset: &Asset,
+        current_price: U64,
+        price_decimal_shift: usize,
+        volume_decimal_shift: usize,
+    ) -> Result<PriceImpact, PriceImpactError> {
+        if current_price <= self.price_increment {
+            return Err(PriceImpactError::CurrentPriceCantBeLessThanIncrement(
+                current_price,
+                self.price_increment,
+            ));
+        }
+        if !order.quantity_sell.is_finite() {
+            return Err(PriceImpactError::QuantitySellNotFinite(order.quantity_sell));
+        }
+        if order.quantity_sell <= 0f64 {
+            return Err(PriceImpactError::QuantitySellShouldBeGtZero(
+                order.quantity_sell,
+            ));
+        }
+        let price_shift = 10f64.powi(price_decimal_shift as i32);
+        let volume_shift = 10f64.powi(volume_decimal_shift as i32);
+        let reference_price = current_price.as_u64() as f64 / price_shift;
+
+        let is_sell_base = &order.asset_sell == base_asset;
+        let is_buy_base = &order.asset_buy == base_asset;
+        let (quantity_sell, quantity_buy, worst_price) = match (is_sell_base, is_buy_base) {
+            (true, _) => {
+                let market_volume = order.quantity_sell * volume_shift;
+                if market_volume >= u64::MAX as f64 {
+                    return Err(PriceImpactError::QuantitySellOverflow(order.quantity_sell));
+                }
+                let market_volume = U64::from(market_volume as u64);
+                let levels = self._walk(current_price, market_volume, false, MAX_LEVEL_COUNT + 1);
+                if levels.len() > MAX_LEVEL_COUNT {
+                    return Err(PriceImpactError::LevelCountGtMax(MAX_LEVEL_COUNT));
+                }
+                let volume: U64 = levels
+                    .iter()
+                    .fold(U64::zero(), |sum, (_, volume)| sum + volume);
+                if volume < market_volume {
+                    return Err(PriceImpactError::PriceFloorReached(current_price));
+                }
+                let value: f64 = levels
+                    .iter()
+                    .map(|(price, volume)| price.as_u64() as f64 * volume.as_u64() as f64)
+                    .sum();
+                let worst_price = levels.last().map_or(current_price, |(price, _)| *price);
+                (
+                    volume.as_u64() as f64 / volume_shift,
+                    value / price_shift / volume_shift,
+                    worst_price,
+                )
+            }
+            (false, true) => {
+                let mut quote_left = order.quantity_sell;
+                let mut quantity_buy = 0f64;
+                let mut limit_volume = self.limit_volume_by_tick;
+                let mut price = current_price;
+                let mut level_count = 0usize;
+                while quote_left > 0f64 {
+                    level_count += 1;
+                    if level_count > MAX_LEVEL_COUNT {
+                        return Err(PriceImpactError::LevelCountGtMax(MAX_LEVEL_COUNT));
+                    }
+                    let level_price = price.as_u64() as f64 / price_shift;
+                    let level_volume = limit_volume.as_u64() as f64 / volume_shift;
+                    let level_quote = level_volume * level_price;
+                    if level_quote >= quote_left {
+                        quantity_buy += quote_left / level_price;
+                        quote_left = 0f64;
+                    } else {
+                        quantity_buy += level_volume;
+                        quote_left -= level_quote;
+                        price = price
+                            .checked_add(self.price_increment)
+                            .ok_or(PriceImpactError::PriceOverflow(price))?;
+                        limit_volume =
+                            limit_volume.saturating_add(self.limit_volume_change_by_tick);
+                    }
+                }
+                (order.quantity_sell, quantity_buy, price)
+            }
+            _ => return Err(PriceImpactError::OrderBaseAssetNone(base_asset.id.clone())),
+        };
+
+        // Average price in quote by base whatever the side
+        let average_price = if is_sell_base {
+            quantity_buy / quantity_sell
+        } else {
+            quantity_sell / quantity_buy
+        };
+        let slippage = if is_sell_base {
+            (reference_price - average_price) / reference_price
+        } else {
+            (average_price - reference_price) / reference_price
+        };
+        Ok(PriceImpact {
+            reference_price,
+            average_price,
+            worst_price: worst_price.as_u64() as f64 / price_shift,
+            slippage,
+            quantity_sell,
+            quantity_buy,
+        })
+    }
+}
+
+impl TryFrom<MarketDepthRaw> for MarketDepth {
+    type Error = PriceImpactError;
+
+    fn try_from(raw: MarketDepthRaw) -> Result<Self, Self::Error> {
+        MarketDepth::new(
+            raw.price_increment,
+            raw.limit_volume_by_tick,
+            raw.limit_volume_change_by_tick,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _market_depth_new() -> MarketDepth {
+        MarketDepth::new(U64::from(10), U64::from(100), U64::from(10)).unwrap()
+    }
+
+    fn _eth() -> Asset {
+        Asset::new(String::from("ETH"), String::from("Ether"))
+    }
+
+    fn _lusd() -> Asset {
+        Asset::new(String::from("LUSD"), String::from("Liquity USD"))
+    }
+
+    #[test]
+    fn market_depth_new_incorrect() {
+        let depth = MarketDepth::new(U64::zero(), U64::from(100), U64::from(10));
+        assert!(depth.is_err());
+        assert_eq!(
+            depth.unwrap_err(),
+            PriceImpactError::PriceIncrementShouldBeGtZero(U64::zero())
+        );
+    }
+
+    #[test]
+    fn market_depth_walk() {
+        let depth = _market_depth_new();
+        let levels = depth.walk(U64::from(1_000), U64::from(220), true);
+        assert_eq!(
+            levels,
+            vec![
+                (U64::from(1_000), U64::from(100)),
+                (U64::from(1_010), U64::from(110)),
+                (U64::from(1_020), U64::from(10)),
+            ]
+        );
+
+        let levels = depth.walk(U64::from(30), U64::from(1_000), false);
+        assert_eq!(
+            levels,
+            vec![
+                (U64::from(30), U64::from(100)),
+                (U64::from(20), U64::from(110))
+            ]
+        );
+    }
+
+    #[test]
+    fn market_depth_estimate_sell() {
+        let depth = _market_depth_new();
+        let order = MarketOrder::new(_eth(), _lusd(), 220f64);
+        let impact = depth.estimate(&order, &_eth(), U64::from(1_000), 0, 0);
+        assert!(impact.is_ok());
+        let impact = impact.unwrap();
+
+        assert_eq!(impact.reference_price, 1_000f64);
+        assert_eq!(impact.worst_price, 980f64);
+        assert_eq!(impact.quantity_sell, 220f64);
+        assert_eq!(impact.quantity_buy, 100_000f64 + 990f64 * 110f64 + 9_800f64);
+        assert_eq!(impact.average_price, impact.quantity_buy / 220f64);
+        assert!(impact.slippage > 0f64);
+    }
+
+    #[test]
+    fn market_depth_estimate_buy() {
+        let depth = _market_depth_new();
+        let order = MarketOrder::new(_lusd(), _eth(), 100_000f64 + 1_010f64 * 55f64);
+        let impact = depth.estimate(&order, &_eth(), U64::from(1_000), 0, 0);
+        assert!(impact.is_ok());
+        let impact = impact.unwrap();
+
+        assert_eq!(impact.worst_price, 1_010f64);
+        assert_eq!(impact.quantity_buy, 155f64);
+        assert!(impact.average_price > 1_000f64 && impact.average_price < 1_010f64);
+        assert!(impact.slippage > 0f64);
+    }
+
+    #[test]
+    fn market_depth_estimate_incorrect() {
+        let depth = _market_depth_new();
+        let order = MarketOrder::new(_lusd(), _eth(), 1_000f64);
+        let btc = Asset::new(String::from("BTC"), String::from("Bitcoin"));
+        let impact = depth.estimate(&order, &btc, U64::from(1_000), 0, 0);
+        assert!(impact.is_err());
+        assert_eq!(
+            impact.unwrap_err(),
+            PriceImpactError::OrderBaseAssetNone(String::from("BTC"))
+        );
+    }
+
+    #[test]
+    fn market_depth_estimate_unbounded() {
+        let depth = _market_depth_new();
+        for quantity_sell in [f64::INFINITY, f64::NAN] {
+            let order = MarketOrder::new(_lusd(), _eth(), quantity_sell);
+            let impact = depth.estimate(&order, &_eth(), U64::from(1_000), 0, 0);
+            assert!(matches!(
+                impact,
+                Err(PriceImpactError::QuantitySellNotFinite(_))
+            ));
+        }
+
+        // each level only hold 100 / 10^12 ETH
+        let depth = MarketDepth::new(U64::from(10), U64::from(100), U64::zero()).unwrap();
+        let order = MarketOrder::new(_lusd(), _eth(), 1_000f64);
+        let impact = depth.estimate(&order, &_eth(), U64::from(1_000), 0, 12);
+        assert_eq!(
+            impact.unwrap_err(),
+            PriceImpactError::LevelCountGtMax(MAX_LEVEL_COUNT)
+        );
+
+        let depth = MarketDepth::new(U64::MAX / 2, U64::one(), U64::zero()).unwrap();
+        let order = MarketOrder::new(_lusd(), _eth(), f64::MAX);
+        let impact = depth.estimate(&order, &_eth(), U64::MAX / 2 + 1, 0, 0);
+        assert_eq!(
+            impact.unwrap_err(),
+            PriceImpactError::PriceOverflow(U64::MAX)
+        );
+    }
+
+    #[test]
+    fn market_depth_walk_uncapped() {
+        let depth = MarketDepth::new(U64::from(10), U64::one(), U64::zero()).unwrap();
+        let level_count = 2 * MAX_LEVEL_COUNT as u64;
+        let levels = depth.walk(
+            U64::from(10 * (level_count + 2)),
+            U64::from(level_count),
+            false,
+        );
+        assert_eq!(levels.len(), 2 * MAX_LEVEL_COUNT);
+    }
+
+    #[test]
+    fn market_depth_estimate_sell_depth() {
+        let depth = _market_depth_new();
+        let order = MarketOrder::new(_eth(), _lusd(), 1_000f64);
+        let impact = depth.estimate(&order, &_eth(), U64::from(30), 0, 0);
+        assert_eq!(
+            impact.unwrap_err(),
+            PriceImpactError::PriceFloorReached(U64::from(30))
+        );
+
+        let order = MarketOrder::new(_eth(), _lusd(), 1e30f64);
+        let impact = depth.estimate(&order, &_eth(), U64::from(1_000), 0, 0);
+        assert_eq!(
+            impact.unwrap_err(),
+            PriceImpactError::QuantitySellOverflow(1e30f64)
+        );
+
+        // the volume is consumed on the last level allowed
+        let depth = MarketDepth::new(U64::from(10), U64::one(), U64::zero()).unwrap();
+        let order = MarketOrder::new(_eth(), _lusd(), MAX_LEVEL_COUNT as f64);
+        let current_price = U64::from(10 * (MAX_LEVEL_COUNT as u64 + 2));
+        let impact = depth.estimate(&order, &_eth(), current_price, 0, 0);
+        assert!(impact.is_ok());
+        assert_eq!(impact.unwrap().worst_price, 30f64);
+    }
+}
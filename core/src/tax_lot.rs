@@ -0,0 +1,552 @@
+use crate::asset::Asset;
+use crate::order::MarketOrder;
+use crate::order_lifecycle::Fill;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Relative float error tolerated on a disposed quantity.
+const QUANTITY_TOLERANCE: f64 = 1e-9;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum TaxLotError {
+    #[error("Quantity should be greater than 0({0})")]
+    QuantityShouldBeGtZero(f64),
+    #[error("Price can't be negative({0})")]
+    PriceIsNegative(f64),
+    #[error("Quantity of {0} is greater than the quantity held ({1} > {2})")]
+    QuantityGtQuantityHeld(String, f64, f64),
+    #[error("Lot {0} doesn't exist")]
+    LotNone(u64),
+    #[error("Lot {0} is not a lot of {1}")]
+    LotAssetIncorrect(u64, String),
+    #[error("Lot {0} is given more than once")]
+    LotIdDuplicated(u64),
+    #[error("Order should sell or buy the quote asset ({0} => {1})")]
+    QuoteAssetNone(String, String),
+    #[error("Price of {0} is needed for the unrealized gains")]
+    PriceNone(String),
+    #[error("Next lot id should be greater than the lot ids ({0} <= {1})")]
+    NextLotIdIncorrect(u64, u64),
+}
+
+/// Which lots a disposal consumes first, SpecificId consumes the given lots in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LotMatching {
+    Fifo,
+    Lifo,
+    Hifo,
+    SpecificId(Vec<u64>),
+}
+
+/// Quantity acquired at once, unit_cost in quote asset fees included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TaxLotRaw")]
+pub struct TaxLot {
+    pub id: u64,
+    pub asset: Asset,
+    pub quantity: f64,
+    pub unit_cost: f64,
+    pub time: u64,
+}
+
+/// TaxLot as deserialized, checked by TaxLot::new.
+#[derive(Deserialize)]
+struct TaxLotRaw {
+    id: u64,
+    asset: Asset,
+    quantity: f64,
+    unit_cost: f64,
+    time: u64,
+}
+
+/// Part of a lot disposed, values in quote asset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RealizedGain {
+    pub lot_id: u64,
+    pub asset: Asset,
+    pub quantity: f64,
+    pub cost_basis: f64,
+    pub proceeds: f64,
+    pub acquired_time: u64,
+    pub disposed_time: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetGains {
+    pub asset: Asset,
+    pub realized_gain: f64,
+    pub unrealized_gain: f64,
+    pub quantity: f64,
+    pub cost_basis: f64,
+}
+
+/// Lots held and realized gains, every value in quote_asset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TaxLotLedgerRaw")]
+pub struct TaxLotLedger {
+    pub quote_asset: Asset,
+    pub matching: LotMatching,
+    pub lots: Vec<TaxLot>,
+    pub realized_gains: Vec<RealizedGain>,
+    pub next_lot_id: u64,
+}
+
+/// TaxLotLedger as deserialized, its lot ids checked to be unique and below next_lot_id.
+#[derive(Deserialize)]
+struct TaxLotLedgerRaw {
+    quote_asset: Asset,
+    matching: LotMatching,
+    lots: Vec<TaxLot>,
+    realized_gains: Vec<RealizedGain>,
+    next_lot_id: u64,
+}
+
+impl TaxLot {
+    pub fn new(
+        id: u64,
+        asset: Asset,
+        quantity: f64,
+        unit_cost: f64,
+        time: u64,
+    ) -> Result<Self, TaxLotError> {
+        if !(quantity > 0f64 && quantity.is_finite()) {
+            return Err(TaxLotError::QuantityShouldBeGtZero(quantity));
+        }
+        if !(unit_cost >= 0f64 && unit_cost.is_finite()) {
+            return Err(TaxLotError::PriceIsNegative(unit_cost));
+        }
+        Ok(Self {
+            id,
+            asset,
+            quantity,
+            unit_cost,
+            time,
+        })
+    }
+}
+
+impl TryFrom<TaxLotRaw> for TaxLot {
+    type Error = TaxLotError;
+
+    fn try_from(raw: TaxLotRaw) -> Result<Self, Self::Error> {
+        TaxLot::new(raw.id, raw.asset, raw.quantity, raw.unit_cost, raw.time)
+    }
+}
+
+impl RealizedGain {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost_basis
+    }
+}
+
+impl TaxLotLedger {
+    pub fn new(quote_asset: Asset, matching: LotMatching) -> Self {
+        Self {
+            quote_asset,
+            matching,
+            lots: vec![],
+            realized_gains: vec![],
+            next_lot_id: 0,
+        }
+    }
+
+    pub fn quantity(&self, asset: &Asset) -> f64 {
+        self.lots
+            .iter()
+            .filter(|i| &i.asset == asset)
+            .map(|i| i.quantity)
+            .sum()
+    }
+
+    /// Opens a lot, returns its id.
+    pub fn acquire(
+        &mut self,
+        asset: Asset,
+        quantity: f64,
+        unit_cost: f64,
+        time: u64,
+    ) -> Result<u64, TaxLotError> {
+        let id = self.next_lot_id;
+        self.lots
+            .push(TaxLot::new(id, asset, quantity, unit_cost, time)?);
+        self.next_lot_id += 1;
+        Ok(id)
+    }
+
+    /// Disposes quantity at unit_price (fees deducted) with the ledger matching.
+    pub fn dispose(
+        &mut self,
+        asset: &Asset,
+        quantity: f64,
+        unit_price: f64,
+        time: u64,
+    ) -> Result<Vec<RealizedGain>, TaxLotError> {
+        let matching = self.matching.clone();
+        self.dispose_with(asset, quantity, unit_price, time, &matching)
+    }
+
+    pub fn dispose_with(
+        &mut self,
+        asset: &Asset,
+        quantity: f64,
+        unit_price: f64,
+        time: u64,
+        matching: &LotMatching,
+    ) -> Result<Vec<RealizedGain>, TaxLotError> {
+        if quantity <= 0f64 {
+            return Err(TaxLotError::QuantityShouldBeGtZero(quantity));
+        }
+        if unit_price < 0f64 {
+            return Err(TaxLotError::PriceIsNegative(unit_price));
+        }
+        let lot_indexes = self._matched_lot_indexes(asset, matching)?;
+        let tolerance = quantity * QUANTITY_TOLERANCE;
+        let mut quantity_left = quantity;
+        let mut lot_quantities = vec![];
+        for index in lot_indexes {
+            if quantity_left <= tolerance {
+                break;
+            }
+            let lot_quantity = self.lots[index].quantity.min(quantity_left);
+            quantity_left -= lot_quantity;
+            lot_quantities.push((index, lot_quantity));
+        }
+        if quantity_left > tolerance {
+            return Err(TaxLotError::QuantityGtQuantityHeld(
+                asset.id.clone(),
+                quantity,
+                quantity - quantity_left,
+            ));
+        }
+
+        let mut realized_gains = vec![];
+        for (index, lot_quantity) in lot_quantities {
+            let lot = &mut self.lots[index];
+            lot.quantity -= lot_quantity;
+            realized_gains.push(RealizedGain {
+                lot_id: lot.id,
+                asset: asset.clone(),
+                quantity: lot_quantity,
+                cost_basis: lot_quantity * lot.unit_cost,
+                proceeds: lot_quantity * unit_price,
+                acquired_time: lot.time,
+                disposed_time: time,
+            });
+        }
+        self.lots
+            .retain(|i| &i.asset != asset || i.quantity > tolerance);
+        self.realized_gains.extend(realized_gains.iter().cloned());
+        Ok(realized_gains)
+    }
+
+    /// Buying from quote asset opens a lot, selling for quote asset disposes lots.
+    pub fn record_fill(
+        &mut self,
+        order: &MarketOrder,
+        fill: &Fill,
+    ) -> Result<Vec<RealizedGain>, TaxLotError> {
+        let quantity_buy = fill.quantity_buy();
+        if order.asset_sell == self.quote_asset {
+            self.acquire(
+                order.asset_buy.clone(),
+                quantity_buy,
+                fill.quantity / quantity_buy,
+                fill.time,
+            )?;
+            return Ok(vec![]);
+        }
+        if order.asset_buy == self.quote_asset {
+            return self.dispose(
+                &order.asset_sell,
+                fill.quantity,
+                quantity_buy / fill.quantity,
+                fill.time,
+            );
+        }
+        Err(TaxLotError::QuoteAssetNone(
+            order.asset_sell.id.clone(),
+            order.asset_buy.id.clone(),
+        ))
+    }
+
+    /// Gains by asset, realized from start_time to end_time (excluded),
+    /// unrealized at prices (quote by asset) for the lots held.
+    pub fn gains(
+        &self,
+        start_time: u64,
+        end_time: u64,
+        prices: &[(Asset, f64)],
+    ) -> Result<Vec<AssetGains>, TaxLotError> {
+        let mut assets: Vec<&Asset> = vec![];
+        let realized_gains: Vec<&RealizedGain> = self
+            .realized_gains
+            .iter()
+            .filter(|i| i.disposed_time >= start_time && i.disposed_time < end_time)
+            .collect();
+        for asset in realized_gains
+            .iter()
+            .map(|i| &i.asset)
+            .chain(self.lots.iter().map(|i| &i.asset))
+        {
+            if !assets.contains(&asset) {
+                assets.push(asset);
+            }
+        }
+        assets
+            .into_iter()
+            .map(|asset| {
+                let lots: Vec<&TaxLot> = self.lots.iter().filter(|i| &i.asset == asset).collect();
+                let quantity: f64 = lots.iter().map(|i| i.quantity).sum();
+                let cost_basis: f64 = lots.iter().map(|i| i.quantity * i.unit_cost).sum();
+                let unrealized_gain = if lots.is_empty() {
+                    0f64
+                } else {
+                    let price = prices
+                        .iter()
+                        .find(|(i, _)| i == asset)
+                        .map(|(_, i)| *i)
+                        .ok_or(TaxLotError::PriceNone(asset.id.clone()))?;
+                    quantity * price - cost_basis
+                };
+                Ok(AssetGains {
+                    asset: asset.clone(),
+                    realized_gain: realized_gains
+                        .iter()
+                        .filter(|i| &i.asset == asset)
+                        .map(|i| i.gain())
+                        .sum(),
+                    unrealized_gain,
+                    quantity,
+                    cost_basis,
+                })
+            })
+            .collect()
+    }
+
+    fn _matched_lot_indexes(
+        &self,
+        asset: &Asset,
+        matching: &LotMatching,
+    ) -> Result<Vec<usize>, TaxLotError> {
+        let mut lot_indexes: Vec<usize> = (0..self.lots.len())
+            .filter(|i| &self.lots[*i].asset == asset)
+            .collect();
+        match matching {
+            LotMatching::Fifo => {
+                lot_indexes.sort_by_key(|i| (self.lots[*i].time, self.lots[*i].id))
+            }
+            LotMatching::Lifo => {
+                lot_indexes.sort_by_key(|i| (self.lots[*i].time, self.lots[*i].id));
+                lot_indexes.reverse();
+            }
+            LotMatching::Hifo => lot_indexes
+                .sort_by(|i, j| self.lots[*j].unit_cost.total_cmp(&self.lots[*i].unit_cost)),
+            LotMatching::SpecificId(ids) => {
+                return ids
+                    .iter()
+                    .enumerate()
+                    .map(|(i, id)| {
+                        if ids[..i].contains(id) {
+                            return Err(TaxLotError::LotIdDuplicated(*id));
+                        }
+                        let index = self
+                            .lots
+                            .iter()
+                            .position(|i| i.id == *id)
+                            .ok_or(TaxLotError::LotNone(*id))?;
+                        if &self.lots[index].asset != asset {
+                            return Err(TaxLotError::LotAssetIncorrect(*id, asset.id.clone()));
+                        }
+                        Ok(index)
+                    })
+                    .collect();
+            }
+        }
+        Ok(lot_indexes)
+    }
+}
+
+impl TryFrom<TaxLotLedgerRaw> for TaxLotLedger {
+    type Error = TaxLotError;
+
+    /// Lots disposed are gone from lots, their ids are still checked through the realized gains.
+    fn try_from(raw: TaxLotLedgerRaw) -> Result<Self, Self::Error> {
+        let mut lot_ids: Vec<u64> = raw.lots.iter().map(|i| i.id).collect();
+        lot_ids.sort_unstable();
+        if let Some(i) = lot_ids.windows(2).find(|i| i[0] == i[1]) {
+            return Err(TaxLotError::LotIdDuplicated(i[0]));
+        }
+        let max_lot_id = lot_ids
+            .into_iter()
+            .chain(raw.realized_gains.iter().map(|i| i.lot_id))
+            .max();
+        if let Some(max_lot_id) = max_lot_id {
+            if raw.next_lot_id <= max_lot_id {
+                return Err(TaxLotError::NextLotIdIncorrect(raw.next_lot_id, max_lot_id));
+            }
+        }
+        Ok(Self {
+            quote_asset: raw.quote_asset,
+            matching: raw.matching,
+            lots: raw.lots,
+            realized_gains: raw.realized_gains,
+            next_lot_id: raw.next_lot_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _eth() -> Asset {
+        Asset::new(String::from("ETH"), String::from("Ether"))
+    }
+
+    fn _lusd() -> Asset {
+        Asset::new(String::from("LUSD"), String::from("Liquity USD"))
+    }
+
+    fn _ledger_new(matching: LotMatching) -> TaxLotLedger {
+        let mut ledger = TaxLotLedger::new(_lusd(), matching);
+        ledger.acquire(_eth(), 1f64, 1_000f64, 0).unwrap();
+        ledger.acquire(_eth(), 1f64, 1_500f64, 1).unwrap();
+        ledger.acquire(_eth(), 1f64, 1_200f64, 2).unwrap();
+        ledger
+    }
+
+    fn _gains(realized_gains: &[RealizedGain]) -> Vec<(u64, f64)> {
+        realized_gains
+            .iter()
+            .map(|i| (i.lot_id, i.gain()))
+            .collect()
+    }
+
+    #[test]
+    fn tax_lot_ledger_dispose_matching() {
+        let mut ledger = _ledger_new(LotMatching::Fifo);
+        let gains = ledger.dispose(&_eth(), 1.5f64, 1_400f64, 3).unwrap();
+        assert_eq!(_gains(&gains), vec![(0, 400f64), (1, -50f64)]);
+        assert_eq!(ledger.quantity(&_eth()), 1.5f64);
+
+        let mut ledger = _ledger_new(LotMatching::Lifo);
+        let gains = ledger.dispose(&_eth(), 1f64, 1_400f64, 3).unwrap();
+        assert_eq!(_gains(&gains), vec![(2, 200f64)]);
+
+        let mut ledger = _ledger_new(LotMatching::Hifo);
+        let gains = ledger.dispose(&_eth(), 2f64, 1_400f64, 3).unwrap();
+        assert_eq!(_gains(&gains), vec![(1, -100f64), (2, 200f64)]);
+
+        let mut ledger = _ledger_new(LotMatching::SpecificId(vec![2, 0]));
+        let gains = ledger.dispose(&_eth(), 1.5f64, 1_400f64, 3).unwrap();
+        assert_eq!(_gains(&gains), vec![(2, 200f64), (0, 200f64)]);
+        assert_eq!(ledger.lots.len(), 2);
+    }
+
+    #[test]
+    fn tax_lot_ledger_dispose_incorrect() {
+        let mut ledger = _ledger_new(LotMatching::Fifo);
+        let gains = ledger.dispose(&_eth(), 4f64, 1_400f64, 3);
+        assert!(gains.is_err());
+        assert_eq!(
+            gains.unwrap_err(),
+            TaxLotError::QuantityGtQuantityHeld(String::from("ETH"), 4f64, 3f64)
+        );
+        let gains = ledger.dispose_with(
+            &_eth(),
+            1f64,
+            1_400f64,
+            3,
+            &LotMatching::SpecificId(vec![7]),
+        );
+        assert_eq!(gains.unwrap_err(), TaxLotError::LotNone(7));
+        let gains = ledger.dispose_with(
+            &_eth(),
+            2f64,
+            1_400f64,
+            3,
+            &LotMatching::SpecificId(vec![0, 0]),
+        );
+        assert_eq!(gains.unwrap_err(), TaxLotError::LotIdDuplicated(0));
+        let gains = ledger.dispose_with(
+            &_eth(),
+            2f64,
+            1_400f64,
+            3,
+            &LotMatching::SpecificId(vec![1]),
+        );
+        assert_eq!(
+            gains.unwrap_err(),
+            TaxLotError::QuantityGtQuantityHeld(String::from("ETH"), 2f64, 1f64)
+        );
+        assert_eq!(ledger.quantity(&_eth()), 3f64);
+        assert!(ledger.realized_gains.is_empty());
+    }
+
+    #[test]
+    fn tax_lot_ledger_dispose_float_error() {
+        let mut ledger = TaxLotLedger::new(_lusd(), LotMatching::Fifo);
+        ledger.acquire(_eth(), 0.1f64, 1_000f64, 0).unwrap();
+        ledger.acquire(_eth(), 0.2f64, 1_000f64, 1).unwrap();
+        // 0.1 + 0.2 is 0.30000000000000004
+        let gains = ledger.dispose(&_eth(), 0.3f64, 1_000f64, 2).unwrap();
+        assert_eq!(gains.len(), 2);
+        assert!(ledger.lots.is_empty());
+        let gains = ledger.dispose(&_eth(), 1e-12f64, 1_000f64, 3);
+        assert_eq!(
+            gains.unwrap_err(),
+            TaxLotError::QuantityGtQuantityHeld(String::from("ETH"), 1e-12f64, 0f64)
+        );
+    }
+
+    #[test]
+    fn tax_lot_ledger_record_fill() {
+        let mut ledger = TaxLotLedger::new(_lusd(), LotMatching::Fifo);
+        let buy_order = MarketOrder::new(_lusd(), _eth(), 2_010f64);
+        // 2_010 LUSD for 1 ETH, 0.005 ETH fee
+        let fill = Fill::new(1f64 / 2_000f64, 2_010f64, 0.005f64, 10).unwrap();
+        assert!(ledger.record_fill(&buy_order, &fill).is_ok());
+        assert!((ledger.lots[0].quantity - 1f64).abs() < 1e-12);
+        assert!((ledger.lots[0].unit_cost - 2_010f64).abs() < 1e-9);
+
+        let sell_order = MarketOrder::new(_eth(), _lusd(), 0.5f64);
+        let fill = Fill::new(2_500f64, 0.5f64, 5f64, 20).unwrap();
+        let gains = ledger.record_fill(&sell_order, &fill).unwrap();
+        assert!((gains[0].gain() - (1_245f64 - 1_005f64)).abs() < 1e-6);
+
+        let order = MarketOrder::new(
+            _eth(),
+            Asset::new(String::from("BTC"), String::from("Bitcoin")),
+            0.1f64,
+        );
+        assert_eq!(
+            ledger.record_fill(&order, &fill),
+            Err(TaxLotError::QuoteAssetNone(
+                String::from("ETH"),
+                String::from("BTC")
+            ))
+        );
+    }
+
+    #[test]
+    fn tax_lot_ledger_gains() {
+        let mut ledger = _ledger_new(LotMatching::Fifo);
+        ledger.dispose(&_eth(), 1f64, 1_400f64, 3).unwrap();
+        ledger.dispose(&_eth(), 1f64, 1_400f64, 10).unwrap();
+        let gains = ledger.gains(0, 5, &[(_eth(), 2_000f64)]).unwrap();
+        assert_eq!(
+            gains,
+            vec![AssetGains {
+                asset: _eth(),
+                realized_gain: 400f64,
+                unrealized_gain: 800f64,
+                quantity: 1f64,
+                cost_basis: 1_200f64,
+            }]
+        );
+        assert_eq!(
+            ledger.gains(0, 5, &[]),
+            Err(TaxLotError::PriceNone(String::from("ETH")))
+        );
+    }
+}
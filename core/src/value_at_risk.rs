@@ -0,0 +1,559 @@
+use crate::asset::Asset;
+use crate::market::Tick;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ValueAtRiskError {
+    #[error("Confidence should be between 0 and 1 excluded({0})")]
+    ConfidenceIncorrect(f64),
+    #[error("Horizon should be greater than 0({0})")]
+    HorizonShouldBeGtZero(usize),
+    #[error("Position list should not be empty")]
+    PositionNone,
+    #[error("Quantity of {0} should be finite and can't be negative({1})")]
+    QuantityIncorrect(String, f64),
+    #[error("Portfolio value should be greater than 0({0})")]
+    PortfolioValueShouldBeGtZero(f64),
+    #[error("Price history of {0} should have the same length as the others ({1} != {2})")]
+    PriceHistoryLengthIncorrect(String, usize, usize),
+    #[error("Price history should be longer than the horizon ({0} <= {1})")]
+    PriceHistoryTooShort(usize, usize),
+    #[error("Price of {0} should be greater than 0")]
+    PriceShouldBeGtZero(String),
+    #[error("Simulation count should be greater than 0({0})")]
+    SimulationCountShouldBeGtZero(usize),
+    #[error("Tick has no moving average")]
+    TickMovingAverageNone,
+    #[error("Tick has no variance")]
+    TickVarianceNone,
+    #[error("Window tick count should be greater than 1({0})")]
+    WindowTickCountShouldBeGtOne(usize),
+}
+
+/// Quantity held of an asset with its price history in quote asset, oldest first,
+/// every position sampled on the same periods.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssetPosition {
+    pub asset: Asset,
+    pub quantity: f64,
+    pub prices: Vec<f64>,
+}
+
+/// Losses in quote asset, positive when value is lost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskEstimate {
+    pub value_at_risk: f64,
+    pub expected_shortfall: f64,
+}
+
+/// Loss not exceeded with confidence (0.99 = 99%) over horizon periods of the price history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ValueAtRiskRaw")]
+pub struct ValueAtRisk {
+    pub confidence: f64,
+    pub horizon: usize,
+}
+
+/// ValueAtRisk as deserialized, checked by ValueAtRisk::new.
+#[derive(Deserialize)]
+struct ValueAtRiskRaw {
+    confidence: f64,
+    horizon: usize,
+}
+
+impl AssetPosition {
+    pub fn new(asset: Asset, quantity: f64, prices: Vec<f64>) -> Self {
+        Self {
+            asset,
+            quantity,
+            prices,
+        }
+    }
+
+    /// Value at the last price.
+    pub fn value(&self) -> f64 {
+        self.quantity * self.prices.last().unwrap_or(&0f64)
+    }
+
+    fn returns(&self) -> Vec<f64> {
+        self.prices.windows(2).map(|i| i[1] / i[0] - 1f64).collect()
+    }
+}
+
+impl ValueAtRisk {
+    pub fn new(confidence: f64, horizon: usize) -> Result<Self, ValueAtRiskError> {
+        if confidence <= 0f64 || confidence >= 1f64 {
+            return Err(ValueAtRiskError::ConfidenceIncorrect(confidence));
+        }
+        if horizon == 0 {
+            return Err(ValueAtRiskError::HorizonShouldBeGtZero(horizon));
+        }
+        Ok(Self {
+            confidence,
+            horizon,
+        })
+    }
+
+    /// Losses of the current portfolio over every horizon window of the history.
+    pub fn historical(
+        &self,
+        positions: &[AssetPosition],
+    ) -> Result<RiskEstimate, ValueAtRiskError> {
+        let period_count = self._validate(positions)?;
+        let value = portfolio_value(positions);
+        let values: Vec<f64> = (0..period_count)
+            .map(|i| positions.iter().map(|j| j.quantity * j.prices[i]).sum())
+            .collect();
+        let losses: Vec<f64> = values
+            .iter()
+            .zip(values.iter().skip(self.horizon))
+            .map(|(i, j)| -(j / i - 1f64) * value)
+            .collect();
+        Ok(self._estimate_from_losses(losses))
+    }
+
+    /// Normal portfolio returns with the mean and covariance of the asset returns.
+    pub fn parametric(
+        &self,
+        positions: &[AssetPosition],
+    ) -> Result<RiskEstimate, ValueAtRiskError> {
+        self._validate(positions)?;
+        let (mean, covariance) = return_moments(positions);
+        let weights = weights(positions);
+        let portfolio_mean: f64 = weights.iter().zip(&mean).map(|(i, j)| i * j).sum();
+        let portfolio_variance: f64 = weights
+            .iter()
+            .enumerate()
+            .map(|(i, wi)| {
+                weights
+                    .iter()
+                    .enumerate()
+                    .map(|(j, wj)| wi * wj * covariance[i][j])
+                    .sum::<f64>()
+            })
+            .sum();
+        Ok(self._normal_estimate(
+            portfolio_value(positions),
+            portfolio_mean,
+            portfolio_variance.max(0f64).sqrt(),
+        ))
+    }
+
+    /// Normal returns with no drift, horizon is in ticks, the volatility by tick is
+    /// Tick::return_volatility over the window_tick_count ticks of the moving average.
+    pub fn parametric_from_tick(
+        &self,
+        value: f64,
+        tick: &Tick,
+        window_tick_count: usize,
+    ) -> Result<RiskEstimate, ValueAtRiskError> {
+        if window_tick_count < 2 {
+            return Err(ValueAtRiskError::WindowTickCountShouldBeGtOne(
+                window_tick_count,
+            ));
+        }
+        if tick.moving_average.is_none() {
+            return Err(ValueAtRiskError::TickMovingAverageNone);
+        }
+        let volatility = tick
+            .return_volatility(window_tick_count)
+            .ok_or(ValueAtRiskError::TickVarianceNone)?;
+        Ok(self._normal_estimate(value, 0f64, volatility))
+    }
+
+    /// simulation_count horizons of correlated normal asset returns, seeded to be replayed.
+    pub fn monte_carlo(
+        &self,
+        positions: &[AssetPosition],
+        simulation_count: usize,
+        seed: u64,
+    ) -> Result<RiskEstimate, ValueAtRiskError> {
+        self._validate(positions)?;
+        if simulation_count == 0 {
+            return Err(ValueAtRiskError::SimulationCountShouldBeGtZero(
+                simulation_count,
+            ));
+        }
+        let (mean, covariance) = return_moments(positions);
+        let cholesky = cholesky(&covariance);
+        let weights = weights(positions);
+        let value = portfolio_value(positions);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let losses = (0..simulation_count)
+            .map(|_| {
+                let mut portfolio_return = 0f64;
+                for _ in 0..self.horizon {
+                    let normals: Vec<f64> =
+                        mean.iter().map(|_| standard_normal(&mut rng)).collect();
+                    for (i, weight) in weights.iter().enumerate() {
+                        let shock: f64 = (0..=i).map(|j| cholesky[i][j] * normals[j]).sum();
+                        portfolio_return += weight * (mean[i] + shock);
+                    }
+                }
+                -portfolio_return * value
+            })
+            .collect();
+        Ok(self._estimate_from_losses(losses))
+    }
+
+    fn _validate(&self, positions: &[AssetPosition]) -> Result<usize, ValueAtRiskError> {
+        let period_count = positions
+            .first()
+            .ok_or(ValueAtRiskError::PositionNone)?
+            .prices
+            .len();
+        for position in positions {
+            if !(position.quantity >= 0f64 && position.quantity.is_finite()) {
+                return Err(ValueAtRiskError::QuantityIncorrect(
+                    position.asset.id.clone(),
+                    position.quantity,
+                ));
+            }
+            if position.prices.len() != period_count {
+                return Err(ValueAtRiskError::PriceHistoryLengthIncorrect(
+                    position.asset.id.clone(),
+                    position.prices.len(),
+                    period_count,
+                ));
+            }
+            if position
+                .prices
+                .iter()
+                .any(|i| !(*i > 0f64 && i.is_finite()))
+            {
+                return Err(ValueAtRiskError::PriceShouldBeGtZero(
+                    position.asset.id.clone(),
+                ));
+            }
+        }
+        if period_count <= self.horizon {
+            return Err(ValueAtRiskError::PriceHistoryTooShort(
+                period_count,
+                self.horizon,
+            ));
+        }
+        // Weights and losses are relative to the portfolio value
+        let value = portfolio_value(positions);
+        if value <= 0f64 {
+            return Err(ValueAtRiskError::PortfolioValueShouldBeGtZero(value));
+        }
+        Ok(period_count)
+    }
+
+    fn _normal_estimate(&self, value: f64, mean: f64, standard_deviation: f64) -> RiskEstimate {
+        let horizon = self.horizon as f64;
+        let z = inverse_standard_normal_cdf(self.confidence);
+        let horizon_mean = mean * horizon;
+        let horizon_deviation = standard_deviation * horizon.sqrt();
+        let density = (-z * z / 2f64).exp() / (2f64 * PI).sqrt();
+        RiskEstimate {
+            value_at_risk: (z * horizon_deviation - horizon_mean) * value,
+            expected_shortfall: (horizon_deviation * density / (1f64 - self.confidence)
+                - horizon_mean)
+                * value,
+        }
+    }
+
+    /// Nearest-rank quantile of the losses and mean of the losses from it.
+    fn _estimate_from_losses(&self, mut losses: Vec<f64>) -> RiskEstimate {
+        losses.sort_by(|i, j| i.total_cmp(j));
+        let rank = (self.confidence * losses.len() as f64).ceil() as usize;
+        let index = rank.clamp(1, losses.len()) - 1;
+        let tail = &losses[index..];
+        RiskEstimate {
+            value_at_risk: losses[index],
+            expected_shortfall: tail.iter().sum::<f64>() / tail.len() as f64,
+        }
+    }
+}
+
+impl TryFrom<ValueAtRiskRaw> for ValueAtRisk {
+    type Error = ValueAtRiskError;
+
+    fn try_from(raw: ValueAtRiskRaw) -> Result<Self, Self::Error> {
+        ValueAtRisk::new(raw.confidence, raw.horizon)
+    }
+}
+
+fn portfolio_value(positions: &[AssetPosition]) -> f64 {
+    positions.iter().map(|i| i.value()).sum()
+}
+
+fn weights(positions: &[AssetPosition]) -> Vec<f64> {
+    let value = portfolio_value(positions);
+    positions.iter().map(|i| i.value() / value).collect()
+}
+
+/// Mean and sample covariance of the period returns of each asset.
+fn return_moments(positions: &[AssetPosition]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let returns: Vec<Vec<f64>> = positions.iter().map(|i| i.returns()).collect();
+    let count = returns[0].len() as f64;
+    let mean: Vec<f64> = returns
+        .iter()
+        .map(|i| i.iter().sum::<f64>() / count)
+        .collect();
+    let covariance = (0..returns.len())
+        .map(|i| {
+            (0..returns.len())
+                .map(|j| {
+                    returns[i]
+                        .iter()
+                        .zip(&returns[j])
+                        .map(|(a, b)| (a - mean[i]) * (b - mean[j]))
+                        .sum::<f64>()
+                        / (count - 1f64).max(1f64)
+                })
+                .collect()
+        })
+        .collect();
+    (mean, covariance)
+}
+
+/// Lower triangular matrix of the covariance, not positive directions are set to 0.
+fn cholesky(covariance: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let size = covariance.len();
+    let mut lower = vec![vec![0f64; size]; size];
+    for i in 0..size {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| lower[i][k] * lower[j][k]).sum();
+            if i == j {
+                lower[i][j] = (covariance[i][i] - sum).max(0f64).sqrt();
+            } else if lower[j][j] > 0f64 {
+                lower[i][j] = (covariance[i][j] - sum) / lower[j][j];
+            }
+        }
+    }
+    lower
+}
+
+/// Box-Muller transform.
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let u1: f64 = 1f64 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2f64 * u1.ln()).sqrt() * (2f64 * PI * u2).cos()
+}
+
+/// Acklam's rational approximation, relative error below 1.15e-9.
+fn inverse_standard_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.383_577_518_672_69e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    let p_low = 0.02425f64;
+    if p < p_low {
+        let q = (-2f64 * p.ln()).sqrt();
+        return (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1f64);
+    }
+    if p > 1f64 - p_low {
+        return -inverse_standard_normal_cdf(1f64 - p);
+    }
+    let q = p - 0.5f64;
+    let r = q * q;
+    (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+        / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+
+    fn _eth_position_new(prices: Vec<f64>) -> AssetPosition {
+        AssetPosition::new(
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            1f64,
+            prices,
+        )
+    }
+
+    fn _alternating_prices(count: usize) -> Vec<f64> {
+        // returns of +2% and -2% one after the other
+        let mut prices = vec![1_000f64];
+        for i in 1..count {
+            let change = if i % 2 == 1 { 1.02f64 } else { 0.98f64 };
+            prices.push(prices[i - 1] * change);
+        }
+        prices
+    }
+
+    #[test]
+    fn value_at_risk_new_incorrect() {
+        let value_at_risk = ValueAtRisk::new(1f64, 1);
+        assert!(value_at_risk.is_err());
+        assert_eq!(
+            value_at_risk.unwrap_err(),
+            ValueAtRiskError::ConfidenceIncorrect(1f64)
+        );
+        let value_at_risk = ValueAtRisk::new(0.95f64, 2).unwrap();
+        let estimate = value_at_risk.historical(&[_eth_position_new(vec![1f64, 2f64])]);
+        assert_eq!(
+            estimate.unwrap_err(),
+            ValueAtRiskError::PriceHistoryTooShort(2, 2)
+        );
+    }
+
+    #[test]
+    fn value_at_risk_positions_incorrect() {
+        let value_at_risk = ValueAtRisk::new(0.95f64, 1).unwrap();
+        let mut position = _eth_position_new(vec![1f64, 2f64]);
+        position.quantity = -1f64;
+        assert_eq!(
+            value_at_risk.parametric(&[position.clone()]).unwrap_err(),
+            ValueAtRiskError::QuantityIncorrect(String::from("ETH"), -1f64)
+        );
+        position.quantity = f64::NAN;
+        assert!(matches!(
+            value_at_risk.historical(&[position.clone()]).unwrap_err(),
+            ValueAtRiskError::QuantityIncorrect(..)
+        ));
+
+        position.quantity = 0f64;
+        assert_eq!(
+            value_at_risk.parametric(&[position.clone()]).unwrap_err(),
+            ValueAtRiskError::PortfolioValueShouldBeGtZero(0f64)
+        );
+        assert_eq!(
+            value_at_risk.monte_carlo(&[position], 10, 42).unwrap_err(),
+            ValueAtRiskError::PortfolioValueShouldBeGtZero(0f64)
+        );
+    }
+
+    #[test]
+    fn value_at_risk_historical() {
+        let value_at_risk = ValueAtRisk::new(0.9f64, 1).unwrap();
+        let prices = vec![100f64, 110f64, 99f64, 99f64, 108.9f64, 98.01f64];
+        let position = _eth_position_new(prices);
+        let estimate = value_at_risk.historical(&[position]).unwrap();
+        // worst period is -10% of a 98.01 portfolio
+        assert!((estimate.value_at_risk - 9.801f64).abs() < 1e-9);
+        assert!((estimate.expected_shortfall - 9.801f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn value_at_risk_parametric() {
+        let value_at_risk = ValueAtRisk::new(0.99f64, 4).unwrap();
+        let position = _eth_position_new(_alternating_prices(101));
+        let value = position.value();
+        let estimate = value_at_risk.parametric(&[position]).unwrap();
+        let standard_deviation = 0.02f64 * (100f64 / 99f64).sqrt();
+        let expected = 2.326348f64 * standard_deviation * 2f64 * value;
+        assert!((estimate.value_at_risk - expected).abs() / expected < 1e-3);
+        assert!(estimate.expected_shortfall > estimate.value_at_risk);
+    }
+
+    #[test]
+    fn value_at_risk_parametric_from_tick() {
+        let value_at_risk = ValueAtRisk::new(0.95f64, 1).unwrap();
+        // 2 ticks moving of 40 have a variance of 400, volatility is 40 / 1_000 = 4%
+        let tick = Tick::new(
+            U64::from(1_000),
+            0,
+            U64::one(),
+            true,
+            Some(U64::from(1_000)),
+            Some(U64::from(400)),
+        )
+        .unwrap();
+        let estimate = value_at_risk
+            .parametric_from_tick(10_000f64, &tick, 2)
+            .unwrap();
+        assert!((estimate.value_at_risk - 1.644854f64 * 400f64).abs() < 1e-3);
+        assert_eq!(
+            value_at_risk.parametric_from_tick(10_000f64, &tick, 1),
+            Err(ValueAtRiskError::WindowTickCountShouldBeGtOne(1))
+        );
+        let tick = Tick::new(U64::from(1_000), 0, U64::one(), true, None, None).unwrap();
+        assert_eq!(
+            value_at_risk.parametric_from_tick(10_000f64, &tick, 2),
+            Err(ValueAtRiskError::TickMovingAverageNone)
+        );
+    }
+
+    #[test]
+    fn value_at_risk_parametric_from_tick_random_walk() {
+        // price moving by 10 (0.1% of 10_000) each tick, variance measured on 20 ticks windows
+        let (window_tick_count, window_count) = (20, 5_000);
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut variance_sum = 0f64;
+        for _ in 0..window_count {
+            let mut prices = vec![10_000f64];
+            for i in 1..window_tick_count {
+                prices.push(prices[i - 1] + 10f64 * standard_normal(&mut rng));
+            }
+            let mean = prices.iter().sum::<f64>() / window_tick_count as f64;
+            variance_sum +=
+                prices.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / window_tick_count as f64;
+        }
+        let tick = Tick::new(
+            U64::from(10_000),
+            0,
+            U64::one(),
+            true,
+            Some(U64::from(10_000)),
+            Some(U64::from(
+                (variance_sum / window_count as f64).round() as u64
+            )),
+        )
+        .unwrap();
+        let value_at_risk = ValueAtRisk::new(0.95f64, 4).unwrap();
+        let estimate = value_at_risk
+            .parametric_from_tick(10_000f64, &tick, window_tick_count)
+            .unwrap();
+        // 0.1% by tick over 4 ticks
+        let expected = 1.644854f64 * 0.001f64 * 2f64 * 10_000f64;
+        assert!((estimate.value_at_risk - expected).abs() / expected < 0.05f64);
+    }
+
+    #[test]
+    fn value_at_risk_monte_carlo() {
+        let value_at_risk = ValueAtRisk::new(0.99f64, 1).unwrap();
+        let positions = vec![
+            _eth_position_new(_alternating_prices(101)),
+            AssetPosition::new(
+                Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+                1_000f64,
+                vec![1f64; 101],
+            ),
+        ];
+        let estimate = value_at_risk.monte_carlo(&positions, 20_000, 42).unwrap();
+        let parametric = value_at_risk.parametric(&positions).unwrap();
+        assert!(
+            (estimate.value_at_risk - parametric.value_at_risk).abs() / parametric.value_at_risk
+                < 0.05f64
+        );
+        assert_eq!(
+            estimate,
+            value_at_risk.monte_carlo(&positions, 20_000, 42).unwrap()
+        );
+    }
+}
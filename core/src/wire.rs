@@ -0,0 +1,325 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Version of the JSON wire format, bumped on any breaking change of a core type.
+pub const WIRE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum WireError {
+    #[error("Wire format version {0} is not supported (current {1})")]
+    VersionUnsupported(u32, u32),
+    #[error("Serialization error ({0})")]
+    SerializationIncorrect(String),
+}
+
+/// Envelope of every core value sent on the wire: {"version": 1, "data": ...}.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub version: u32,
+    pub data: T,
+}
+
+pub fn to_json<T: Serialize>(data: &T) -> Result<String, WireError> {
+    serde_json::to_string(&Versioned {
+        version: WIRE_FORMAT_VERSION,
+        data,
+    })
+    .map_err(|e| WireError::SerializationIncorrect(e.to_string()))
+}
+
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, WireError> {
+    #[derive(Deserialize)]
+    struct Version {
+        version: u32,
+    }
+    let version: Version =
+        serde_json::from_str(json).map_err(|e| WireError::SerializationIncorrect(e.to_string()))?;
+    if version.version != WIRE_FORMAT_VERSION {
+        return Err(WireError::VersionUnsupported(
+            version.version,
+            WIRE_FORMAT_VERSION,
+        ));
+    }
+    let versioned: Versioned<T> =
+        serde_json::from_str(json).map_err(|e| WireError::SerializationIncorrect(e.to_string()))?;
+    Ok(versioned.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::asset_registry::AssetRegistry;
+    use crate::execution::{ExecutionAlgorithm, ExecutionPlan};
+    use crate::execution_cost::FeeAndGasCostModel;
+    use crate::market::{Hloc, Tick};
+    use crate::order::{LimitOrder, MarketOrder, Order, OrderSide};
+    use crate::order_lifecycle::{Fill, TrackedOrder};
+    use crate::order_netting::ReferencePrice;
+    use crate::pair::{Eth, Lusd, Pair, Price};
+    use crate::performance::PerformanceReport;
+    use crate::price_graph::Quote;
+    use crate::price_impact::MarketDepth;
+    use crate::tax_lot::{LotMatching, TaxLot, TaxLotLedger};
+    use crate::value_at_risk::ValueAtRisk;
+    use ethers::types::U64;
+    use serde_json::{json, Value};
+
+    fn _market_order_new() -> MarketOrder {
+        MarketOrder::new(
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+            1.5f64,
+        )
+    }
+
+    /// JSON of data with one field of the data replaced by value.
+    fn _json_with<T: Serialize>(data: &T, field: &str, value: Value) -> String {
+        let mut json: Value = serde_json::from_str(&to_json(data).unwrap()).unwrap();
+        json["data"][field] = value;
+        json.to_string()
+    }
+
+    fn _assert_rejected<T: DeserializeOwned + std::fmt::Debug>(json: &str, message: &str) {
+        match from_json::<T>(json) {
+            Err(WireError::SerializationIncorrect(i)) => assert!(i.contains(message), "{i}"),
+            i => panic!("{json} should be rejected, got {i:?}"),
+        }
+    }
+
+    fn _eth() -> Asset {
+        Asset::new(String::from("ETH"), String::from("Ether"))
+    }
+
+    fn _lusd() -> Asset {
+        Asset::new(String::from("LUSD"), String::from("Liquity USD"))
+    }
+
+    #[test]
+    fn wire_market_order() {
+        let json = to_json(&_market_order_new()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"data":{"asset_sell":{"id":"ETH","display_name":"Ether"},"asset_buy":{"id":"LUSD","display_name":"Liquity USD"},"quantity_sell":1.5}}"#
+        );
+        let order: MarketOrder = from_json(&json).unwrap();
+        assert_eq!(order, _market_order_new());
+    }
+
+    #[test]
+    fn wire_round_trip() {
+        let tick = Tick::new(
+            U64::from(1_000),
+            10,
+            U64::one(),
+            true,
+            Some(U64::from(900)),
+            None,
+        )
+        .unwrap();
+        assert_eq!(from_json::<Tick>(&to_json(&tick).unwrap()), Ok(tick));
+
+        let hloc = Hloc::new(
+            U64::from(3),
+            U64::from(1),
+            U64::from(2),
+            U64::from(2),
+            0,
+            U64::one(),
+        )
+        .unwrap();
+        assert_eq!(from_json::<Hloc>(&to_json(&hloc).unwrap()), Ok(hloc));
+
+        let order = Order::Limit(
+            LimitOrder::new(
+                _market_order_new(),
+                Asset::new(String::from("ETH"), String::from("Ether")),
+                OrderSide::Sell,
+                U64::from(2_000),
+            )
+            .unwrap(),
+        );
+        assert_eq!(from_json::<Order>(&to_json(&order).unwrap()), Ok(order));
+    }
+
+    #[test]
+    fn wire_version_unsupported() {
+        let json = r#"{"version":2,"data":{"id":"ETH","display_name":"Ether"}}"#;
+        assert_eq!(
+            from_json::<Asset>(json),
+            Err(WireError::VersionUnsupported(2, 1))
+        );
+        assert!(matches!(
+            from_json::<Asset>("{}"),
+            Err(WireError::SerializationIncorrect(_))
+        ));
+    }
+
+    #[test]
+    fn wire_constructor_invariants() {
+        let zero = json!(U64::zero());
+
+        let tick = Tick::new(U64::from(1_000), 10, U64::one(), true, None, None).unwrap();
+        _assert_rejected::<Tick>(&_json_with(&tick, "price", zero.clone()), "Price");
+
+        let hloc = Hloc::new(
+            U64::from(3),
+            U64::from(1),
+            U64::from(2),
+            U64::from(2),
+            0,
+            U64::one(),
+        )
+        .unwrap();
+        _assert_rejected::<Hloc>(&_json_with(&hloc, "low", json!(U64::from(4))), "High");
+
+        let pair = Pair::new(_eth(), _lusd()).unwrap();
+        assert_eq!(
+            from_json::<Pair>(&to_json(&pair).unwrap()),
+            Ok(pair.clone())
+        );
+        _assert_rejected::<Pair>(&_json_with(&pair, "quote", json!(_eth())), "Quote asset");
+        _assert_rejected::<Pair<Lusd, Eth>>(&to_json(&pair).unwrap(), "Asset should be");
+
+        let price = Price::new(pair.with_direction::<Eth, Lusd>().unwrap(), U64::one(), 0).unwrap();
+        assert_eq!(
+            from_json::<Price<Eth, Lusd>>(&to_json(&price).unwrap()),
+            Ok(price.clone())
+        );
+        _assert_rejected::<Price<Eth, Lusd>>(&_json_with(&price, "value", zero.clone()), "Price");
+
+        let quote = Quote::new(_eth(), _lusd(), 2_000f64, 0).unwrap();
+        assert_eq!(
+            from_json::<Quote>(&to_json(&quote).unwrap()),
+            Ok(quote.clone())
+        );
+        _assert_rejected::<Quote>(&_json_with(&quote, "price", json!(0f64)), "Price");
+
+        let market_depth = MarketDepth::new(U64::one(), U64::one(), U64::zero()).unwrap();
+        _assert_rejected::<MarketDepth>(
+            &_json_with(&market_depth, "limit_volume_by_tick", zero),
+            "Limit volume",
+        );
+
+        let order = LimitOrder::new(
+            _market_order_new(),
+            _eth(),
+            OrderSide::Sell,
+            U64::from(2_000),
+        )
+        .unwrap();
+        _assert_rejected::<LimitOrder>(&_json_with(&order, "side", json!("Buy")), "side");
+
+        let plan = ExecutionPlan::new(
+            _market_order_new(),
+            ExecutionAlgorithm::Iceberg {
+                visible_quantity: 0.5f64,
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            from_json::<ExecutionPlan>(&to_json(&plan).unwrap()),
+            Ok(plan.clone())
+        );
+        _assert_rejected::<ExecutionPlan>(
+            &_json_with(&plan, "filled_quantity", json!(1f64)),
+            "quantities",
+        );
+        _assert_rejected::<ExecutionPlan>(
+            &_json_with(
+                &plan,
+                "algorithm",
+                json!({"Iceberg": {"visible_quantity": 0f64}}),
+            ),
+            "Visible quantity",
+        );
+
+        let fill = Fill::new(2_000f64, 1f64, 0f64, 0).unwrap();
+        _assert_rejected::<Fill>(&_json_with(&fill, "quantity", json!(0f64)), "quantity");
+
+        let reference_price = ReferencePrice::new(_eth(), _lusd(), 2_000f64).unwrap();
+        _assert_rejected::<ReferencePrice>(
+            &_json_with(&reference_price, "price", json!(0f64)),
+            "Reference price",
+        );
+
+        let cost_model = FeeAndGasCostModel::new(0.003f64, 0f64, 0f64, 0, 0f64).unwrap();
+        _assert_rejected::<FeeAndGasCostModel>(
+            &_json_with(&cost_model, "fee_rate", json!(2f64)),
+            "Fee rate",
+        );
+
+        let value_at_risk = ValueAtRisk::new(0.99f64, 1).unwrap();
+        _assert_rejected::<ValueAtRisk>(
+            &_json_with(&value_at_risk, "confidence", json!(1f64)),
+            "Confidence",
+        );
+
+        let json = json!({
+            "version": WIRE_FORMAT_VERSION,
+            "data": {"assets": [
+                {"id": "ETH", "display_name": "Ether", "kind": "wrapped_native"},
+                {"id": "ETH", "display_name": "Ether", "kind": "wrapped_native"},
+            ]},
+        });
+        _assert_rejected::<AssetRegistry>(&json.to_string(), "more than once");
+    }
+
+    #[test]
+    fn wire_tracked_state_invariants() {
+        let mut order = TrackedOrder::new(1, 100, Order::Market(_market_order_new()));
+        order.submit(110).unwrap();
+        order
+            .fill(Fill::new(2_000f64, 0.5f64, 0f64, 120).unwrap())
+            .unwrap();
+        assert_eq!(
+            from_json::<TrackedOrder>(&to_json(&order).unwrap()),
+            Ok(order.clone())
+        );
+        _assert_rejected::<TrackedOrder>(&_json_with(&order, "status", json!("Filled")), "status");
+        _assert_rejected::<TrackedOrder>(
+            &_json_with(&order, "status", json!("Submitted")),
+            "status",
+        );
+        _assert_rejected::<TrackedOrder>(
+            &_json_with(
+                &order,
+                "fills",
+                json!([Fill::new(2_000f64, 2f64, 0f64, 120).unwrap()]),
+            ),
+            "status",
+        );
+        _assert_rejected::<TrackedOrder>(&_json_with(&order, "updated_time", json!(90)), "time");
+
+        let lot = TaxLot::new(0, _eth(), 1f64, 2_000f64, 0).unwrap();
+        _assert_rejected::<TaxLot>(&_json_with(&lot, "quantity", json!(-1f64)), "Quantity");
+
+        let mut ledger = TaxLotLedger::new(_lusd(), LotMatching::Fifo);
+        ledger.acquire(_eth(), 1f64, 2_000f64, 0).unwrap();
+        ledger.acquire(_eth(), 1f64, 2_100f64, 10).unwrap();
+        assert_eq!(
+            from_json::<TaxLotLedger>(&to_json(&ledger).unwrap()),
+            Ok(ledger.clone())
+        );
+        _assert_rejected::<TaxLotLedger>(
+            &_json_with(&ledger, "lots", json!([lot.clone(), lot])),
+            "more than once",
+        );
+        _assert_rejected::<TaxLotLedger>(
+            &_json_with(&ledger, "next_lot_id", json!(1)),
+            "Next lot id",
+        );
+
+        let report = PerformanceReport::new(&[(0, 100f64), (1, 90f64)], 0f64, 0f64, None).unwrap();
+        assert_eq!(
+            from_json::<PerformanceReport>(&to_json(&report).unwrap()),
+            Ok(report.clone())
+        );
+        _assert_rejected::<PerformanceReport>(
+            &_json_with(&report, "max_drawdown", json!(2f64)),
+            "max_drawdown",
+        );
+    }
+}
@@ -42,55 +42,55 @@ pub fn make_indicators_from_ticks(
     let is_ticks_zero = tick_len == 0;
     if is_ticks_zero {
         state = TicksState::Zero;
-        return make_sliding_moving_average(_runner, &None, &None, tick_len, _new_tick);
-    }
-    if _new_ticks.is_empty() {
-        state = TicksState::FirstOldLastOld;
-    }
-    if _old_ticks.is_empty() {
-        state = TicksState::FirstNewLastNew
-    }
-    let is_new_full = _new_ticks.len() >= _runner.duration_moving_average_tick;
-    if is_new_full {
-        state = TicksState::FirstNewLastNew
+    } else {
+        if _new_ticks.is_empty() {
+            state = TicksState::FirstOldLastOld;
+        }
+        if _old_ticks.is_empty() {
+            state = TicksState::FirstNewLastNew
+        }
+        let is_new_full = _new_ticks.len() >= _runner.duration_moving_average_tick;
+        if is_new_full {
+            state = TicksState::FirstNewLastNew
+        }
     }
 
-    return match state {
-        TicksState::Zero => make_sliding_moving_average(_runner, &None, &None, tick_len, _new_tick),
-        TicksState::FirstNewLastNew => make_sliding_moving_average(
+    // First tick of the window (removed once the window is full) and last tick before the new one
+    let (first_tick, last_tick) = match state {
+        TicksState::Zero => (None, None),
+        TicksState::FirstNewLastNew => (
             // old[...] new[1, =>2<=, 3, =>4<=] len=3
-            _runner,
-            &_new_ticks.get(
+            _new_ticks.get(
                 0.max((_new_ticks.len() as i64) - (_runner.duration_moving_average_tick as i64))
                     as usize,
             ),
-            &_new_ticks.last(),
-            tick_len,
-            _new_tick,
+            _new_ticks.last(),
         ),
-        TicksState::FirstOldLastNew => make_sliding_moving_average(
+        TicksState::FirstOldLastNew => (
             // old[1, 2, 3, =>4<=] new [5, =>6<=] len=3
-            _runner,
-            &_old_ticks.get(0.max(
+            _old_ticks.get(0.max(
                 (_old_ticks.len() as i64)
                     - ((_runner.duration_moving_average_tick as i64) - (_new_ticks.len() as i64)),
             ) as usize),
-            &_new_ticks.last(),
-            tick_len,
-            _new_tick,
+            _new_ticks.last(),
         ),
-        TicksState::FirstOldLastOld => make_sliding_moving_average(
+        TicksState::FirstOldLastOld => (
             // old[1, =>2<=, 3, =>4<=] new [] len=3
-            _runner,
-            &_old_ticks.get(
+            _old_ticks.get(
                 0.max((_old_ticks.len() as i64) - (_runner.duration_moving_average_tick as i64))
                     as usize,
             ),
-            &_old_ticks.last(),
-            tick_len,
-            _new_tick,
+            _old_ticks.last(),
         ),
     };
+
+    let tick = make_sliding_moving_average(_runner, &first_tick, &last_tick, tick_len, _new_tick)?;
+    // The variance is carried on from the last tick, ticks following one without keep none
+    let is_variance_carried = tick_len <= 1 || last_tick.map_or(false, |i| i.variance.is_some());
+    if !is_variance_carried {
+        return Ok(tick);
+    }
+    make_sliding_variance(_runner, &first_tick, &last_tick, tick_len, &tick)
 }
 
 pub fn make_sliding_moving_average(
@@ -177,7 +177,7 @@ pub fn make_sliding_variance(
             variance = mul_div_i256(variance, I256::exp10(6), new_size).ok_or(
                 IndicatorError::VarianceMulDivLenOverflow(variance, new_size),
             )?;
-            tick.variance = Some(U64::from(variance.abs().as_u64()));
+            tick.variance = Some(_variance_as_u64(variance));
         }
         i if i == _runner.duration_moving_average_tick => {
             // variance = old_variance + (new_ma - old_ma)²
@@ -204,7 +204,7 @@ pub fn make_sliding_variance(
             )?;
             variance += (new_ma - old_ma).pow(2);
             variance += old_variance;
-            tick.variance = Some(U64::from(variance.abs().as_u64()));
+            tick.variance = Some(_variance_as_u64(variance));
         }
         _ => {
             // variance =  (old_size / new_size) * (old_variance + ((old_ma - new_value)²/new_size))
@@ -230,20 +230,26 @@ pub fn make_sliding_variance(
             variance = mul_div_i256(variance, old_size, new_size).ok_or(
                 IndicatorError::VarianceMulDivLenOverflow(variance, new_size),
             )?;
-            tick.variance = Some(U64::from(variance.abs().as_u64()));
+            tick.variance = Some(_variance_as_u64(variance));
         }
     }
     Ok(tick)
 }
 
+/// A variance too large for U64 saturates, it never fails the simulation.
+fn _variance_as_u64(variance: I256) -> U64 {
+    U64::from(u64::try_from(variance.abs()).unwrap_or(u64::MAX))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::indicator::{
-        make_indicators_from_ticks, make_sliding_moving_average, make_sliding_variance,
+        _variance_as_u64, make_indicators_from_ticks, make_sliding_moving_average,
+        make_sliding_variance,
     };
     use crate::market::Tick;
     use crate::runner::Runner;
-    use ethers::types::U64;
+    use ethers::types::{I256, U64};
 
     #[test]
     fn make_moving_average_success() {
@@ -763,4 +769,97 @@ mod tests {
         assert!(tick.variance.is_some());
         assert_eq!(tick.variance.unwrap(), U64::from(100));
     }
+
+    fn _ticks_with_variance_new() -> Vec<Tick> {
+        vec![
+            Tick::new(
+                U64::from(10),
+                0,
+                U64::one(),
+                true,
+                Some(U64::from(10)),
+                Some(U64::from(0)),
+            )
+            .unwrap(),
+            Tick::new(
+                U64::from(20),
+                0,
+                U64::one(),
+                true,
+                Some(U64::from(15)),
+                Some(U64::from(25)),
+            )
+            .unwrap(),
+            Tick::new(
+                U64::from(30),
+                0,
+                U64::one(),
+                true,
+                Some(U64::from(20)),
+                Some(U64::from(66)),
+            )
+            .unwrap(),
+        ]
+    }
+
+    #[test]
+    fn make_indicators_from_ticks_variance_full_old() {
+        let mut runner = Runner::default();
+        runner.duration_moving_average_tick = 3;
+        let tick = make_indicators_from_ticks(
+            &runner,
+            &_ticks_with_variance_new(),
+            &vec![],
+            &Tick::new(U64::from(40), 0, U64::one(), true, None, None).unwrap(),
+        );
+        assert!(tick.is_ok());
+        let tick = tick.unwrap();
+        assert_eq!(tick.moving_average, Some(U64::from(30)));
+        assert_eq!(tick.variance, Some(U64::from(66)));
+    }
+
+    #[test]
+    fn make_indicators_from_ticks_variance_partial_old() {
+        let mut runner = Runner::default();
+        runner.duration_moving_average_tick = 3;
+        let mut old_ticks = _ticks_with_variance_new();
+        old_ticks.pop();
+        let tick = make_indicators_from_ticks(
+            &runner,
+            &old_ticks,
+            &vec![],
+            &Tick::new(U64::from(30), 0, U64::one(), true, None, None).unwrap(),
+        );
+        assert!(tick.is_ok());
+        let tick = tick.unwrap();
+        assert_eq!(tick.moving_average, Some(U64::from(20)));
+        assert_eq!(tick.variance, Some(U64::from(66)));
+    }
+
+    #[test]
+    fn make_indicators_from_ticks_variance_none() {
+        let mut runner = Runner::default();
+        runner.duration_moving_average_tick = 3;
+        let mut old_ticks = _ticks_with_variance_new();
+        old_ticks[2].variance = None;
+        let tick = make_indicators_from_ticks(
+            &runner,
+            &old_ticks,
+            &vec![],
+            &Tick::new(U64::from(40), 0, U64::one(), true, None, None).unwrap(),
+        );
+        assert!(tick.is_ok());
+        let tick = tick.unwrap();
+        assert_eq!(tick.moving_average, Some(U64::from(30)));
+        assert_eq!(tick.variance, None);
+    }
+
+    #[test]
+    fn variance_as_u64_saturated() {
+        assert_eq!(_variance_as_u64(I256::from(66)), U64::from(66));
+        assert_eq!(
+            _variance_as_u64(I256::from(u64::MAX) * I256::from(4)),
+            U64::from(u64::MAX)
+        );
+    }
 }
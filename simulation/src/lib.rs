@@ -7,7 +7,10 @@ use std::ops::Div;
 
 mod actor;
 mod indicator;
-mod runner;
+pub mod monte_carlo;
+pub mod parameter_sweep;
+pub mod runner;
+pub mod walk_forward;
 
 pub fn generate_price_graph() -> (Vec<(DateTime<Utc>, f64)>, Vec<(DateTime<Utc>, f64)>) {
     let mut runner = Runner::default();
@@ -0,0 +1,374 @@
+use crate::runner::{Runner, RunnerError};
+use ethers::types::U64;
+use std::thread;
+use strategy::backtest::{Backtest, BacktestError};
+use strategy::execution_cost::CostModel;
+use strategy::portfolio::PortfolioStrategy;
+use thiserror::Error;
+
+type Safe = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MonteCarloError {
+    #[error("Simulation count should be greater than 0({0})")]
+    SimulationCountShouldBeGtZero(usize),
+    #[error("Thread count should be greater than 0({0})")]
+    ThreadCountShouldBeGtZero(usize),
+    #[error("Percentile should be between 0 and 1({0})")]
+    PercentileIncorrect(f64),
+    #[error("Runner error {0}")]
+    Runner(RunnerError),
+    #[error("Backtest error {0}")]
+    Backtest(BacktestError),
+}
+
+/// Result of one simulated market, replayable with its seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarloOutcome {
+    pub seed: u64,
+    pub terminal_value: Safe,
+    pub max_drawdown: f64,
+    pub is_floor_breached: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarloReport {
+    pub outcomes: Vec<MonteCarloOutcome>,
+}
+
+/// Config to backtest a strategy on simulation_count markets seeded from seed to
+/// seed + simulation_count - 1, split across thread_count threads.
+/// floor is the portfolio value (as the CPPI floor) that should never be breached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarlo {
+    pub simulation_count: usize,
+    pub seed: u64,
+    pub thread_count: usize,
+    pub start_price: U64,
+    pub duration_ms: u64,
+    pub floor: Option<Safe>,
+}
+
+impl MonteCarlo {
+    pub fn new(
+        simulation_count: usize,
+        seed: u64,
+        thread_count: usize,
+        start_price: U64,
+        duration_ms: u64,
+        floor: Option<Safe>,
+    ) -> Result<Self, MonteCarloError> {
+        if simulation_count == 0 {
+            return Err(MonteCarloError::SimulationCountShouldBeGtZero(
+                simulation_count,
+            ));
+        }
+        if thread_count == 0 {
+            return Err(MonteCarloError::ThreadCountShouldBeGtZero(thread_count));
+        }
+        Ok(Self {
+            simulation_count,
+            seed,
+            thread_count,
+            start_price,
+            duration_ms,
+            floor,
+        })
+    }
+
+    /// make_strategy gives a new strategy for each simulation, cost_model is applied to every backtest.
+    pub fn run<F>(
+        &self,
+        runner: &Runner,
+        backtest: &Backtest,
+        cost_model: Option<&(dyn CostModel + Sync)>,
+        make_strategy: F,
+    ) -> Result<MonteCarloReport, MonteCarloError>
+    where
+        F: Fn() -> Box<dyn PortfolioStrategy> + Sync,
+    {
+        let seeds: Vec<u64> = (0..self.simulation_count as u64)
+            .map(|i| self.seed.wrapping_add(i))
+            .collect();
+        let chunk_size = self.simulation_count.div_ceil(self.thread_count);
+        let results = thread::scope(|scope| {
+            let handles: Vec<_> = seeds
+                .chunks(chunk_size)
+                .map(|i| {
+                    scope.spawn(|| {
+                        i.iter()
+                            .map(|j| {
+                                self._run_seed(*j, runner, backtest, cost_model, &make_strategy)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|i| i.join().expect("Monte Carlo thread panicked"))
+                .collect::<Vec<_>>()
+        });
+        let outcomes = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+        Ok(MonteCarloReport { outcomes })
+    }
+
+    fn _run_seed<F>(
+        &self,
+        seed: u64,
+        runner: &Runner,
+        backtest: &Backtest,
+        cost_model: Option<&(dyn CostModel + Sync)>,
+        make_strategy: &F,
+    ) -> Result<MonteCarloOutcome, MonteCarloError>
+    where
+        F: Fn() -> Box<dyn PortfolioStrategy>,
+    {
+        let ticks = runner
+            .run_seeded(seed, 0, self.duration_ms, self.start_price)
+            .map_err(MonteCarloError::Runner)?;
+        let mut strategy = make_strategy();
+        let report = backtest
+            .run(
+                &ticks,
+                strategy.as_mut(),
+                cost_model.map(|i| i as &dyn CostModel),
+            )
+            .map_err(MonteCarloError::Backtest)?;
+        let terminal_value = report.equity_curve.last().map_or(0f64, |(_, i)| *i);
+        let is_floor_breached = self
+            .floor
+            .is_some_and(|i| report.equity_curve.iter().any(|(_, j)| *j < i));
+        Ok(MonteCarloOutcome {
+            seed,
+            terminal_value,
+            max_drawdown: report.performance.max_drawdown,
+            is_floor_breached,
+        })
+    }
+}
+
+impl MonteCarloReport {
+    /// Terminal value under which percentile of the outcomes are (0.05 = 5%).
+    pub fn terminal_value_percentile(&self, percentile: f64) -> Result<Safe, MonteCarloError> {
+        Self::_percentile(
+            self.outcomes.iter().map(|i| i.terminal_value).collect(),
+            percentile,
+        )
+    }
+
+    /// Max drawdown under which percentile of the outcomes are.
+    pub fn max_drawdown_percentile(&self, percentile: f64) -> Result<f64, MonteCarloError> {
+        Self::_percentile(
+            self.outcomes.iter().map(|i| i.max_drawdown).collect(),
+            percentile,
+        )
+    }
+
+    pub fn floor_breach_probability(&self) -> f64 {
+        let breach_count = self.outcomes.iter().filter(|i| i.is_floor_breached).count();
+        breach_count as f64 / self.outcomes.len() as f64
+    }
+
+    /// Nearest-rank percentile.
+    fn _percentile(mut values: Vec<f64>, percentile: f64) -> Result<f64, MonteCarloError> {
+        if !(0f64..=1f64).contains(&percentile) {
+            return Err(MonteCarloError::PercentileIncorrect(percentile));
+        }
+        values.sort_by(|i, j| i.total_cmp(j));
+        let rank = (percentile * values.len() as f64).ceil() as usize;
+        Ok(values[rank.clamp(1, values.len()) - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strategy::asset::Asset;
+    use strategy::constant_proportion_portfolio_insurance::ConstantProportionPortfolioInsurance;
+    use strategy::dollar_cost_averaging::{
+        DollarCostAveraging, DollarCostAveragingLimits, DollarCostAveragingPlan,
+        DollarCostAveragingScaling,
+    };
+    use strategy::execution_cost::FeeAndGasCostModel;
+    use strategy::portfolio::Portfolio;
+    use strategy::volatility_targeting::VolatilityTargeting;
+
+    fn _runner_new() -> Runner {
+        Runner::new(
+            U64::from(1) * U64::exp10(5),
+            (15, 30_000),
+            (60 * 60 * 1000, 2 * 60 * 60 * 1000),
+            (U64::from(1) * U64::exp10(6), U64::from(100) * U64::exp10(6)),
+            (U64::from(1) * U64::exp10(6), U64::from(100) * U64::exp10(6)),
+            U64::from(1_005_000),
+            100,
+        )
+        .unwrap()
+    }
+
+    fn _backtest_new() -> Backtest {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Backtest::new(
+            Portfolio::new(risky_asset, safe_asset, 0f64, 10_000f64),
+            6,
+            0f64,
+        )
+        .with_benchmarks(vec![])
+        .unwrap()
+    }
+
+    fn _make_strategy() -> Box<dyn PortfolioStrategy> {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Box::new(ConstantProportionPortfolioInsurance::new(
+            risky_asset,
+            safe_asset,
+            2f64,
+            8_000f64,
+        ))
+    }
+
+    fn _monte_carlo_new(thread_count: usize) -> MonteCarlo {
+        MonteCarlo::new(
+            4,
+            42,
+            thread_count,
+            U64::from(1_000) * U64::exp10(6),
+            60 * 60 * 1000,
+            Some(8_000f64),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn monte_carlo_new_incorrect() {
+        let monte_carlo = MonteCarlo::new(0, 42, 1, U64::one(), 1, None);
+        assert!(monte_carlo.is_err());
+        assert_eq!(
+            monte_carlo.unwrap_err(),
+            MonteCarloError::SimulationCountShouldBeGtZero(0)
+        );
+    }
+
+    #[test]
+    fn monte_carlo_run_seeded() {
+        let (runner, backtest) = (_runner_new(), _backtest_new());
+        let report = _monte_carlo_new(2).run(&runner, &backtest, None, _make_strategy);
+        assert!(report.is_ok());
+        let report = report.unwrap();
+        let seeds: Vec<u64> = report.outcomes.iter().map(|i| i.seed).collect();
+        assert_eq!(seeds, vec![42, 43, 44, 45]);
+        // same seeds give the same outcomes whatever the thread count
+        let report_single_thread = _monte_carlo_new(1)
+            .run(&runner, &backtest, None, _make_strategy)
+            .unwrap();
+        assert_eq!(report, report_single_thread);
+    }
+
+    #[test]
+    fn monte_carlo_run_seed_wrapping() {
+        let mut monte_carlo = _monte_carlo_new(1);
+        monte_carlo.simulation_count = 2;
+        monte_carlo.seed = u64::MAX;
+        let report = monte_carlo.run(&_runner_new(), &_backtest_new(), None, _make_strategy);
+        assert!(report.is_ok());
+        let seeds: Vec<u64> = report.unwrap().outcomes.iter().map(|i| i.seed).collect();
+        assert_eq!(seeds, vec![u64::MAX, 0]);
+    }
+
+    #[test]
+    fn monte_carlo_run_seeded_cost_model() {
+        let (runner, backtest) = (_runner_new(), _backtest_new());
+        let cost_model = FeeAndGasCostModel::new(0.01f64, 1f64, 0f64, 0, 0f64).unwrap();
+        let report = _monte_carlo_new(2)
+            .run(&runner, &backtest, None, _make_strategy)
+            .unwrap();
+        let report_with_cost = _monte_carlo_new(2)
+            .run(&runner, &backtest, Some(&cost_model), _make_strategy)
+            .unwrap();
+        for (i, j) in report.outcomes.iter().zip(&report_with_cost.outcomes) {
+            assert_eq!(i.seed, j.seed);
+            assert!(j.terminal_value < i.terminal_value);
+        }
+    }
+
+    #[test]
+    fn monte_carlo_run_seeded_volatility_targeting() {
+        let (runner, backtest) = (_runner_new(), _backtest_new());
+        let ticks = runner
+            .run_seeded(42, 0, 60 * 60 * 1000, U64::from(1_000) * U64::exp10(6))
+            .unwrap();
+        assert!(ticks.iter().all(|i| i.variance.is_some()));
+
+        let report = _monte_carlo_new(1).run(&runner, &backtest, None, || {
+            let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+            let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+            Box::new(
+                VolatilityTargeting::new(risky_asset, safe_asset, 0.01f64, 1f64, 0.2f64, 100, 6)
+                    .unwrap(),
+            )
+        });
+        assert!(report.is_ok());
+        let report = report.unwrap();
+        assert_eq!(report.outcomes.len(), 4);
+        assert!(report.outcomes.iter().all(|i| i.terminal_value > 0f64));
+    }
+
+    #[test]
+    fn monte_carlo_run_seeded_dollar_cost_averaging_volatility() {
+        let (runner, backtest) = (_runner_new(), _backtest_new());
+        let report = _monte_carlo_new(1).run(&runner, &backtest, None, || {
+            let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+            let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+            Box::new(
+                DollarCostAveraging::new_smart(
+                    safe_asset,
+                    risky_asset,
+                    chrono::Duration::minutes(10),
+                    100f64,
+                    DollarCostAveragingScaling::Volatility {
+                        reference_volatility: 0.01f64,
+                        window_tick_count: 100,
+                    },
+                    DollarCostAveragingLimits::new(50f64, 200f64).unwrap(),
+                    DollarCostAveragingPlan::default(),
+                )
+                .unwrap(),
+            )
+        });
+        assert!(report.is_ok());
+        assert!(report
+            .unwrap()
+            .outcomes
+            .iter()
+            .all(|i| i.terminal_value > 0f64));
+    }
+
+    #[test]
+    fn monte_carlo_report_percentile() {
+        let outcomes = [9_000f64, 12_000f64, 7_000f64, 10_000f64]
+            .iter()
+            .enumerate()
+            .map(|(i, j)| MonteCarloOutcome {
+                seed: i as u64,
+                terminal_value: *j,
+                max_drawdown: 0.1f64 * i as f64,
+                is_floor_breached: *j < 8_000f64,
+            })
+            .collect();
+        let report = MonteCarloReport { outcomes };
+        assert_eq!(report.terminal_value_percentile(0.5f64), Ok(9_000f64));
+        assert_eq!(report.terminal_value_percentile(0f64), Ok(7_000f64));
+        assert_eq!(report.terminal_value_percentile(1f64), Ok(12_000f64));
+        assert_eq!(report.max_drawdown_percentile(1f64), Ok(0.1f64 * 3f64));
+        assert_eq!(report.floor_breach_probability(), 0.25f64);
+        assert_eq!(
+            report.terminal_value_percentile(2f64),
+            Err(MonteCarloError::PercentileIncorrect(2f64))
+        );
+    }
+}
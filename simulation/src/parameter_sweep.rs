@@ -0,0 +1,551 @@
+use crate::monte_carlo::{MonteCarlo, MonteCarloError};
+use crate::runner::Runner;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use strategy::backtest::{Backtest, BacktestError};
+use strategy::execution_cost::CostModel;
+use strategy::market::Tick;
+use strategy::portfolio::PortfolioStrategy;
+use thiserror::Error;
+
+/// Metrics computed by run_backtest, in the results order.
+const BACKTEST_METRIC_NAMES: [&str; 6] = [
+    "total_return",
+    "cagr",
+    "sharpe",
+    "sortino",
+    "calmar",
+    "max_drawdown",
+];
+
+/// Metrics computed by run_monte_carlo, in the results order.
+const MONTE_CARLO_METRIC_NAMES: [&str; 3] = [
+    "median_terminal_value",
+    "max_drawdown",
+    "floor_breach_probability",
+];
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ParameterSweepError {
+    #[error("Parameter list should not be empty")]
+    ParameterNone,
+    #[error("Parameter {0} range should be ascending ({1} => {2})")]
+    ParameterRangeIncorrect(String, f64, f64),
+    #[error("Parameter {0} range should be finite ({1} => {2})")]
+    ParameterRangeNotFinite(String, f64, f64),
+    #[error("Parameter {0} step count should be greater than 0({1})")]
+    StepCountShouldBeGtZero(String, usize),
+    #[error("Sample count should be greater than 0({0})")]
+    SampleCountShouldBeGtZero(usize),
+    #[error("Objective {0} is not computed by this run")]
+    ObjectiveMetricNone(String),
+    #[error("Backtest error {0}")]
+    Backtest(BacktestError),
+    #[error("Monte Carlo error {0}")]
+    MonteCarlo(MonteCarloError),
+}
+
+/// Values from min to max, step_count values on a grid search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub step_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchMethod {
+    /// Every combination of the parameter steps.
+    Grid,
+    /// sample_count combinations drawn uniformly between min and max.
+    Random { sample_count: usize, seed: u64 },
+}
+
+/// Metric to rank the results by, MaxDrawdown and FloorBreachProbability are minimized.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Objective {
+    TotalReturn,
+    Cagr,
+    Sharpe,
+    Sortino,
+    Calmar,
+    MaxDrawdown,
+    MedianTerminalValue,
+    FloorBreachProbability,
+}
+
+/// Metrics of one parameter combination, score is the objective metric.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterSweepResult {
+    pub parameters: Vec<f64>,
+    pub metrics: Vec<(String, f64)>,
+    pub score: f64,
+}
+
+/// Results ranked from the best score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterSweepReport {
+    pub parameter_names: Vec<String>,
+    pub results: Vec<ParameterSweepResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterSweep {
+    pub parameters: Vec<Parameter>,
+    pub method: SearchMethod,
+    pub objective: Objective,
+}
+
+impl Parameter {
+    pub fn new(
+        name: String,
+        min: f64,
+        max: f64,
+        step_count: usize,
+    ) -> Result<Self, ParameterSweepError> {
+        if !(min.is_finite() && max.is_finite()) {
+            return Err(ParameterSweepError::ParameterRangeNotFinite(name, min, max));
+        }
+        if min > max {
+            return Err(ParameterSweepError::ParameterRangeIncorrect(name, min, max));
+        }
+        if step_count == 0 {
+            return Err(ParameterSweepError::StepCountShouldBeGtZero(
+                name, step_count,
+            ));
+        }
+        Ok(Self {
+            name,
+            min,
+            max,
+            step_count,
+        })
+    }
+
+    pub fn steps(&self) -> Vec<f64> {
+        if self.step_count == 1 {
+            return vec![self.min];
+        }
+        let step = (self.max - self.min) / (self.step_count - 1) as f64;
+        (0..self.step_count)
+            .map(|i| self.min + step * i as f64)
+            .collect()
+    }
+}
+
+impl Objective {
+    pub fn metric_name(&self) -> &'static str {
+        match self {
+            Objective::TotalReturn => "total_return",
+            Objective::Cagr => "cagr",
+            Objective::Sharpe => "sharpe",
+            Objective::Sortino => "sortino",
+            Objective::Calmar => "calmar",
+            Objective::MaxDrawdown => "max_drawdown",
+            Objective::MedianTerminalValue => "median_terminal_value",
+            Objective::FloorBreachProbability => "floor_breach_probability",
+        }
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        !matches!(
+            self,
+            Objective::MaxDrawdown | Objective::FloorBreachProbability
+        )
+    }
+}
+
+impl ParameterSweep {
+    pub fn new(
+        parameters: Vec<Parameter>,
+        method: SearchMethod,
+        objective: Objective,
+    ) -> Result<Self, ParameterSweepError> {
+        if parameters.is_empty() {
+            return Err(ParameterSweepError::ParameterNone);
+        }
+        if let SearchMethod::Random { sample_count, .. } = method {
+            if sample_count == 0 {
+                return Err(ParameterSweepError::SampleCountShouldBeGtZero(sample_count));
+            }
+        }
+        Ok(Self {
+            parameters,
+            method,
+            objective,
+        })
+    }
+
+    /// Parameter combinations to run, in the parameters order.
+    pub fn combinations(&self) -> Vec<Vec<f64>> {
+        match self.method {
+            SearchMethod::Grid => {
+                self.parameters
+                    .iter()
+                    .fold(vec![vec![]], |combinations, parameter| {
+                        combinations
+                            .iter()
+                            .flat_map(|i| {
+                                parameter.steps().into_iter().map(move |j| {
+                                    let mut combination = i.clone();
+                                    combination.push(j);
+                                    combination
+                                })
+                            })
+                            .collect()
+                    })
+            }
+            SearchMethod::Random { sample_count, seed } => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                (0..sample_count)
+                    .map(|_| {
+                        self.parameters
+                            .iter()
+                            .map(|i| rng.gen_range(i.min..=i.max))
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// One backtest on ticks for each combination.
+    pub fn run_backtest<F>(
+        &self,
+        ticks: &[Tick],
+        backtest: &Backtest,
+        cost_model: Option<&dyn CostModel>,
+        make_strategy: F,
+    ) -> Result<ParameterSweepReport, ParameterSweepError>
+    where
+        F: Fn(&[f64]) -> Box<dyn PortfolioStrategy>,
+    {
+        self._check_objective(&BACKTEST_METRIC_NAMES)?;
+        let mut results = vec![];
+        for parameters in self.combinations() {
+            let mut strategy = make_strategy(&parameters);
+            let report = backtest
+                .run(ticks, strategy.as_mut(), cost_model)
+                .map_err(ParameterSweepError::Backtest)?;
+            let performance = report.performance;
+            let metrics = vec![
+                (String::from("total_return"), performance.total_return),
+                (String::from("cagr"), performance.cagr),
+                (String::from("sharpe"), performance.sharpe),
+                (String::from("sortino"), performance.sortino),
+                (String::from("calmar"), performance.calmar),
+                (String::from("max_drawdown"), performance.max_drawdown),
+            ];
+            results.push(self._result(parameters, metrics)?);
+        }
+        Ok(self._report(results))
+    }
+
+    /// One Monte Carlo run for each combination.
+    pub fn run_monte_carlo<F>(
+        &self,
+        monte_carlo: &MonteCarlo,
+        runner: &Runner,
+        backtest: &Backtest,
+        cost_model: Option<&(dyn CostModel + Sync)>,
+        make_strategy: F,
+    ) -> Result<ParameterSweepReport, ParameterSweepError>
+    where
+        F: Fn(&[f64]) -> Box<dyn PortfolioStrategy> + Sync,
+    {
+        self._check_objective(&MONTE_CARLO_METRIC_NAMES)?;
+        let mut results = vec![];
+        for parameters in self.combinations() {
+            let report = monte_carlo
+                .run(runner, backtest, cost_model, || make_strategy(&parameters))
+                .map_err(ParameterSweepError::MonteCarlo)?;
+            let metrics = vec![
+                (
+                    String::from("median_terminal_value"),
+                    report
+                        .terminal_value_percentile(0.5f64)
+                        .map_err(ParameterSweepError::MonteCarlo)?,
+                ),
+                (
+                    String::from("max_drawdown"),
+                    report
+                        .max_drawdown_percentile(0.5f64)
+                        .map_err(ParameterSweepError::MonteCarlo)?,
+                ),
+                (
+                    String::from("floor_breach_probability"),
+                    report.floor_breach_probability(),
+                ),
+            ];
+            results.push(self._result(parameters, metrics)?);
+        }
+        Ok(self._report(results))
+    }
+
+    /// The objective should be one of the metric_names computed by the run.
+    fn _check_objective(&self, metric_names: &[&str]) -> Result<(), ParameterSweepError> {
+        let metric_name = self.objective.metric_name();
+        if !metric_names.contains(&metric_name) {
+            return Err(ParameterSweepError::ObjectiveMetricNone(String::from(
+                metric_name,
+            )));
+        }
+        Ok(())
+    }
+
+    fn _result(
+        &self,
+        parameters: Vec<f64>,
+        metrics: Vec<(String, f64)>,
+    ) -> Result<ParameterSweepResult, ParameterSweepError> {
+        let metric_name = self.objective.metric_name();
+        let score = metrics
+            .iter()
+            .find(|(i, _)| i == metric_name)
+            .map(|(_, i)| *i)
+            .ok_or(ParameterSweepError::ObjectiveMetricNone(String::from(
+                metric_name,
+            )))?;
+        Ok(ParameterSweepResult {
+            parameters,
+            metrics,
+            score,
+        })
+    }
+
+    fn _report(&self, mut results: Vec<ParameterSweepResult>) -> ParameterSweepReport {
+        results.sort_by(|i, j| match self.objective.is_maximized() {
+            true => j.score.total_cmp(&i.score),
+            false => i.score.total_cmp(&j.score),
+        });
+        ParameterSweepReport {
+            parameter_names: self.parameters.iter().map(|i| i.name.clone()).collect(),
+            results,
+        }
+    }
+}
+
+impl ParameterSweepReport {
+    pub fn best(&self) -> Option<&ParameterSweepResult> {
+        self.results.first()
+    }
+
+    /// Full results table, one ranked row by combination.
+    pub fn to_csv(&self) -> String {
+        let mut header = vec![String::from("rank")];
+        header.extend(self.parameter_names.iter().map(|i| _csv_field(i)));
+        if let Some(result) = self.results.first() {
+            header.extend(result.metrics.iter().map(|(i, _)| _csv_field(i)));
+        }
+        header.push(String::from("score"));
+        let mut lines = vec![header.join(",")];
+        for (rank, result) in self.results.iter().enumerate() {
+            let mut line = vec![(rank + 1).to_string()];
+            line.extend(result.parameters.iter().map(|i| i.to_string()));
+            line.extend(result.metrics.iter().map(|(_, i)| i.to_string()));
+            line.push(result.score.to_string());
+            lines.push(line.join(","));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Field quoted when it holds a separator, a quote or a line break, quotes are doubled.
+fn _csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+    use strategy::asset::Asset;
+    use strategy::constant_proportion_portfolio_insurance::ConstantProportionPortfolioInsurance;
+    use strategy::execution_cost::FeeAndGasCostModel;
+    use strategy::portfolio::Portfolio;
+
+    fn _backtest_new() -> Backtest {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Backtest::new(
+            Portfolio::new(risky_asset, safe_asset, 0f64, 10_000f64),
+            0,
+            0f64,
+        )
+        .with_benchmarks(vec![])
+        .unwrap()
+    }
+
+    fn _make_strategy(parameters: &[f64]) -> Box<dyn PortfolioStrategy> {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Box::new(ConstantProportionPortfolioInsurance::new(
+            risky_asset,
+            safe_asset,
+            parameters[0],
+            parameters[1],
+        ))
+    }
+
+    fn _parameters_new() -> Vec<Parameter> {
+        vec![
+            Parameter::new(String::from("multiplier"), 1f64, 3f64, 3).unwrap(),
+            Parameter::new(String::from("min_safe_quantity"), 5_000f64, 9_000f64, 2).unwrap(),
+        ]
+    }
+
+    fn _ticks_new(prices: &[u64]) -> Vec<Tick> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, j)| {
+                Tick::new(
+                    U64::from(*j),
+                    i as u64 * 86_400_000,
+                    U64::one(),
+                    true,
+                    None,
+                    None,
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parameter_new_incorrect() {
+        let parameter = Parameter::new(String::from("multiplier"), 3f64, 1f64, 3);
+        assert!(parameter.is_err());
+        assert_eq!(
+            parameter.unwrap_err(),
+            ParameterSweepError::ParameterRangeIncorrect(String::from("multiplier"), 3f64, 1f64)
+        );
+
+        let parameter = Parameter::new(String::from("multiplier"), 1f64, f64::INFINITY, 3);
+        assert_eq!(
+            parameter.unwrap_err(),
+            ParameterSweepError::ParameterRangeNotFinite(
+                String::from("multiplier"),
+                1f64,
+                f64::INFINITY
+            )
+        );
+        let parameter = Parameter::new(String::from("multiplier"), f64::NAN, 1f64, 3);
+        assert!(matches!(
+            parameter.unwrap_err(),
+            ParameterSweepError::ParameterRangeNotFinite(..)
+        ));
+    }
+
+    #[test]
+    fn parameter_sweep_combinations() {
+        let sweep =
+            ParameterSweep::new(_parameters_new(), SearchMethod::Grid, Objective::Sharpe).unwrap();
+        let combinations = sweep.combinations();
+        assert_eq!(combinations.len(), 6);
+        assert_eq!(combinations[0], vec![1f64, 5_000f64]);
+        assert_eq!(combinations[5], vec![3f64, 9_000f64]);
+
+        let method = SearchMethod::Random {
+            sample_count: 10,
+            seed: 42,
+        };
+        let sweep = ParameterSweep::new(_parameters_new(), method, Objective::Sharpe).unwrap();
+        let combinations = sweep.combinations();
+        assert_eq!(combinations.len(), 10);
+        assert!(combinations
+            .iter()
+            .all(|i| (1f64..=3f64).contains(&i[0]) && (5_000f64..=9_000f64).contains(&i[1])));
+        assert_eq!(combinations, sweep.combinations());
+    }
+
+    #[test]
+    fn parameter_sweep_run_backtest() {
+        let sweep = ParameterSweep::new(
+            _parameters_new(),
+            SearchMethod::Grid,
+            Objective::TotalReturn,
+        )
+        .unwrap();
+        let ticks = _ticks_new(&[1_000, 1_100, 1_200, 1_300]);
+        let report = sweep
+            .run_backtest(&ticks, &_backtest_new(), None, _make_strategy)
+            .unwrap();
+        assert_eq!(report.results.len(), 6);
+        // rising market, the most exposed setting wins and the least exposed is last
+        assert_eq!(report.best().unwrap().parameters[1], 5_000f64);
+        assert_eq!(report.results[5].parameters, vec![1f64, 9_000f64]);
+        assert!(report.results.windows(2).all(|i| i[0].score >= i[1].score));
+
+        let csv = report.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("rank,multiplier,min_safe_quantity,total_return,cagr,sharpe,sortino,calmar,max_drawdown,score")
+        );
+        assert_eq!(lines.count(), 6);
+    }
+
+    #[test]
+    fn parameter_sweep_objective_metric_none() {
+        let sweep = ParameterSweep::new(
+            _parameters_new(),
+            SearchMethod::Grid,
+            Objective::FloorBreachProbability,
+        )
+        .unwrap();
+        let ticks = _ticks_new(&[1_000, 1_100]);
+        // checked before any backtest is run
+        let report = sweep.run_backtest(&ticks, &_backtest_new(), None, |_| {
+            panic!("no strategy should be made")
+        });
+        assert_eq!(
+            report.unwrap_err(),
+            ParameterSweepError::ObjectiveMetricNone(String::from("floor_breach_probability"))
+        );
+    }
+
+    #[test]
+    fn parameter_sweep_run_backtest_cost_model() {
+        let sweep = ParameterSweep::new(
+            vec![Parameter::new(String::from("multiplier"), 1f64, 1f64, 1).unwrap()],
+            SearchMethod::Grid,
+            Objective::TotalReturn,
+        )
+        .unwrap();
+        let make_strategy = |i: &[f64]| _make_strategy(&[i[0], 5_000f64]);
+        let ticks = _ticks_new(&[1_000, 1_100, 1_200, 1_300]);
+        let report = sweep
+            .run_backtest(&ticks, &_backtest_new(), None, make_strategy)
+            .unwrap();
+        // rebalanced on each tick, so every fill after the first is charged
+        let cost_model = FeeAndGasCostModel::new(0.01f64, 0f64, 0f64, 0, 0f64).unwrap();
+        let report_with_cost = sweep
+            .run_backtest(&ticks, &_backtest_new(), Some(&cost_model), make_strategy)
+            .unwrap();
+        assert!(report_with_cost.results[0].score < report.results[0].score);
+    }
+
+    #[test]
+    fn parameter_sweep_report_to_csv_escaped() {
+        let report = ParameterSweepReport {
+            parameter_names: vec![String::from("rate, \"%\"")],
+            results: vec![ParameterSweepResult {
+                parameters: vec![0.5f64],
+                metrics: vec![(String::from("sharpe"), 1f64)],
+                score: 1f64,
+            }],
+        };
+        assert_eq!(
+            report.to_csv(),
+            "rank,\"rate, \"\"%\"\"\",sharpe,score\n1,0.5,1,1\n"
+        );
+    }
+}
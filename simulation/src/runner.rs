@@ -2,9 +2,10 @@ use crate::actor::*;
 use crate::indicator::{make_indicators_from_ticks, IndicatorError};
 use crate::market::{Tick, TickError};
 use crate::mul_div::*;
+use crate::price_impact::{MarketDepth, PriceImpactError};
 use ethers::types::{I256, U64};
-use rand::rngs::ThreadRng;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
 use std::env::var;
 use thiserror::Error;
 
@@ -40,6 +41,8 @@ pub enum RunnerError {
     Actor(ActorsError),
     #[error("Indicator error {0}")]
     Indicator(IndicatorError),
+    #[error("Price impact error {0}")]
+    PriceImpact(PriceImpactError),
 }
 
 /// Config to run a market simulation
@@ -145,25 +148,47 @@ impl Runner {
 
     pub fn run(
         &mut self,
+        current_time_ms: u64,
+        end_time_ms: u64,
+        current_price: U64,
+    ) -> Result<Vec<Tick>, RunnerError> {
+        let mut rng = thread_rng();
+        Runner::run_with_rng(self, &mut rng, current_time_ms, end_time_ms, current_price)
+    }
+
+    /// Same run for the same seed, to replay or compare simulations.
+    pub fn run_seeded(
+        &self,
+        seed: u64,
+        current_time_ms: u64,
+        end_time_ms: u64,
+        current_price: U64,
+    ) -> Result<Vec<Tick>, RunnerError> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        Runner::run_with_rng(self, &mut rng, current_time_ms, end_time_ms, current_price)
+    }
+
+    pub fn run_with_rng(
+        _runner: &Runner,
+        _rng: &mut impl Rng,
         mut current_time_ms: u64,
         end_time_ms: u64,
         mut current_price: U64,
     ) -> Result<Vec<Tick>, RunnerError> {
-        let mut rng = thread_rng();
         let mut ticks: Vec<Tick> = Vec::new();
 
         while current_time_ms < end_time_ms {
             let current_actor_power =
                 ActorPower::new(ActorPowerState::EQUAL, ActorPowerState::LESS);
-            let current_duration_market_state_ms = rng
+            let current_duration_market_state_ms = _rng
                 .gen_range(
-                    self.duration_between_market_state_range_ms.0
-                        ..=self.duration_between_market_state_range_ms.1,
+                    _runner.duration_between_market_state_range_ms.0
+                        ..=_runner.duration_between_market_state_range_ms.1,
                 )
                 .min(end_time_ms - current_time_ms);
             ticks.append(&mut Runner::make_ticks_for_actor_power(
-                self,
-                &mut rng,
+                _runner,
+                _rng,
                 current_time_ms,
                 current_price,
                 current_duration_market_state_ms,
@@ -182,7 +207,7 @@ impl Runner {
 
     pub fn make_ticks_for_actor_power(
         _runner: &Runner,
-        _rng: &mut ThreadRng,
+        _rng: &mut impl Rng,
         _current_time_ms: u64,
         _current_price: U64,
         _current_duration_market_state_ms: u64,
@@ -237,35 +262,25 @@ impl Runner {
             ));
         }
 
+        let market_depth = MarketDepth::new(
+            _runner.price_increment,
+            _actors.limit_volume_by_tick,
+            _actors.limit_volume_change_by_tick,
+        )
+        .map_err(RunnerError::PriceImpact)?;
         let mut ticks: Vec<Tick> = Vec::new();
-        let mut market_volume_left = _actors.market_volume;
-        let mut limit_volume_left = _actors.limit_volume_by_tick;
-        let mut current_price = _current_price;
-
-        while market_volume_left > U64::zero() && current_price > _runner.price_increment {
-            let is_liquidity_consumed = market_volume_left > limit_volume_left;
-            let volume = market_volume_left.min(limit_volume_left);
-            market_volume_left -= volume;
-
+        for (price, volume) in market_depth.walk(_current_price, _actors.market_volume, is_buy) {
             ticks.push(
-                Tick::new(current_price, _current_time_ms, volume, is_buy, None, None)
+                Tick::new(price, _current_time_ms, volume, is_buy, None, None)
                     .map_err(RunnerError::Tick)?,
             );
-            if is_liquidity_consumed {
-                current_price = if is_buy {
-                    current_price + _runner.price_increment
-                } else {
-                    current_price - _runner.price_increment
-                };
-                limit_volume_left += _actors.limit_volume_change_by_tick;
-            }
         }
         Ok(ticks)
     }
 
     pub fn make_actors(
         _runner: &Runner,
-        _rng: &mut ThreadRng,
+        _rng: &mut impl Rng,
         _current_actor_power: &ActorPower,
         _is_buy: bool,
     ) -> Result<Actors, RunnerError> {
@@ -681,7 +696,7 @@ mod tests {
 
     #[test]
     fn make_ticks_for_actor_power_trend() {
-        let mut rng = thread_rng();
+        let mut rng = StdRng::seed_from_u64(42);
         let mut runner = Runner::default();
         let current_time_ms: u64 = 42;
         let current_price = U64::from(1_000) * U64::exp10(6);
@@ -0,0 +1,336 @@
+use crate::parameter_sweep::{ParameterSweep, ParameterSweepError};
+use strategy::backtest::{Backtest, BacktestError};
+use strategy::market::Tick;
+use strategy::performance::{PerformanceError, PerformanceReport};
+use strategy::portfolio::PortfolioStrategy;
+use thiserror::Error;
+
+type Safe = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum WalkForwardError {
+    #[error("In sample duration should be greater than 0({0})")]
+    InSampleDurationShouldBeGtZero(u64),
+    #[error("Out of sample duration should be greater than 0({0})")]
+    OutOfSampleDurationShouldBeGtZero(u64),
+    #[error("Ticks are too short for one in sample and out of sample window")]
+    WindowNone,
+    #[error("Parameter sweep has no result to pick the window parameters from")]
+    BestParametersNone,
+    #[error("Parameter sweep error {0}")]
+    ParameterSweep(ParameterSweepError),
+    #[error("Backtest error {0}")]
+    Backtest(BacktestError),
+    #[error("Performance error {0}")]
+    Performance(PerformanceError),
+}
+
+/// Parameters tuned on in_sample then run on the following out_of_sample,
+/// ranges are (start, end) times in ms, end excluded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkForwardWindow {
+    pub in_sample: (u64, u64),
+    pub out_of_sample: (u64, u64),
+    pub parameters: Vec<f64>,
+    pub in_sample_score: f64,
+    pub out_of_sample_performance: PerformanceReport,
+}
+
+/// equity_curve chains the out of sample curves, each window starting with the
+/// portfolio the previous one ended with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkForwardReport {
+    pub windows: Vec<WalkForwardWindow>,
+    pub equity_curve: Vec<(u64, Safe)>,
+    pub performance: PerformanceReport,
+}
+
+/// Rolling windows moving by out_of_sample_duration (ms), with is_anchored the
+/// in sample window always starts at the first tick and grows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkForward {
+    pub in_sample_duration: u64,
+    pub out_of_sample_duration: u64,
+    pub is_anchored: bool,
+}
+
+impl WalkForward {
+    pub fn new(
+        in_sample_duration: u64,
+        out_of_sample_duration: u64,
+        is_anchored: bool,
+    ) -> Result<Self, WalkForwardError> {
+        if in_sample_duration == 0 {
+            return Err(WalkForwardError::InSampleDurationShouldBeGtZero(
+                in_sample_duration,
+            ));
+        }
+        if out_of_sample_duration == 0 {
+            return Err(WalkForwardError::OutOfSampleDurationShouldBeGtZero(
+                out_of_sample_duration,
+            ));
+        }
+        Ok(Self {
+            in_sample_duration,
+            out_of_sample_duration,
+            is_anchored,
+        })
+    }
+
+    /// In sample and out of sample ranges fitting in the ticks, windows with less than 2 ticks
+    /// in a range (a gap in the ticks) are skipped.
+    pub fn windows(&self, ticks: &[Tick]) -> Vec<((u64, u64), (u64, u64))> {
+        let (first_time, last_time) = match (ticks.first(), ticks.last()) {
+            (Some(i), Some(j)) => (i.time, j.time),
+            _ => return vec![],
+        };
+        let mut windows = vec![];
+        let mut in_sample_start = first_time;
+        let mut out_of_sample_start = match first_time.checked_add(self.in_sample_duration) {
+            Some(i) => i,
+            None => return vec![],
+        };
+        while let Some(out_of_sample_end) =
+            out_of_sample_start.checked_add(self.out_of_sample_duration)
+        {
+            if out_of_sample_end - 1 > last_time {
+                break;
+            }
+            let in_sample = (in_sample_start, out_of_sample_start);
+            let out_of_sample = (out_of_sample_start, out_of_sample_end);
+            let is_tick_count_enough = _ticks_between(ticks, in_sample).len() > 1
+                && _ticks_between(ticks, out_of_sample).len() > 1;
+            if is_tick_count_enough {
+                windows.push((in_sample, out_of_sample));
+            }
+            if !self.is_anchored {
+                in_sample_start += self.out_of_sample_duration;
+            }
+            out_of_sample_start = out_of_sample_end;
+        }
+        windows
+    }
+
+    pub fn run<F>(
+        &self,
+        ticks: &[Tick],
+        parameter_sweep: &ParameterSweep,
+        backtest: &Backtest,
+        make_strategy: F,
+    ) -> Result<WalkForwardReport, WalkForwardError>
+    where
+        F: Fn(&[f64]) -> Box<dyn PortfolioStrategy>,
+    {
+        let windows = self.windows(ticks);
+        if windows.is_empty() {
+            return Err(WalkForwardError::WindowNone);
+        }
+        let mut walk_forward_windows = vec![];
+        let mut equity_curve: Vec<(u64, Safe)> = vec![];
+        let mut out_of_sample_backtest = backtest.clone();
+        let mut traded_notional = 0f64;
+        for (in_sample, out_of_sample) in windows {
+            let sweep_report = parameter_sweep
+                .run_backtest(
+                    _ticks_between(ticks, in_sample),
+                    backtest,
+                    None,
+                    &make_strategy,
+                )
+                .map_err(WalkForwardError::ParameterSweep)?;
+            let best = sweep_report
+                .best()
+                .ok_or(WalkForwardError::BestParametersNone)?;
+            let mut strategy = make_strategy(&best.parameters);
+            let report = out_of_sample_backtest
+                .run(
+                    _ticks_between(ticks, out_of_sample),
+                    strategy.as_mut(),
+                    None,
+                )
+                .map_err(WalkForwardError::Backtest)?;
+
+            equity_curve.extend(report.equity_curve.iter().cloned());
+            traded_notional += report.traded_notional;
+            out_of_sample_backtest.portfolio = report.portfolio;
+            walk_forward_windows.push(WalkForwardWindow {
+                in_sample,
+                out_of_sample,
+                parameters: best.parameters.clone(),
+                in_sample_score: best.score,
+                out_of_sample_performance: report.performance,
+            });
+        }
+        let performance = PerformanceReport::new(
+            &equity_curve,
+            traded_notional,
+            backtest.risk_free_rate,
+            None,
+        )
+        .map_err(WalkForwardError::Performance)?;
+        Ok(WalkForwardReport {
+            windows: walk_forward_windows,
+            equity_curve,
+            performance,
+        })
+    }
+}
+
+fn _ticks_between(ticks: &[Tick], (start, end): (u64, u64)) -> &[Tick] {
+    let start_index = ticks.partition_point(|i| i.time < start);
+    let end_index = ticks.partition_point(|i| i.time < end);
+    &ticks[start_index..end_index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parameter_sweep::{Objective, Parameter, SearchMethod};
+    use ethers::types::U64;
+    use strategy::asset::Asset;
+    use strategy::constant_proportion_portfolio_insurance::ConstantProportionPortfolioInsurance;
+    use strategy::portfolio::Portfolio;
+
+    const DAY: u64 = 86_400_000;
+
+    fn _ticks_new(prices: &[u64]) -> Vec<Tick> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, j)| {
+                Tick::new(U64::from(*j), i as u64 * DAY, U64::one(), true, None, None).unwrap()
+            })
+            .collect()
+    }
+
+    fn _make_strategy(parameters: &[f64]) -> Box<dyn PortfolioStrategy> {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Box::new(ConstantProportionPortfolioInsurance::new(
+            risky_asset,
+            safe_asset,
+            parameters[0],
+            5_000f64,
+        ))
+    }
+
+    fn _backtest_new() -> Backtest {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Backtest::new(
+            Portfolio::new(risky_asset, safe_asset, 0f64, 10_000f64),
+            0,
+            0f64,
+        )
+        .with_benchmarks(vec![])
+        .unwrap()
+    }
+
+    #[test]
+    fn walk_forward_new_incorrect() {
+        let walk_forward = WalkForward::new(0, DAY, false);
+        assert!(walk_forward.is_err());
+        assert_eq!(
+            walk_forward.unwrap_err(),
+            WalkForwardError::InSampleDurationShouldBeGtZero(0)
+        );
+    }
+
+    #[test]
+    fn walk_forward_windows() {
+        let ticks = _ticks_new(&[1_000; 11]);
+        let windows = WalkForward::new(4 * DAY, 3 * DAY, false)
+            .unwrap()
+            .windows(&ticks);
+        assert_eq!(
+            windows,
+            vec![
+                ((0, 4 * DAY), (4 * DAY, 7 * DAY)),
+                ((3 * DAY, 7 * DAY), (7 * DAY, 10 * DAY)),
+            ]
+        );
+        let windows = WalkForward::new(4 * DAY, 3 * DAY, true)
+            .unwrap()
+            .windows(&ticks);
+        assert_eq!(windows[1], ((0, 7 * DAY), (7 * DAY, 10 * DAY)));
+        let windows = WalkForward::new(9 * DAY, 3 * DAY, true)
+            .unwrap()
+            .windows(&ticks);
+        assert!(windows.is_empty());
+    }
+
+    #[test]
+    fn walk_forward_run() {
+        let ticks = _ticks_new(&[
+            1_000, 1_100, 1_200, 1_300, 1_200, 1_100, 1_000, 1_100, 1_200,
+        ]);
+        let parameter_sweep = ParameterSweep::new(
+            vec![Parameter::new(String::from("multiplier"), 0f64, 2f64, 3).unwrap()],
+            SearchMethod::Grid,
+            Objective::TotalReturn,
+        )
+        .unwrap();
+        let walk_forward = WalkForward::new(4 * DAY, 2 * DAY, false).unwrap();
+        let report = walk_forward
+            .run(&ticks, &parameter_sweep, &_backtest_new(), _make_strategy)
+            .unwrap();
+        assert_eq!(report.windows.len(), 2);
+        // tuned on a rising market, fully exposed out of sample
+        assert_eq!(report.windows[0].parameters, vec![2f64]);
+        assert_eq!(report.equity_curve.len(), 4);
+        assert_eq!(report.equity_curve[0], (4 * DAY, 10_000f64));
+        // bought at 1_200, rebalanced at 1_100 to 2 * (value - 5_000) of risky asset
+        let value = 10_000f64 / 1_200f64 * 1_100f64;
+        let risky_value = 2f64 * (value - 5_000f64);
+        assert!((report.equity_curve[1].1 - value).abs() < 1e-9);
+        // the second window starts with that portfolio, the 1_100 => 1_000 move is kept
+        let value = risky_value / 1_100f64 * 1_000f64 + value - risky_value;
+        assert_eq!(report.equity_curve[2].0, 6 * DAY);
+        assert!((report.equity_curve[2].1 - value).abs() < 1e-9);
+        let total_return = report.equity_curve[3].1 / 10_000f64 - 1f64;
+        assert!((report.performance.total_return - total_return).abs() < 1e-9);
+        assert!(report.performance.turnover > 0f64);
+
+        let walk_forward = WalkForward::new(8 * DAY, 2 * DAY, false).unwrap();
+        let report = walk_forward.run(&ticks, &parameter_sweep, &_backtest_new(), _make_strategy);
+        assert_eq!(report.unwrap_err(), WalkForwardError::WindowNone);
+
+        let walk_forward = WalkForward::new(u64::MAX, 2 * DAY, false).unwrap();
+        let report = walk_forward.run(&ticks, &parameter_sweep, &_backtest_new(), _make_strategy);
+        assert_eq!(report.unwrap_err(), WalkForwardError::WindowNone);
+    }
+
+    #[test]
+    fn walk_forward_run_tick_gap() {
+        let mut ticks = _ticks_new(&[1_000, 1_100, 1_200, 1_300, 1_200, 1_100]);
+        ticks.extend(_ticks_new(&[1_000; 18]).into_iter().skip(12));
+        let parameter_sweep = ParameterSweep::new(
+            vec![Parameter::new(String::from("multiplier"), 0f64, 2f64, 3).unwrap()],
+            SearchMethod::Random {
+                sample_count: 2,
+                seed: 42,
+            },
+            Objective::TotalReturn,
+        )
+        .unwrap();
+        let walk_forward = WalkForward::new(4 * DAY, 2 * DAY, false).unwrap();
+        let windows = walk_forward.windows(&ticks);
+        assert_eq!(
+            windows,
+            vec![
+                ((0, 4 * DAY), (4 * DAY, 6 * DAY)),
+                ((10 * DAY, 14 * DAY), (14 * DAY, 16 * DAY)),
+            ]
+        );
+        let report = walk_forward.run(&ticks, &parameter_sweep, &_backtest_new(), _make_strategy);
+        assert!(report.is_ok());
+        assert_eq!(report.unwrap().windows.len(), 2);
+
+        let windows = WalkForward::new(DAY, u64::MAX, false)
+            .unwrap()
+            .windows(&ticks);
+        assert!(windows.is_empty());
+    }
+}
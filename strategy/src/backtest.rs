@@ -0,0 +1,374 @@
+use crate::execution_cost::{CostModel, ExecutionCost};
+use crate::market::Tick;
+use crate::order::MarketOrder;
+use crate::performance::{BenchmarkComparison, PerformanceError, PerformanceReport};
+use crate::portfolio::{Portfolio, PortfolioError, PortfolioStrategy, PortfolioStrategyError};
+use thiserror::Error;
+
+type Safe = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum BacktestError {
+    #[error("Static mix risky weight should be between 0 and 1({0})")]
+    StaticMixRiskyWeightIncorrect(f64),
+    #[error("Strategy error {0}")]
+    Strategy(PortfolioStrategyError),
+    #[error("Portfolio error {0}")]
+    Portfolio(PortfolioError),
+    #[error("Performance error {0}")]
+    Performance(PerformanceError),
+}
+
+/// Reference portfolio run on the same ticks as the strategy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BenchmarkPortfolio {
+    /// All the initial value in risky asset.
+    BuyAndHold,
+    /// All the initial value in safe asset.
+    HoldSafe,
+    /// Initial value split with risky_weight in risky asset, never rebalanced.
+    StaticMix(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchmarkReport {
+    pub benchmark: BenchmarkPortfolio,
+    pub equity_curve: Vec<(u64, Safe)>,
+    pub performance: PerformanceReport,
+    pub comparison: BenchmarkComparison,
+}
+
+/// Strategy run tick by tick, orders filled at the tick price, costs paid from the asset
+/// bought. portfolio is the one held after the last tick, traded_notional the value of all orders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+    pub equity_curve: Vec<(u64, Safe)>,
+    pub orders: Vec<(u64, MarketOrder)>,
+    pub cost: ExecutionCost,
+    pub portfolio: Portfolio,
+    pub traded_notional: Safe,
+    pub performance: PerformanceReport,
+    pub benchmarks: Vec<BenchmarkReport>,
+}
+
+/// Config to backtest a strategy from an initial portfolio, against benchmarks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backtest {
+    pub portfolio: Portfolio,
+    pub price_decimal_shift: usize,
+    pub risk_free_rate: f64,
+    pub benchmarks: Vec<BenchmarkPortfolio>,
+}
+
+impl BenchmarkPortfolio {
+    pub fn name(&self) -> String {
+        match self {
+            BenchmarkPortfolio::BuyAndHold => String::from("Buy and hold"),
+            BenchmarkPortfolio::HoldSafe => String::from("Hold safe"),
+            BenchmarkPortfolio::StaticMix(i) => format!("Static mix {:.0}%", i * 100f64),
+        }
+    }
+
+    /// The benchmark holding of the initial portfolio value.
+    pub fn portfolio(&self, portfolio: &Portfolio, risky_price: Safe) -> Portfolio {
+        let value = portfolio.value(risky_price);
+        let risky_weight = match self {
+            BenchmarkPortfolio::BuyAndHold => 1f64,
+            BenchmarkPortfolio::HoldSafe => 0f64,
+            BenchmarkPortfolio::StaticMix(i) => *i,
+        };
+        Portfolio::new(
+            portfolio.risky_asset.clone(),
+            portfolio.safe_asset.clone(),
+            value * risky_weight / risky_price,
+            value * (1f64 - risky_weight),
+        )
+    }
+}
+
+impl Backtest {
+    /// Benchmarks are buy and hold, hold safe and a 50/50 static mix.
+    pub fn new(portfolio: Portfolio, price_decimal_shift: usize, risk_free_rate: f64) -> Self {
+        Self {
+            portfolio,
+            price_decimal_shift,
+            risk_free_rate,
+            benchmarks: vec![
+                BenchmarkPortfolio::BuyAndHold,
+                BenchmarkPortfolio::HoldSafe,
+                BenchmarkPortfolio::StaticMix(0.5f64),
+            ],
+        }
+    }
+
+    pub fn with_benchmarks(
+        mut self,
+        benchmarks: Vec<BenchmarkPortfolio>,
+    ) -> Result<Self, BacktestError> {
+        if let Some(BenchmarkPortfolio::StaticMix(i)) = benchmarks
+            .iter()
+            .find(|i| matches!(i, BenchmarkPortfolio::StaticMix(j) if !(0f64..=1f64).contains(j)))
+        {
+            return Err(BacktestError::StaticMixRiskyWeightIncorrect(*i));
+        }
+        self.benchmarks = benchmarks;
+        Ok(self)
+    }
+
+    /// Orders are filled at the tick price, the market depth price impact isn't simulated.
+    pub fn run(
+        &self,
+        ticks: &[Tick],
+        strategy: &mut dyn PortfolioStrategy,
+        cost_model: Option<&dyn CostModel>,
+    ) -> Result<BacktestReport, BacktestError> {
+        let mut portfolio = self.portfolio.clone();
+        let mut equity_curve = vec![];
+        let mut orders = vec![];
+        let mut cost = ExecutionCost::default();
+        let mut traded_notional = 0f64;
+        for tick in ticks {
+            let risky_price = tick.price_as_f64(self.price_decimal_shift);
+            let order = strategy
+                .check_new_order(tick, risky_price, &portfolio)
+                .map_err(BacktestError::Strategy)?;
+            if let Some(order) = order {
+                let notional = if order.asset_sell == portfolio.safe_asset {
+                    order.quantity_sell
+                } else {
+                    order.quantity_sell * risky_price
+                };
+                portfolio
+                    .apply_order(&order, risky_price)
+                    .map_err(BacktestError::Portfolio)?;
+                if let Some(cost_model) = cost_model {
+                    let order_cost = cost_model.cost(notional);
+                    let cost_bought = if order.asset_buy == portfolio.safe_asset {
+                        order_cost.total()
+                    } else {
+                        order_cost.total() / risky_price
+                    };
+                    portfolio
+                        .pay_cost(&order.asset_buy, cost_bought)
+                        .map_err(BacktestError::Portfolio)?;
+                    cost.add(&order_cost);
+                }
+                traded_notional += notional;
+                orders.push((tick.time, order));
+            }
+            push_equity(&mut equity_curve, tick.time, portfolio.value(risky_price));
+        }
+        let performance =
+            PerformanceReport::new(&equity_curve, traded_notional, self.risk_free_rate, None)
+                .map_err(BacktestError::Performance)?;
+        let benchmarks = self
+            .benchmarks
+            .iter()
+            .map(|i| self._run_benchmark(i, ticks, &equity_curve))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BacktestReport {
+            equity_curve,
+            orders,
+            cost,
+            portfolio,
+            traded_notional,
+            performance,
+            benchmarks,
+        })
+    }
+
+    fn _run_benchmark(
+        &self,
+        benchmark: &BenchmarkPortfolio,
+        ticks: &[Tick],
+        strategy_equity_curve: &[(u64, Safe)],
+    ) -> Result<BenchmarkReport, BacktestError> {
+        let first_price = ticks
+            .first()
+            .map_or(1f64, |i| i.price_as_f64(self.price_decimal_shift));
+        let portfolio = benchmark.portfolio(&self.portfolio, first_price);
+        let mut equity_curve = vec![];
+        for tick in ticks {
+            let risky_price = tick.price_as_f64(self.price_decimal_shift);
+            push_equity(&mut equity_curve, tick.time, portfolio.value(risky_price));
+        }
+        let performance = PerformanceReport::new(&equity_curve, 0f64, self.risk_free_rate, None)
+            .map_err(BacktestError::Performance)?;
+        let comparison = BenchmarkComparison::new(strategy_equity_curve, &equity_curve)
+            .map_err(BacktestError::Performance)?;
+        Ok(BenchmarkReport {
+            benchmark: benchmark.clone(),
+            equity_curve,
+            performance,
+            comparison,
+        })
+    }
+}
+
+/// Ticks of the same trade share their time, only the last value is kept.
+fn push_equity(equity_curve: &mut Vec<(u64, Safe)>, time: u64, value: Safe) {
+    match equity_curve.last_mut() {
+        Some(last) if last.0 == time => last.1 = value,
+        _ => equity_curve.push((time, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::execution_cost::FeeAndGasCostModel;
+    use ethers::types::U64;
+
+    struct _BuyOnceStrategy;
+
+    impl PortfolioStrategy for _BuyOnceStrategy {
+        fn check_new_order(
+            &mut self,
+            _tick: &Tick,
+            _risky_price: Safe,
+            portfolio: &Portfolio,
+        ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+            let is_buy = portfolio.safe_quantity > 1_000f64;
+            Ok(is_buy.then(|| {
+                MarketOrder::new(
+                    portfolio.safe_asset.clone(),
+                    portfolio.risky_asset.clone(),
+                    1_000f64,
+                )
+            }))
+        }
+    }
+
+    struct _BuyAllStrategy;
+
+    impl PortfolioStrategy for _BuyAllStrategy {
+        fn check_new_order(
+            &mut self,
+            _tick: &Tick,
+            _risky_price: Safe,
+            portfolio: &Portfolio,
+        ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+            Ok((portfolio.safe_quantity > 0f64).then(|| {
+                MarketOrder::new(
+                    portfolio.safe_asset.clone(),
+                    portfolio.risky_asset.clone(),
+                    portfolio.safe_quantity,
+                )
+            }))
+        }
+    }
+
+    fn _backtest_new() -> Backtest {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Backtest::new(
+            Portfolio::new(risky_asset, safe_asset, 0f64, 2_000f64),
+            0,
+            0f64,
+        )
+    }
+
+    fn _ticks_new(prices: &[u64]) -> Vec<Tick> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, j)| {
+                Tick::new(
+                    U64::from(*j),
+                    i as u64 * 86_400_000,
+                    U64::one(),
+                    true,
+                    None,
+                    None,
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn backtest_with_benchmarks_incorrect() {
+        let backtest = _backtest_new().with_benchmarks(vec![BenchmarkPortfolio::StaticMix(2f64)]);
+        assert!(backtest.is_err());
+        assert_eq!(
+            backtest.unwrap_err(),
+            BacktestError::StaticMixRiskyWeightIncorrect(2f64)
+        );
+    }
+
+    #[test]
+    fn backtest_run() {
+        let backtest = _backtest_new();
+        let ticks = _ticks_new(&[1_000, 1_100, 1_200]);
+        let report = backtest.run(&ticks, &mut _BuyOnceStrategy, None).unwrap();
+        assert_eq!(report.orders.len(), 1);
+        assert_eq!(
+            report.equity_curve,
+            vec![
+                (0, 2_000f64),
+                (86_400_000, 2_100f64),
+                (172_800_000, 2_200f64)
+            ]
+        );
+        assert!((report.performance.total_return - 0.1f64).abs() < 1e-12);
+
+        let names: Vec<String> = report
+            .benchmarks
+            .iter()
+            .map(|i| i.benchmark.name())
+            .collect();
+        assert_eq!(names, vec!["Buy and hold", "Hold safe", "Static mix 50%"]);
+        // the strategy is a 50/50 static mix
+        assert_eq!(report.benchmarks[2].equity_curve, report.equity_curve);
+        assert!(report.benchmarks[2].comparison.excess_return.abs() < 1e-12);
+        assert!((report.benchmarks[0].performance.total_return - 0.2f64).abs() < 1e-12);
+        assert!((report.benchmarks[0].comparison.upside_capture - 0.5f64).abs() < 0.05f64);
+        assert_eq!(report.benchmarks[1].performance.total_return, 0f64);
+    }
+
+    #[test]
+    fn backtest_run_cost() {
+        let backtest = _backtest_new().with_benchmarks(vec![]).unwrap();
+        let cost_model = FeeAndGasCostModel::new(0.01f64, 5f64, 0f64, 0, 0f64).unwrap();
+        let ticks = _ticks_new(&[1_000, 1_000]);
+        let report = backtest
+            .run(&ticks, &mut _BuyOnceStrategy, Some(&cost_model))
+            .unwrap();
+        assert_eq!(report.cost, ExecutionCost::new(10f64, 5f64, 0f64));
+        assert_eq!(report.equity_curve[1].1, 1_985f64);
+        assert!(report.benchmarks.is_empty());
+    }
+
+    #[test]
+    fn backtest_run_cost_whole_balance() {
+        let backtest = _backtest_new().with_benchmarks(vec![]).unwrap();
+        let cost_model = FeeAndGasCostModel::new(0.01f64, 5f64, 0f64, 0, 0f64).unwrap();
+        let ticks = _ticks_new(&[1_000, 1_000, 1_100]);
+        let report = backtest
+            .run(&ticks, &mut _BuyAllStrategy, Some(&cost_model))
+            .unwrap();
+        assert_eq!(report.orders.len(), 1);
+        assert_eq!(report.cost, ExecutionCost::new(20f64, 5f64, 0f64));
+        assert_eq!(report.portfolio.safe_quantity, 0f64);
+        assert!((report.portfolio.risky_quantity - 1.975f64).abs() < 1e-12);
+        assert!((report.equity_curve[2].1 - 2_172.5f64).abs() < 1e-9);
+    }
+
+    #[test]
+    fn backtest_run_same_time_ticks() {
+        let backtest = _backtest_new();
+        let mut ticks = _ticks_new(&[1_000, 1_100]);
+        ticks.insert(
+            1,
+            Tick::new(U64::from(1_050), 0, U64::one(), true, None, None).unwrap(),
+        );
+        let report = backtest.run(&ticks, &mut _BuyOnceStrategy, None).unwrap();
+        assert_eq!(
+            report.equity_curve,
+            vec![(0, 2_050f64), (86_400_000, 2_100f64)]
+        );
+        assert_eq!(report.benchmarks[0].equity_curve[0], (0, 2_100f64));
+    }
+}
@@ -0,0 +1,405 @@
+use crate::market::Tick;
+use crate::order::MarketOrder;
+use crate::portfolio::{Portfolio, PortfolioStrategy, PortfolioStrategyError};
+use std::collections::VecDeque;
+use thiserror::Error;
+
+type Safe = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CircuitBreakerError {
+    #[error("Max drawdown should be between 0 and 1({0})")]
+    MaxDrawdownIncorrect(f64),
+    #[error("Max window loss should be between 0 and 1({0})")]
+    MaxWindowLossIncorrect(f64),
+    #[error("Window duration should be greater than 0({0})")]
+    WindowDurationShouldBeGtZero(u64),
+    #[error("Portfolio value can't be negative({0})")]
+    PortfolioValueIsNegative(f64),
+    #[error("Circuit breaker is not halted")]
+    NotHalted,
+}
+
+/// Why the circuit breaker halted, drawdown and loss are ratios (0.1 = 10%).
+#[derive(Debug, Clone, PartialEq)]
+pub enum CircuitBreakerTrigger {
+    Drawdown { drawdown: f64, max: f64 },
+    WindowLoss { loss: f64, max: f64 },
+    Manual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CircuitBreakerState {
+    Running,
+    Halted {
+        trigger: CircuitBreakerTrigger,
+        time: u64,
+    },
+}
+
+/// Watches the portfolio value, halts once the drawdown from the high-water mark
+/// or the loss over window_duration (ms) is too high, until re_enable is called.
+/// With is_liquidate, the risky asset is sold for the safe asset on every update while halted,
+/// until none is left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreaker {
+    pub max_drawdown: Option<f64>,
+    pub max_window_loss: Option<f64>,
+    pub window_duration: u64,
+    pub is_liquidate: bool,
+    pub high_water_mark: Safe,
+    pub values: VecDeque<(u64, Safe)>,
+    pub state: CircuitBreakerState,
+}
+
+/// A strategy sending no more orders once its circuit breaker is halted.
+pub struct GuardedStrategy {
+    pub circuit_breaker: CircuitBreaker,
+    pub strategy: Box<dyn PortfolioStrategy>,
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        max_drawdown: Option<f64>,
+        max_window_loss: Option<f64>,
+        window_duration: u64,
+        is_liquidate: bool,
+    ) -> Result<Self, CircuitBreakerError> {
+        if let Some(i) = max_drawdown.filter(|i| *i <= 0f64 || *i > 1f64) {
+            return Err(CircuitBreakerError::MaxDrawdownIncorrect(i));
+        }
+        if let Some(i) = max_window_loss.filter(|i| *i <= 0f64 || *i > 1f64) {
+            return Err(CircuitBreakerError::MaxWindowLossIncorrect(i));
+        }
+        if max_window_loss.is_some() && window_duration == 0 {
+            return Err(CircuitBreakerError::WindowDurationShouldBeGtZero(
+                window_duration,
+            ));
+        }
+        Ok(Self {
+            max_drawdown,
+            max_window_loss,
+            window_duration,
+            is_liquidate,
+            high_water_mark: 0f64,
+            values: VecDeque::new(),
+            state: CircuitBreakerState::Running,
+        })
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.state != CircuitBreakerState::Running
+    }
+
+    /// Records the portfolio value at time, returns the liquidation order while halted.
+    pub fn update(
+        &mut self,
+        time: u64,
+        portfolio: &Portfolio,
+        risky_price: Safe,
+    ) -> Result<Option<MarketOrder>, CircuitBreakerError> {
+        let value = portfolio.value(risky_price);
+        if value < 0f64 {
+            return Err(CircuitBreakerError::PortfolioValueIsNegative(value));
+        }
+        self.high_water_mark = self.high_water_mark.max(value);
+        self.values.push_back((time, value));
+        while self
+            .values
+            .front()
+            .is_some_and(|(i, _)| i.saturating_add(self.window_duration) < time)
+        {
+            self.values.pop_front();
+        }
+        if self.is_halted() {
+            return Ok(self._liquidation_order(portfolio));
+        }
+
+        let trigger = match (self.max_drawdown, self.max_window_loss) {
+            (Some(max), _) if self.drawdown() > max => Some(CircuitBreakerTrigger::Drawdown {
+                drawdown: self.drawdown(),
+                max,
+            }),
+            (_, Some(max)) if self.window_loss() > max => Some(CircuitBreakerTrigger::WindowLoss {
+                loss: self.window_loss(),
+                max,
+            }),
+            _ => None,
+        };
+        match trigger {
+            Some(trigger) => Ok(self._halt(trigger, time, portfolio)),
+            None => Ok(None),
+        }
+    }
+
+    /// Kill switch, returns the liquidation order.
+    pub fn halt(&mut self, time: u64, portfolio: &Portfolio) -> Option<MarketOrder> {
+        self._halt(CircuitBreakerTrigger::Manual, time, portfolio)
+    }
+
+    /// Restarts after a halt, the drawdown and window loss are measured from now.
+    pub fn re_enable(&mut self) -> Result<(), CircuitBreakerError> {
+        if !self.is_halted() {
+            return Err(CircuitBreakerError::NotHalted);
+        }
+        let last_value = self.values.back().cloned();
+        self.values.clear();
+        self.values.extend(last_value);
+        self.high_water_mark = last_value.map_or(0f64, |(_, i)| i);
+        self.state = CircuitBreakerState::Running;
+        Ok(())
+    }
+
+    /// Loss from the high-water mark to the last value.
+    pub fn drawdown(&self) -> f64 {
+        match self.values.back() {
+            Some((_, i)) if self.high_water_mark > 0f64 => 1f64 - i / self.high_water_mark,
+            _ => 0f64,
+        }
+    }
+
+    /// Loss from the highest value of the window to the last value.
+    pub fn window_loss(&self) -> f64 {
+        let max_value = self.values.iter().map(|(_, i)| *i).fold(0f64, f64::max);
+        match self.values.back() {
+            Some((_, i)) if max_value > 0f64 => 1f64 - i / max_value,
+            _ => 0f64,
+        }
+    }
+
+    fn _halt(
+        &mut self,
+        trigger: CircuitBreakerTrigger,
+        time: u64,
+        portfolio: &Portfolio,
+    ) -> Option<MarketOrder> {
+        self.state = CircuitBreakerState::Halted { trigger, time };
+        self._liquidation_order(portfolio)
+    }
+
+    fn _liquidation_order(&self, portfolio: &Portfolio) -> Option<MarketOrder> {
+        let is_liquidation = self.is_liquidate && portfolio.risky_quantity > 0f64;
+        is_liquidation.then(|| {
+            MarketOrder::new(
+                portfolio.risky_asset.clone(),
+                portfolio.safe_asset.clone(),
+                portfolio.risky_quantity,
+            )
+        })
+    }
+}
+
+impl GuardedStrategy {
+    pub fn new(circuit_breaker: CircuitBreaker, strategy: Box<dyn PortfolioStrategy>) -> Self {
+        Self {
+            circuit_breaker,
+            strategy,
+        }
+    }
+}
+
+impl PortfolioStrategy for GuardedStrategy {
+    fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        risky_price: Safe,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+        let is_halted = self.circuit_breaker.is_halted();
+        let liquidation_order = self
+            .circuit_breaker
+            .update(tick.time, portfolio, risky_price)
+            .map_err(PortfolioStrategyError::CircuitBreaker)?;
+        if is_halted || self.circuit_breaker.is_halted() {
+            return Ok(liquidation_order);
+        }
+        self.strategy.check_new_order(tick, risky_price, portfolio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::strategy_composer::{SleeveAllocation, StrategyComposer};
+    use ethers::types::U64;
+
+    struct _BuyStrategy;
+
+    impl PortfolioStrategy for _BuyStrategy {
+        fn check_new_order(
+            &mut self,
+            _tick: &Tick,
+            _risky_price: Safe,
+            portfolio: &Portfolio,
+        ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+            Ok(Some(MarketOrder::new(
+                portfolio.safe_asset.clone(),
+                portfolio.risky_asset.clone(),
+                1f64,
+            )))
+        }
+    }
+
+    fn _portfolio_new() -> Portfolio {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Portfolio::new(risky_asset, safe_asset, 1f64, 0f64)
+    }
+
+    #[test]
+    fn circuit_breaker_new_incorrect() {
+        let circuit_breaker = CircuitBreaker::new(Some(0f64), None, 0, false);
+        assert!(circuit_breaker.is_err());
+        assert_eq!(
+            circuit_breaker.unwrap_err(),
+            CircuitBreakerError::MaxDrawdownIncorrect(0f64)
+        );
+        let circuit_breaker = CircuitBreaker::new(None, Some(0.1f64), 0, false);
+        assert_eq!(
+            circuit_breaker.unwrap_err(),
+            CircuitBreakerError::WindowDurationShouldBeGtZero(0)
+        );
+    }
+
+    #[test]
+    fn circuit_breaker_drawdown() {
+        let mut circuit_breaker = CircuitBreaker::new(Some(0.2f64), None, 0, true).unwrap();
+        let portfolio = _portfolio_new();
+        assert_eq!(circuit_breaker.update(0, &portfolio, 1_000f64), Ok(None));
+        assert_eq!(circuit_breaker.update(1, &portfolio, 1_200f64), Ok(None));
+        assert_eq!(circuit_breaker.update(2, &portfolio, 1_000f64), Ok(None));
+        let order = circuit_breaker.update(3, &portfolio, 900f64).unwrap();
+        assert_eq!(
+            order,
+            Some(MarketOrder::new(
+                portfolio.risky_asset.clone(),
+                portfolio.safe_asset.clone(),
+                1f64
+            ))
+        );
+        assert_eq!(
+            circuit_breaker.state,
+            CircuitBreakerState::Halted {
+                trigger: CircuitBreakerTrigger::Drawdown {
+                    drawdown: 0.25f64,
+                    max: 0.2f64
+                },
+                time: 3
+            }
+        );
+        // stays halted even if the value recovers, liquidating until no risky asset is left
+        assert_eq!(circuit_breaker.update(4, &portfolio, 1_300f64), Ok(order));
+        assert!(circuit_breaker.is_halted());
+        let liquidated_portfolio = Portfolio::new(
+            portfolio.risky_asset.clone(),
+            portfolio.safe_asset.clone(),
+            0f64,
+            1_300f64,
+        );
+        assert_eq!(
+            circuit_breaker.update(5, &liquidated_portfolio, 1_300f64),
+            Ok(None)
+        );
+        assert!(circuit_breaker.re_enable().is_ok());
+        assert!(!circuit_breaker.is_halted());
+        assert_eq!(circuit_breaker.high_water_mark, 1_300f64);
+        assert_eq!(
+            circuit_breaker.re_enable(),
+            Err(CircuitBreakerError::NotHalted)
+        );
+    }
+
+    #[test]
+    fn circuit_breaker_window_loss() {
+        let mut circuit_breaker = CircuitBreaker::new(None, Some(0.1f64), 10, false).unwrap();
+        let portfolio = _portfolio_new();
+        assert_eq!(circuit_breaker.update(0, &portfolio, 1_000f64), Ok(None));
+        assert_eq!(circuit_breaker.update(10, &portfolio, 950f64), Ok(None));
+        // 1_000 is out of the window, loss is 910 / 950
+        assert_eq!(circuit_breaker.update(20, &portfolio, 910f64), Ok(None));
+        assert!(!circuit_breaker.is_halted());
+        assert_eq!(circuit_breaker.update(21, &portfolio, 800f64), Ok(None));
+        assert!(circuit_breaker.is_halted());
+    }
+
+    #[test]
+    fn circuit_breaker_window_duration_max() {
+        let mut circuit_breaker = CircuitBreaker::new(None, Some(0.1f64), u64::MAX, false).unwrap();
+        let portfolio = _portfolio_new();
+        assert_eq!(circuit_breaker.update(10, &portfolio, 1_000f64), Ok(None));
+        assert_eq!(circuit_breaker.update(20, &portfolio, 950f64), Ok(None));
+        assert_eq!(circuit_breaker.values.len(), 2);
+    }
+
+    #[test]
+    fn guarded_strategy_check_new_order() {
+        let circuit_breaker = CircuitBreaker::new(Some(0.5f64), None, 0, true).unwrap();
+        let mut strategy = GuardedStrategy::new(circuit_breaker, Box::new(_BuyStrategy));
+        let portfolio = _portfolio_new();
+        let tick = Tick::new(U64::from(1_000), 0, U64::one(), true, None, None).unwrap();
+
+        let order = strategy.check_new_order(&tick, 1_000f64, &portfolio);
+        assert_eq!(order.unwrap().unwrap().asset_sell, portfolio.safe_asset);
+        let order = strategy.check_new_order(&tick, 400f64, &portfolio);
+        assert_eq!(order.unwrap().unwrap().asset_sell, portfolio.risky_asset);
+        // the liquidation wasn't filled, it is sent again
+        let order = strategy.check_new_order(&tick, 1_000f64, &portfolio);
+        assert_eq!(order.unwrap().unwrap().asset_sell, portfolio.risky_asset);
+        let liquidated_portfolio = Portfolio::new(
+            portfolio.risky_asset.clone(),
+            portfolio.safe_asset.clone(),
+            0f64,
+            400f64,
+        );
+        let order = strategy.check_new_order(&tick, 1_000f64, &liquidated_portfolio);
+        assert_eq!(order, Ok(None));
+        // kill switch on a running strategy
+        strategy.circuit_breaker.re_enable().unwrap();
+        assert!(strategy.circuit_breaker.halt(1, &portfolio).is_some());
+        let order = strategy.check_new_order(&tick, 1_000f64, &liquidated_portfolio);
+        assert_eq!(order, Ok(None));
+    }
+
+    #[test]
+    fn guarded_strategy_check_new_order_composer() {
+        let portfolio = Portfolio::new(
+            Asset::new(String::from("ETH"), String::from("Ether")),
+            Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+            1f64,
+            1_000f64,
+        );
+        let mut composer =
+            StrategyComposer::new(portfolio.risky_asset.clone(), portfolio.safe_asset.clone());
+        for name in ["first", "second"] {
+            composer
+                .add_sleeve(
+                    String::from(name),
+                    SleeveAllocation::Weight(0.5f64),
+                    Box::new(_BuyStrategy),
+                )
+                .unwrap();
+        }
+        let circuit_breaker = CircuitBreaker::new(Some(0.2f64), None, 0, false).unwrap();
+        let mut strategy = GuardedStrategy::new(circuit_breaker, Box::new(composer));
+        let tick = Tick::new(U64::from(1_000), 0, U64::one(), true, None, None).unwrap();
+
+        // both sleeves buy, netted into one order
+        let order = strategy.check_new_order(&tick, 1_000f64, &portfolio);
+        assert_eq!(
+            order,
+            Ok(Some(MarketOrder::new(
+                portfolio.safe_asset.clone(),
+                portfolio.risky_asset.clone(),
+                2f64
+            )))
+        );
+        // drawdown is 0.3, every sleeve is halted
+        let order = strategy.check_new_order(&tick, 400f64, &portfolio);
+        assert_eq!(order, Ok(None));
+        assert!(strategy.circuit_breaker.is_halted());
+        let order = strategy.check_new_order(&tick, 1_000f64, &portfolio);
+        assert_eq!(order, Ok(None));
+    }
+}
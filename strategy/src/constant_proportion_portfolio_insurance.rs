@@ -1,5 +1,7 @@
 use crate::asset::Asset;
+use crate::market::Tick;
 use crate::order::MarketOrder;
+use crate::portfolio::{Portfolio, PortfolioStrategy, PortfolioStrategyError};
 use thiserror::Error;
 
 type Safe = f64;
@@ -86,6 +88,23 @@ impl ConstantProportionPortfolioInsurance {
     }
 }
 
+impl PortfolioStrategy for ConstantProportionPortfolioInsurance {
+    fn check_new_order(
+        &mut self,
+        _tick: &Tick,
+        risky_price: Safe,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+        ConstantProportionPortfolioInsurance::check_new_order(
+            self,
+            portfolio.risky_quantity,
+            portfolio.safe_quantity,
+            risky_price,
+        )
+        .map_err(PortfolioStrategyError::ConstantProportionPortfolioInsurance)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,22 +1,188 @@
 use crate::asset::Asset;
+use crate::market::Tick;
 use crate::order::MarketOrder;
+use crate::portfolio::{Portfolio, PortfolioStrategy, PortfolioStrategyError};
 use chrono::{prelude::*, Duration};
 use thiserror::Error;
 
+/// Relative float error tolerated on the budget spent.
+const BUDGET_TOLERANCE: f64 = 1e-9;
+
 #[derive(Error, Debug, PartialEq)]
 pub enum DollarCostAveragingError {
     #[error("Not enough asset to sell {0} >= {1}")]
     SellAssetBalanceNotEnough(f64, f64),
     #[error("Need to wait {0} >= {1}")]
     NeedToWait(DateTime<Utc>, DateTime<Utc>),
+    #[error("Interval sell quantity range should be positive and first entry smaller than second ({0} => {1})")]
+    IntervalSellQuantityRangeIncorrect(f64, f64),
+    #[error("Total budget should be greater than 0({0})")]
+    TotalBudgetShouldBeGtZero(f64),
+    #[error("Installment count should be greater than 0({0})")]
+    InstallmentCountShouldBeGtZero(u32),
+    #[error("Plan is completed, no more order to make.")]
+    PlanCompleted,
+    #[error("Need a tick to scale the order.")]
+    TickNone,
+    #[error("Need the moving average of the tick to scale the order.")]
+    TickMovingAverageNone,
+    #[error("Need the variance of the tick to scale the order.")]
+    TickVarianceNone,
+    #[error("Tick time is out of the datetime range({0})")]
+    TickTimeIncorrect(u64),
+    #[error("Interval sell quantity should be within the limits ({0} not in {1} => {2})")]
+    IntervalSellQuantityOutOfLimits(f64, f64, f64),
+    #[error("Sensitivity should be finite({0})")]
+    SensitivityNotFinite(f64),
+    #[error("Reference volatility should be greater than 0({0})")]
+    ReferenceVolatilityShouldBeGtZero(f64),
+    #[error("Window tick count should be greater than 1({0})")]
+    WindowTickCountShouldBeGtOne(usize),
+}
+
+/// How the quantity sold each interval is scaled from interval_sell_quantity.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DollarCostAveragingScaling {
+    /// Always sell interval_sell_quantity.
+    Fixed,
+    /// Sell more when the price is under the moving average, less when it is above.
+    /// quantity * (1 + sensitivity * (moving_average - price) / moving_average)
+    MovingAverage { sensitivity: f64 },
+    /// Sell less when the market is volatile, volatility is the return volatility by tick of
+    /// Tick::return_volatility over the window_tick_count ticks of the simulator moving average.
+    /// quantity * (reference_volatility / volatility)
+    Volatility {
+        reference_volatility: f64,
+        window_tick_count: usize,
+    },
+}
+
+/// Caps applied on the scaled quantity of each interval, built with new so min <= max.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DollarCostAveragingLimits {
+    min_interval_sell_quantity: f64,
+    max_interval_sell_quantity: f64,
+}
+
+/// When the plan is over, every bound is optional and the first one reached complete the plan.
+/// With is_final_partial_order the last order sell what is left of the balance instead of stalling.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DollarCostAveragingPlan {
+    pub total_budget: Option<f64>,
+    pub installment_count: Option<u32>,
+    pub end_datetime: Option<DateTime<Utc>>,
+    pub is_final_partial_order: bool,
+}
+
+/// Active can still make orders, Exhausted ran out of asset to sell, Completed reached an end of the plan.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DollarCostAveragingStatus {
+    Active,
+    Exhausted,
+    Completed,
+}
+
+/// What the plan already did, kept by the caller between two checks.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DollarCostAveragingProgress {
+    pub last_position_datetime: Option<DateTime<Utc>>,
+    pub installment_count: u32,
+    pub spent_quantity: f64,
+}
+
+impl DollarCostAveragingLimits {
+    pub fn new(
+        min_interval_sell_quantity: f64,
+        max_interval_sell_quantity: f64,
+    ) -> Result<Self, DollarCostAveragingError> {
+        let is_min_positive = min_interval_sell_quantity >= 0f64;
+        let is_range_ascending = min_interval_sell_quantity <= max_interval_sell_quantity;
+        if !(is_min_positive && is_range_ascending) {
+            return Err(
+                DollarCostAveragingError::IntervalSellQuantityRangeIncorrect(
+                    min_interval_sell_quantity,
+                    max_interval_sell_quantity,
+                ),
+            );
+        }
+
+        Ok(Self {
+            min_interval_sell_quantity,
+            max_interval_sell_quantity,
+        })
+    }
+
+    pub fn min_interval_sell_quantity(&self) -> f64 {
+        self.min_interval_sell_quantity
+    }
+
+    pub fn max_interval_sell_quantity(&self) -> f64 {
+        self.max_interval_sell_quantity
+    }
+}
+
+impl DollarCostAveragingPlan {
+    pub fn new(
+        total_budget: Option<f64>,
+        installment_count: Option<u32>,
+        end_datetime: Option<DateTime<Utc>>,
+        is_final_partial_order: bool,
+    ) -> Result<Self, DollarCostAveragingError> {
+        if let Some(total_budget) = total_budget {
+            if total_budget <= 0f64 {
+                return Err(DollarCostAveragingError::TotalBudgetShouldBeGtZero(
+                    total_budget,
+                ));
+            }
+        }
+        if let Some(installment_count) = installment_count {
+            if installment_count == 0 {
+                return Err(DollarCostAveragingError::InstallmentCountShouldBeGtZero(
+                    installment_count,
+                ));
+            }
+        }
+
+        Ok(Self {
+            total_budget,
+            installment_count,
+            end_datetime,
+            is_final_partial_order,
+        })
+    }
+}
+
+impl DollarCostAveragingProgress {
+    pub fn new(
+        last_position_datetime: Option<DateTime<Utc>>,
+        installment_count: u32,
+        spent_quantity: f64,
+    ) -> Self {
+        Self {
+            last_position_datetime,
+            installment_count,
+            spent_quantity,
+        }
+    }
+
+    pub fn record_order(&mut self, position_datetime: DateTime<Utc>, quantity_sell: f64) {
+        self.last_position_datetime = Some(position_datetime);
+        self.installment_count += 1;
+        self.spent_quantity += quantity_sell;
+    }
 }
 
 /// Periodically sell the same amount of an asset A to buy an asset B.
+/// With a scaling the amount follow the price deviation or the volatility of the last tick.
 pub struct DollarCostAveraging {
     sell_asset: Asset,
     buy_asset: Asset,
     interval_duration: Duration,
     interval_sell_quantity: f64,
+    scaling: DollarCostAveragingScaling,
+    limits: DollarCostAveragingLimits,
+    plan: DollarCostAveragingPlan,
+    progress: DollarCostAveragingProgress,
 }
 
 impl DollarCostAveraging {
@@ -31,26 +197,239 @@ impl DollarCostAveraging {
             buy_asset,
             interval_duration,
             interval_sell_quantity,
+            scaling: DollarCostAveragingScaling::Fixed,
+            limits: DollarCostAveragingLimits {
+                min_interval_sell_quantity: interval_sell_quantity,
+                max_interval_sell_quantity: interval_sell_quantity,
+            },
+            plan: DollarCostAveragingPlan::default(),
+            progress: DollarCostAveragingProgress::default(),
         }
     }
 
+    pub fn new_smart(
+        sell_asset: Asset,
+        buy_asset: Asset,
+        interval_duration: Duration,
+        interval_sell_quantity: f64,
+        scaling: DollarCostAveragingScaling,
+        limits: DollarCostAveragingLimits,
+        plan: DollarCostAveragingPlan,
+    ) -> Result<Self, DollarCostAveragingError> {
+        let is_within_limits = interval_sell_quantity >= limits.min_interval_sell_quantity
+            && interval_sell_quantity <= limits.max_interval_sell_quantity;
+        if !is_within_limits {
+            return Err(DollarCostAveragingError::IntervalSellQuantityOutOfLimits(
+                interval_sell_quantity,
+                limits.min_interval_sell_quantity,
+                limits.max_interval_sell_quantity,
+            ));
+        }
+        match scaling {
+            DollarCostAveragingScaling::Fixed => {}
+            DollarCostAveragingScaling::MovingAverage { sensitivity } => {
+                if !sensitivity.is_finite() {
+                    return Err(DollarCostAveragingError::SensitivityNotFinite(sensitivity));
+                }
+            }
+            DollarCostAveragingScaling::Volatility {
+                reference_volatility,
+                window_tick_count,
+            } => {
+                if !(reference_volatility > 0f64 && reference_volatility.is_finite()) {
+                    return Err(DollarCostAveragingError::ReferenceVolatilityShouldBeGtZero(
+                        reference_volatility,
+                    ));
+                }
+                if window_tick_count < 2 {
+                    return Err(DollarCostAveragingError::WindowTickCountShouldBeGtOne(
+                        window_tick_count,
+                    ));
+                }
+            }
+        }
+
+        Ok(Self {
+            sell_asset,
+            buy_asset,
+            interval_duration,
+            interval_sell_quantity,
+            scaling,
+            limits,
+            plan,
+            progress: DollarCostAveragingProgress::default(),
+        })
+    }
+
+    /// Progress of the plan when driven as a PortfolioStrategy.
+    pub fn progress(&self) -> &DollarCostAveragingProgress {
+        &self.progress
+    }
+
     pub fn check_new_order(
         &self,
         last_position_datetime: &Option<DateTime<Utc>>,
         sell_balance: f64,
     ) -> Result<Option<MarketOrder>, DollarCostAveragingError> {
-        let now = Utc::now();
+        self._check_new_order(
+            Utc::now(),
+            last_position_datetime,
+            sell_balance,
+            self.interval_sell_quantity,
+        )
+    }
+
+    /// Same as check_new_order but follow the plan, the quantity is scaled from the tick,
+    /// capped by the limits and the budget left.
+    pub fn check_new_plan_order(
+        &self,
+        progress: &DollarCostAveragingProgress,
+        sell_balance: f64,
+        tick: Option<&Tick>,
+    ) -> Result<Option<MarketOrder>, DollarCostAveragingError> {
+        self._check_new_plan_order(Utc::now(), progress, sell_balance, tick)
+    }
+
+    pub fn status(
+        &self,
+        progress: &DollarCostAveragingProgress,
+        sell_balance: f64,
+    ) -> DollarCostAveragingStatus {
+        self._status(Utc::now(), progress, sell_balance)
+    }
+
+    pub fn interval_sell_quantity_for_tick(
+        &self,
+        tick: &Tick,
+    ) -> Result<f64, DollarCostAveragingError> {
+        let scale = match self.scaling {
+            DollarCostAveragingScaling::Fixed => 1f64,
+            DollarCostAveragingScaling::MovingAverage { sensitivity } => {
+                let moving_average = tick
+                    .moving_average
+                    .ok_or(DollarCostAveragingError::TickMovingAverageNone)?
+                    .as_u64() as f64;
+                let price = tick.price.as_u64() as f64;
+                1f64 + sensitivity * (moving_average - price) / moving_average
+            }
+            DollarCostAveragingScaling::Volatility {
+                reference_volatility,
+                window_tick_count,
+            } => {
+                if tick.moving_average.is_none() {
+                    return Err(DollarCostAveragingError::TickMovingAverageNone);
+                }
+                let volatility = tick
+                    .return_volatility(window_tick_count)
+                    .ok_or(DollarCostAveragingError::TickVarianceNone)?;
+                if volatility == 0f64 {
+                    f64::MAX
+                } else {
+                    reference_volatility / volatility
+                }
+            }
+        };
+
+        Ok((self.interval_sell_quantity * scale)
+            .max(self.limits.min_interval_sell_quantity)
+            .min(self.limits.max_interval_sell_quantity))
+    }
+
+    fn _budget_left(&self, progress: &DollarCostAveragingProgress) -> f64 {
+        match self.plan.total_budget {
+            Some(total_budget) => total_budget - progress.spent_quantity,
+            None => f64::MAX,
+        }
+    }
+
+    fn _is_budget_spent(&self, progress: &DollarCostAveragingProgress) -> bool {
+        self.plan.total_budget.is_some_and(|total_budget| {
+            total_budget - progress.spent_quantity <= total_budget * BUDGET_TOLERANCE
+        })
+    }
+
+    fn _status(
+        &self,
+        now: DateTime<Utc>,
+        progress: &DollarCostAveragingProgress,
+        sell_balance: f64,
+    ) -> DollarCostAveragingStatus {
+        let is_installment_done = self
+            .plan
+            .installment_count
+            .is_some_and(|count| progress.installment_count >= count);
+        let is_budget_spent = self._is_budget_spent(progress);
+        let is_end_passed = self.plan.end_datetime.is_some_and(|end| now.ge(&end));
+        if is_installment_done || is_budget_spent || is_end_passed {
+            return DollarCostAveragingStatus::Completed;
+        }
+
+        let min_sell_quantity = self
+            .limits
+            .min_interval_sell_quantity
+            .min(self._budget_left(progress));
+        let is_balance_enough = sell_balance.ge(&min_sell_quantity) && sell_balance > 0f64;
+        let is_partial_possible = self.plan.is_final_partial_order && sell_balance > 0f64;
+        if !(is_balance_enough || is_partial_possible) {
+            return DollarCostAveragingStatus::Exhausted;
+        }
+
+        DollarCostAveragingStatus::Active
+    }
+
+    fn _check_new_plan_order(
+        &self,
+        now: DateTime<Utc>,
+        progress: &DollarCostAveragingProgress,
+        sell_balance: f64,
+        tick: Option<&Tick>,
+    ) -> Result<Option<MarketOrder>, DollarCostAveragingError> {
+        let is_completed =
+            self._status(now, progress, sell_balance) == DollarCostAveragingStatus::Completed;
+        if is_completed {
+            return Err(DollarCostAveragingError::PlanCompleted);
+        }
+
+        let mut interval_sell_quantity = match (tick, &self.scaling) {
+            (Some(tick), _) => self.interval_sell_quantity_for_tick(tick)?,
+            (None, DollarCostAveragingScaling::Fixed) => self.interval_sell_quantity,
+            (None, _) => return Err(DollarCostAveragingError::TickNone),
+        };
+        interval_sell_quantity = interval_sell_quantity.min(self._budget_left(progress));
+
+        let is_final_partial_order = self.plan.is_final_partial_order
+            && sell_balance > 0f64
+            && sell_balance < interval_sell_quantity;
+        if is_final_partial_order {
+            interval_sell_quantity = sell_balance;
+        }
+
+        self._check_new_order(
+            now,
+            &progress.last_position_datetime,
+            sell_balance,
+            interval_sell_quantity,
+        )
+    }
+
+    fn _check_new_order(
+        &self,
+        now: DateTime<Utc>,
+        last_position_datetime: &Option<DateTime<Utc>>,
+        sell_balance: f64,
+        interval_sell_quantity: f64,
+    ) -> Result<Option<MarketOrder>, DollarCostAveragingError> {
         let order = MarketOrder::new(
             self.sell_asset.clone(),
             self.buy_asset.clone(),
-            self.interval_sell_quantity,
+            interval_sell_quantity,
         );
 
-        let is_reserve_asset_enough = sell_balance.ge(&self.interval_sell_quantity);
+        let is_reserve_asset_enough = sell_balance.ge(&interval_sell_quantity);
         if !is_reserve_asset_enough {
             return Err(DollarCostAveragingError::SellAssetBalanceNotEnough(
                 sell_balance,
-                self.interval_sell_quantity,
+                interval_sell_quantity,
             ));
         }
 
@@ -72,9 +451,40 @@ impl DollarCostAveraging {
     }
 }
 
+/// Driven tick by tick the plan use the tick time and keep its own progress,
+/// waiting, completed or exhausted plans don't make orders.
+impl PortfolioStrategy for DollarCostAveraging {
+    fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        _risky_price: f64,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+        let now = i64::try_from(tick.time)
+            .ok()
+            .and_then(|i| Utc.timestamp_millis_opt(i).single())
+            .ok_or(PortfolioStrategyError::DollarCostAveraging(
+                DollarCostAveragingError::TickTimeIncorrect(tick.time),
+            ))?;
+        let sell_balance = portfolio.quantity(&self.sell_asset);
+        match self._check_new_plan_order(now, &self.progress, sell_balance, Some(tick)) {
+            Ok(Some(order)) => {
+                self.progress.record_order(now, order.quantity_sell);
+                Ok(Some(order))
+            }
+            Ok(None)
+            | Err(DollarCostAveragingError::NeedToWait(_, _))
+            | Err(DollarCostAveragingError::PlanCompleted)
+            | Err(DollarCostAveragingError::SellAssetBalanceNotEnough(_, _)) => Ok(None),
+            Err(e) => Err(PortfolioStrategyError::DollarCostAveraging(e)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ethers::types::U64;
 
     fn _dollar_cost_averaging_new() -> DollarCostAveraging {
         let sell_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
@@ -91,6 +501,38 @@ mod tests {
         dca
     }
 
+    fn _smart_dollar_cost_averaging_new(
+        scaling: DollarCostAveragingScaling,
+        plan: DollarCostAveragingPlan,
+    ) -> DollarCostAveraging {
+        let sell_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+        let buy_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let limits = DollarCostAveragingLimits::new(250f64, 1_000f64).unwrap();
+
+        DollarCostAveraging::new_smart(
+            sell_asset,
+            buy_asset,
+            Duration::days(7),
+            500f64,
+            scaling,
+            limits,
+            plan,
+        )
+        .unwrap()
+    }
+
+    fn _tick_new(price: u64, moving_average: Option<u64>, variance: Option<u64>) -> Tick {
+        Tick::new(
+            U64::from(price),
+            0,
+            U64::one(),
+            true,
+            moving_average.map(U64::from),
+            variance.map(U64::from),
+        )
+        .unwrap()
+    }
+
     #[test]
     fn dollar_cost_averaging_new() {
         let dca = _dollar_cost_averaging_new();
@@ -150,4 +592,265 @@ mod tests {
             DollarCostAveragingError::SellAssetBalanceNotEnough(499f64, 500f64)
         );
     }
+
+    #[test]
+    fn dollar_cost_averaging_limits_new_incorrect() {
+        let limits = DollarCostAveragingLimits::new(1_000f64, 250f64);
+        assert!(limits.is_err());
+        assert_eq!(
+            limits.unwrap_err(),
+            DollarCostAveragingError::IntervalSellQuantityRangeIncorrect(1_000f64, 250f64)
+        );
+    }
+
+    #[test]
+    fn dollar_cost_averaging_plan_new_incorrect() {
+        let plan = DollarCostAveragingPlan::new(Some(0f64), None, None, false);
+        assert!(plan.is_err());
+        assert_eq!(
+            plan.unwrap_err(),
+            DollarCostAveragingError::TotalBudgetShouldBeGtZero(0f64)
+        );
+
+        let plan = DollarCostAveragingPlan::new(None, Some(0), None, false);
+        assert!(plan.is_err());
+        assert_eq!(
+            plan.unwrap_err(),
+            DollarCostAveragingError::InstallmentCountShouldBeGtZero(0)
+        );
+    }
+
+    #[test]
+    fn dollar_cost_averaging_new_smart_incorrect() {
+        let new_smart = |interval_sell_quantity: f64, scaling: DollarCostAveragingScaling| {
+            DollarCostAveraging::new_smart(
+                Asset::new(String::from("LUSD"), String::from("Liquity USD")),
+                Asset::new(String::from("ETH"), String::from("Ether")),
+                Duration::days(7),
+                interval_sell_quantity,
+                scaling,
+                DollarCostAveragingLimits::new(250f64, 1_000f64).unwrap(),
+                DollarCostAveragingPlan::default(),
+            )
+        };
+
+        let dca = new_smart(100f64, DollarCostAveragingScaling::Fixed);
+        assert!(dca.is_err());
+        assert_eq!(
+            dca.err().unwrap(),
+            DollarCostAveragingError::IntervalSellQuantityOutOfLimits(100f64, 250f64, 1_000f64)
+        );
+
+        let scaling = DollarCostAveragingScaling::MovingAverage {
+            sensitivity: f64::NAN,
+        };
+        assert!(matches!(
+            new_smart(500f64, scaling).err().unwrap(),
+            DollarCostAveragingError::SensitivityNotFinite(_)
+        ));
+
+        let scaling = DollarCostAveragingScaling::Volatility {
+            reference_volatility: f64::NAN,
+            window_tick_count: 2,
+        };
+        assert!(matches!(
+            new_smart(500f64, scaling).err().unwrap(),
+            DollarCostAveragingError::ReferenceVolatilityShouldBeGtZero(_)
+        ));
+
+        let scaling = DollarCostAveragingScaling::Volatility {
+            reference_volatility: 0.05f64,
+            window_tick_count: 1,
+        };
+        assert_eq!(
+            new_smart(500f64, scaling).err().unwrap(),
+            DollarCostAveragingError::WindowTickCountShouldBeGtOne(1)
+        );
+    }
+
+    #[test]
+    fn dollar_cost_averaging_interval_sell_quantity_moving_average() {
+        let dca = _smart_dollar_cost_averaging_new(
+            DollarCostAveragingScaling::MovingAverage { sensitivity: 2f64 },
+            DollarCostAveragingPlan::default(),
+        );
+
+        let quantity = dca.interval_sell_quantity_for_tick(&_tick_new(900, Some(1_000), None));
+        assert!(quantity.is_ok());
+        assert_eq!(quantity.unwrap(), 600f64);
+
+        let quantity = dca.interval_sell_quantity_for_tick(&_tick_new(1_100, Some(1_000), None));
+        assert!(quantity.is_ok());
+        assert_eq!(quantity.unwrap(), 400f64);
+
+        let quantity = dca.interval_sell_quantity_for_tick(&_tick_new(100, Some(1_000), None));
+        assert!(quantity.is_ok());
+        assert_eq!(quantity.unwrap(), 1_000f64);
+
+        let quantity = dca.interval_sell_quantity_for_tick(&_tick_new(2_000, Some(1_000), None));
+        assert!(quantity.is_ok());
+        assert_eq!(quantity.unwrap(), 250f64);
+
+        let quantity = dca.interval_sell_quantity_for_tick(&_tick_new(2_000, None, None));
+        assert_eq!(
+            quantity.unwrap_err(),
+            DollarCostAveragingError::TickMovingAverageNone
+        );
+    }
+
+    #[test]
+    fn dollar_cost_averaging_interval_sell_quantity_volatility() {
+        let dca = _smart_dollar_cost_averaging_new(
+            DollarCostAveragingScaling::Volatility {
+                reference_volatility: 0.05f64,
+                window_tick_count: 2,
+            },
+            DollarCostAveragingPlan::default(),
+        );
+
+        let quantity =
+            dca.interval_sell_quantity_for_tick(&_tick_new(1_000, Some(1_000), Some(10_000)));
+        assert!(quantity.is_ok());
+        assert_eq!(quantity.unwrap(), 250f64);
+
+        // variance of 2 ticks moving of 40 is 400, volatility is 40 / 1_000 = 4%
+        let quantity =
+            dca.interval_sell_quantity_for_tick(&_tick_new(1_000, Some(1_000), Some(400)));
+        assert!(quantity.is_ok());
+        assert_eq!(quantity.unwrap(), 625f64);
+
+        let quantity = dca.interval_sell_quantity_for_tick(&_tick_new(1_000, Some(1_000), None));
+        assert_eq!(
+            quantity.unwrap_err(),
+            DollarCostAveragingError::TickVarianceNone
+        );
+    }
+
+    #[test]
+    fn dollar_cost_averaging_check_new_plan_order_budget() {
+        let plan = DollarCostAveragingPlan::new(Some(2_000f64), None, None, false).unwrap();
+        let dca = _smart_dollar_cost_averaging_new(
+            DollarCostAveragingScaling::MovingAverage { sensitivity: 2f64 },
+            plan,
+        );
+        let tick = _tick_new(900, Some(1_000), None);
+
+        let mut progress = DollarCostAveragingProgress::default();
+        let result = dca.check_new_plan_order(&progress, 10_000f64, Some(&tick));
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        assert_eq!(order.unwrap().quantity_sell, 600f64);
+
+        progress.spent_quantity = 1_700f64;
+        let result = dca.check_new_plan_order(&progress, 10_000f64, Some(&tick));
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        assert_eq!(order.unwrap().quantity_sell, 300f64);
+
+        progress.spent_quantity = 2_000f64;
+        let result = dca.check_new_plan_order(&progress, 10_000f64, Some(&tick));
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), DollarCostAveragingError::PlanCompleted);
+        assert_eq!(
+            dca.status(&progress, 10_000f64),
+            DollarCostAveragingStatus::Completed
+        );
+
+        let result =
+            dca.check_new_plan_order(&DollarCostAveragingProgress::default(), 10_000f64, None);
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), DollarCostAveragingError::TickNone);
+    }
+
+    #[test]
+    fn dollar_cost_averaging_check_new_plan_order_final_partial() {
+        let plan = DollarCostAveragingPlan::new(None, None, None, true).unwrap();
+        let dca = _smart_dollar_cost_averaging_new(DollarCostAveragingScaling::Fixed, plan);
+        let progress =
+            DollarCostAveragingProgress::new(Some(Utc::now() - Duration::days(8)), 3, 1_500f64);
+
+        let result = dca.check_new_plan_order(&progress, 120f64, None);
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        assert_eq!(order.unwrap().quantity_sell, 120f64);
+        assert_eq!(
+            dca.status(&progress, 120f64),
+            DollarCostAveragingStatus::Active
+        );
+        assert_eq!(
+            dca.status(&progress, 0f64),
+            DollarCostAveragingStatus::Exhausted
+        );
+    }
+
+    #[test]
+    fn dollar_cost_averaging_status() {
+        let plan = DollarCostAveragingPlan::new(None, Some(4), None, false).unwrap();
+        let dca = _smart_dollar_cost_averaging_new(DollarCostAveragingScaling::Fixed, plan);
+        let mut progress = DollarCostAveragingProgress::default();
+        assert_eq!(
+            dca.status(&progress, 1_000f64),
+            DollarCostAveragingStatus::Active
+        );
+        assert_eq!(
+            dca.status(&progress, 100f64),
+            DollarCostAveragingStatus::Exhausted
+        );
+        assert_eq!(
+            dca.check_new_plan_order(&progress, 100f64, None)
+                .unwrap_err(),
+            DollarCostAveragingError::SellAssetBalanceNotEnough(100f64, 500f64)
+        );
+
+        for _ in 0..4 {
+            progress.record_order(Utc::now(), 500f64);
+        }
+        assert_eq!(progress.installment_count, 4);
+        assert_eq!(progress.spent_quantity, 2_000f64);
+        assert_eq!(
+            dca.status(&progress, 1_000f64),
+            DollarCostAveragingStatus::Completed
+        );
+
+        // 0.7 + 0.1 is a little under 0.8, the budget is still spent
+        let plan = DollarCostAveragingPlan::new(Some(0.8f64), None, None, false).unwrap();
+        let dca = _smart_dollar_cost_averaging_new(DollarCostAveragingScaling::Fixed, plan);
+        let progress = DollarCostAveragingProgress::new(None, 2, 0.7f64 + 0.1f64);
+        assert_eq!(
+            dca.status(&progress, 1_000f64),
+            DollarCostAveragingStatus::Completed
+        );
+
+        let plan =
+            DollarCostAveragingPlan::new(None, None, Some(Utc::now() - Duration::days(1)), false)
+                .unwrap();
+        let dca = _smart_dollar_cost_averaging_new(DollarCostAveragingScaling::Fixed, plan);
+        assert_eq!(
+            dca.status(&DollarCostAveragingProgress::default(), 1_000f64),
+            DollarCostAveragingStatus::Completed
+        );
+    }
+
+    #[test]
+    fn dollar_cost_averaging_portfolio_strategy_tick_time_incorrect() {
+        let mut dca = _dollar_cost_averaging_new();
+        let portfolio = Portfolio::new(
+            dca.buy_asset.clone(),
+            dca.sell_asset.clone(),
+            0f64,
+            1_000f64,
+        );
+        let mut tick = _tick_new(1_000, None, None);
+        tick.time = u64::MAX;
+        let result = PortfolioStrategy::check_new_order(&mut dca, &tick, 1_000f64, &portfolio);
+        assert_eq!(
+            result.unwrap_err(),
+            PortfolioStrategyError::DollarCostAveraging(
+                DollarCostAveragingError::TickTimeIncorrect(u64::MAX)
+            )
+        );
+    }
 }
@@ -0,0 +1,329 @@
+use crate::asset::Asset;
+use crate::market::Tick;
+use crate::order::MarketOrder;
+use crate::portfolio::{Portfolio, PortfolioStrategy, PortfolioStrategyError};
+use thiserror::Error;
+
+type Safe = f64;
+type Risky = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum GridTradingError {
+    #[error(
+        "Price range should be greater than zero and first entry smaller than second ({0} => {1})"
+    )]
+    PriceRangeIncorrect(Safe, Safe),
+    #[error("Level count should be greater than 1({0})")]
+    LevelCountShouldBeGtOne(usize),
+    #[error("Level quantity should be greater than 0({0})")]
+    LevelQuantityShouldBeGtZero(Risky),
+}
+
+/// Split a price range in levels, buy the risky asset when the price cross a level down
+/// and sell it back when the price cross the level above.
+pub struct GridTrading {
+    risky_asset: Asset,
+    safe_asset: Asset,
+    levels: Vec<Safe>,
+    level_risky_quantity: Risky,
+    price_decimal_shift: usize,
+    filled_levels: Vec<Option<Safe>>,
+    last_price: Option<Safe>,
+    realized_profit: Safe,
+}
+
+impl GridTrading {
+    pub fn new(
+        risky_asset: Asset,
+        safe_asset: Asset,
+        price_range: (Safe, Safe),
+        level_count: usize,
+        level_risky_quantity: Risky,
+        price_decimal_shift: usize,
+    ) -> Result<Self, GridTradingError> {
+        let is_range_ascending = price_range.0 < price_range.1;
+        if !(price_range.0 > 0f64 && is_range_ascending && price_range.1.is_finite()) {
+            return Err(GridTradingError::PriceRangeIncorrect(
+                price_range.0,
+                price_range.1,
+            ));
+        }
+        if level_count < 2 {
+            return Err(GridTradingError::LevelCountShouldBeGtOne(level_count));
+        }
+        if !(level_risky_quantity > 0f64 && level_risky_quantity.is_finite()) {
+            return Err(GridTradingError::LevelQuantityShouldBeGtZero(
+                level_risky_quantity,
+            ));
+        }
+
+        let level_gap = (price_range.1 - price_range.0) / (level_count - 1) as f64;
+        let levels = (0..level_count)
+            .map(|i| price_range.0 + level_gap * i as f64)
+            .collect();
+        Ok(Self {
+            risky_asset,
+            safe_asset,
+            levels,
+            level_risky_quantity,
+            price_decimal_shift,
+            filled_levels: vec![None; level_count],
+            last_price: None,
+            realized_profit: 0f64,
+        })
+    }
+
+    /// Fill price of each level, none when the level is waiting for a buy.
+    pub fn filled_levels(&self) -> &Vec<Option<Safe>> {
+        &self.filled_levels
+    }
+
+    pub fn realized_profit(&self) -> Safe {
+        self.realized_profit
+    }
+
+    pub fn check_new_order(
+        &mut self,
+        tick: &Tick,
+    ) -> Result<Option<MarketOrder>, GridTradingError> {
+        GridTrading::_check_new_order(self, tick, Safe::INFINITY, Risky::INFINITY)
+    }
+
+    /// Only the levels the balances can pay for are filled or sold, the others
+    /// stay as they were and are crossed again later.
+    fn _check_new_order(
+        &mut self,
+        tick: &Tick,
+        safe_balance: Safe,
+        risky_balance: Risky,
+    ) -> Result<Option<MarketOrder>, GridTradingError> {
+        let price = tick.price_as_f64(self.price_decimal_shift);
+        let last_price = self.last_price.replace(price);
+        let last_price = match last_price {
+            Some(last_price) => last_price,
+            None => return Ok(None),
+        };
+
+        let is_down = price < last_price;
+        let mut safe_sell_quantity: Safe = 0f64;
+        let mut risky_sell_quantity: Risky = 0f64;
+        // The last level is only used to sell what was bought on the level below,
+        // levels are crossed from the one nearest to the last price
+        for i in (0..self.levels.len() - 1).rev() {
+            let level = self.levels[i];
+            let is_crossed_down = is_down && last_price > level && price <= level;
+            let level_safe_quantity = self.level_risky_quantity * price;
+            let is_buy_paid = safe_sell_quantity + level_safe_quantity <= safe_balance;
+            if is_crossed_down && self.filled_levels[i].is_none() && is_buy_paid {
+                self.filled_levels[i] = Some(price);
+                safe_sell_quantity += level_safe_quantity;
+            }
+        }
+        for i in 0..self.levels.len() - 1 {
+            let level_above = self.levels[i + 1];
+            let is_crossed_up = !is_down && last_price < level_above && price >= level_above;
+            let is_sell_held = risky_sell_quantity + self.level_risky_quantity <= risky_balance;
+            if let (true, true, Some(fill_price)) =
+                (is_crossed_up, is_sell_held, self.filled_levels[i])
+            {
+                self.filled_levels[i] = None;
+                risky_sell_quantity += self.level_risky_quantity;
+                self.realized_profit += self.level_risky_quantity * (price - fill_price);
+            }
+        }
+
+        if safe_sell_quantity > 0f64 {
+            // Buy risky asset for the levels crossed down
+            return Ok(Some(MarketOrder::new(
+                self.safe_asset.clone(),
+                self.risky_asset.clone(),
+                safe_sell_quantity,
+            )));
+        }
+        if risky_sell_quantity > 0f64 {
+            // Sell risky asset for the levels crossed up
+            return Ok(Some(MarketOrder::new(
+                self.risky_asset.clone(),
+                self.safe_asset.clone(),
+                risky_sell_quantity,
+            )));
+        }
+        Ok(None)
+    }
+}
+
+impl PortfolioStrategy for GridTrading {
+    fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        _risky_price: Safe,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+        GridTrading::_check_new_order(
+            self,
+            tick,
+            portfolio.safe_quantity,
+            portfolio.risky_quantity,
+        )
+        .map_err(PortfolioStrategyError::GridTrading)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+
+    fn _grid_trading_new() -> GridTrading {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        GridTrading::new(risky_asset, safe_asset, (1_000f64, 1_400f64), 5, 2f64, 0).unwrap()
+    }
+
+    fn _tick_new(price: u64) -> Tick {
+        Tick::new(U64::from(price), 0, U64::one(), true, None, None).unwrap()
+    }
+
+    #[test]
+    fn grid_trading_new() {
+        let grid = _grid_trading_new();
+        assert_eq!(
+            grid.levels,
+            vec![1_000f64, 1_100f64, 1_200f64, 1_300f64, 1_400f64]
+        );
+        assert_eq!(grid.filled_levels, vec![None; 5]);
+    }
+
+    #[test]
+    fn grid_trading_new_incorrect() {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        let grid = GridTrading::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            (1_400f64, 1_000f64),
+            5,
+            2f64,
+            0,
+        );
+        assert!(grid.is_err());
+        assert_eq!(
+            grid.err().unwrap(),
+            GridTradingError::PriceRangeIncorrect(1_400f64, 1_000f64)
+        );
+
+        let grid = GridTrading::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            (1_000f64, f64::INFINITY),
+            5,
+            2f64,
+            0,
+        );
+        assert_eq!(
+            grid.err().unwrap(),
+            GridTradingError::PriceRangeIncorrect(1_000f64, f64::INFINITY)
+        );
+
+        let grid = GridTrading::new(risky_asset, safe_asset, (1_000f64, 1_400f64), 1, 2f64, 0);
+        assert!(grid.is_err());
+        assert_eq!(
+            grid.err().unwrap(),
+            GridTradingError::LevelCountShouldBeGtOne(1)
+        );
+    }
+
+    #[test]
+    fn grid_trading_check_new_order_buy() {
+        let mut grid = _grid_trading_new();
+        let result = grid.check_new_order(&_tick_new(1_250));
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_none());
+
+        let result = grid.check_new_order(&_tick_new(1_050));
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        let order = order.unwrap();
+
+        assert_eq!(order.asset_sell, grid.safe_asset);
+        assert_eq!(order.asset_buy, grid.risky_asset);
+        assert_eq!(order.quantity_sell, 4_200f64);
+        assert_eq!(
+            grid.filled_levels(),
+            &vec![None, Some(1_050f64), Some(1_050f64), None, None]
+        );
+    }
+
+    #[test]
+    fn grid_trading_check_new_order_sell() {
+        let mut grid = _grid_trading_new();
+        grid.check_new_order(&_tick_new(1_250)).unwrap();
+        grid.check_new_order(&_tick_new(1_150)).unwrap();
+        grid.check_new_order(&_tick_new(1_050)).unwrap();
+
+        let result = grid.check_new_order(&_tick_new(1_300));
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        let order = order.unwrap();
+
+        assert_eq!(order.asset_sell, grid.risky_asset);
+        assert_eq!(order.asset_buy, grid.safe_asset);
+        assert_eq!(order.quantity_sell, 4f64);
+        assert_eq!(grid.filled_levels(), &vec![None; 5]);
+        assert_eq!(grid.realized_profit(), 2f64 * 150f64 + 2f64 * 250f64);
+    }
+
+    #[test]
+    fn grid_trading_check_new_order_out_of_range() {
+        let mut grid = _grid_trading_new();
+        grid.check_new_order(&_tick_new(1_500)).unwrap();
+
+        let result = grid.check_new_order(&_tick_new(1_450));
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_none());
+
+        let result = grid.check_new_order(&_tick_new(1_600));
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_none());
+    }
+
+    #[test]
+    fn grid_trading_check_new_order_portfolio() {
+        let mut grid = _grid_trading_new();
+        let mut portfolio = Portfolio::new(
+            grid.risky_asset.clone(),
+            grid.safe_asset.clone(),
+            0f64,
+            3_000f64,
+        );
+        PortfolioStrategy::check_new_order(&mut grid, &_tick_new(1_250), 1_250f64, &portfolio)
+            .unwrap();
+
+        // only the 1_200 level is paid, the 1_100 level is left for later
+        let order =
+            PortfolioStrategy::check_new_order(&mut grid, &_tick_new(1_050), 1_050f64, &portfolio)
+                .unwrap()
+                .unwrap();
+        assert_eq!(order.quantity_sell, 2_100f64);
+        assert_eq!(
+            grid.filled_levels(),
+            &vec![None, None, Some(1_050f64), None, None]
+        );
+
+        // the risky asset bought was moved out of the portfolio, nothing is sold
+        portfolio.risky_quantity = 1f64;
+        let order =
+            PortfolioStrategy::check_new_order(&mut grid, &_tick_new(1_300), 1_300f64, &portfolio)
+                .unwrap();
+        assert!(order.is_none());
+        assert_eq!(
+            grid.filled_levels(),
+            &vec![None, None, Some(1_050f64), None, None]
+        );
+        assert_eq!(grid.realized_profit(), 0f64);
+    }
+}
@@ -1,3 +1,11 @@
 pub use core::*;
+pub mod backtest;
+pub mod circuit_breaker;
 pub mod constant_proportion_portfolio_insurance;
 pub mod dollar_cost_averaging;
+pub mod grid_trading;
+pub mod moving_average_crossover;
+pub mod portfolio;
+pub mod risk_policy;
+pub mod strategy_composer;
+pub mod volatility_targeting;
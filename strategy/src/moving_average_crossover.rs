@@ -0,0 +1,443 @@
+use crate::asset::Asset;
+use crate::market::Tick;
+use crate::order::MarketOrder;
+use crate::portfolio::{Portfolio, PortfolioStrategy, PortfolioStrategyError};
+use std::collections::VecDeque;
+use thiserror::Error;
+
+type Safe = f64;
+type Risky = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum MovingAverageCrossoverError {
+    #[error("Fast window should be greater than 0({0})")]
+    FastWindowShouldBeGtZero(usize),
+    #[error("Slow window should be greater than fast window ({0} => {1})")]
+    SlowWindowShouldBeGtFastWindow(usize, usize),
+    #[error("Hysteresis can't be negative({0})")]
+    HysteresisIsNegative(f64),
+    #[error("Hysteresis should be finite({0})")]
+    HysteresisNotFinite(f64),
+    #[error("Need the moving average of the tick for the slow average.")]
+    TickMovingAverageNone,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MovingAverageCrossoverTrend {
+    Up,
+    Down,
+}
+
+/// A confirmed crossover, time and price are the ones of the tick confirming it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MovingAverageCrossoverSignal {
+    pub time: u64,
+    pub price: Safe,
+    pub trend: MovingAverageCrossoverTrend,
+}
+
+/// Hold the risky asset while the fast moving average is above the slow one, the safe asset otherwise.
+/// The fast average is the average of the last fast_window prices. The slow average is the average
+/// of the last slow_window prices, or without slow_window the tick moving average computed by the
+/// simulator, its window then being set by the runner duration_moving_average_tick.
+/// The fast average need to cross the slow one by more than hysteresis (0.01 = 1%)
+/// and stay there for confirmation_tick_count ticks before switching.
+pub struct MovingAverageCrossover {
+    risky_asset: Asset,
+    safe_asset: Asset,
+    fast_window: usize,
+    slow_window: Option<usize>,
+    confirmation_tick_count: usize,
+    hysteresis: f64,
+    price_decimal_shift: usize,
+    prices: VecDeque<Safe>,
+    pending: Option<(MovingAverageCrossoverTrend, usize)>,
+    trend: Option<MovingAverageCrossoverTrend>,
+    signals: Vec<MovingAverageCrossoverSignal>,
+}
+
+impl MovingAverageCrossover {
+    pub fn new(
+        risky_asset: Asset,
+        safe_asset: Asset,
+        fast_window: usize,
+        slow_window: Option<usize>,
+        confirmation_tick_count: usize,
+        hysteresis: f64,
+        price_decimal_shift: usize,
+    ) -> Result<Self, MovingAverageCrossoverError> {
+        if fast_window == 0 {
+            return Err(MovingAverageCrossoverError::FastWindowShouldBeGtZero(
+                fast_window,
+            ));
+        }
+        if let Some(slow_window) = slow_window {
+            if slow_window <= fast_window {
+                return Err(MovingAverageCrossoverError::SlowWindowShouldBeGtFastWindow(
+                    fast_window,
+                    slow_window,
+                ));
+            }
+        }
+        if !hysteresis.is_finite() {
+            return Err(MovingAverageCrossoverError::HysteresisNotFinite(hysteresis));
+        }
+        if hysteresis < 0f64 {
+            return Err(MovingAverageCrossoverError::HysteresisIsNegative(
+                hysteresis,
+            ));
+        }
+
+        Ok(Self {
+            risky_asset,
+            safe_asset,
+            fast_window,
+            slow_window,
+            confirmation_tick_count,
+            hysteresis,
+            price_decimal_shift,
+            prices: VecDeque::with_capacity(slow_window.unwrap_or(fast_window)),
+            pending: None,
+            trend: None,
+            signals: Vec::new(),
+        })
+    }
+
+    pub fn trend(&self) -> &Option<MovingAverageCrossoverTrend> {
+        &self.trend
+    }
+
+    pub fn signals(&self) -> &Vec<MovingAverageCrossoverSignal> {
+        &self.signals
+    }
+
+    pub fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        risky_hold_quantity: Risky,
+        safe_hold_quantity: Safe,
+    ) -> Result<Option<MarketOrder>, MovingAverageCrossoverError> {
+        let price = tick.price_as_f64(self.price_decimal_shift);
+        let tick_slow_average = match self.slow_window {
+            Some(_) => None,
+            None => Some(
+                tick.moving_average
+                    .ok_or(MovingAverageCrossoverError::TickMovingAverageNone)?
+                    .as_u64() as f64
+                    / 10f64.powi(self.price_decimal_shift as i32),
+            ),
+        };
+        self.update_trend(tick.time, price, tick_slow_average);
+
+        match self.trend {
+            Some(MovingAverageCrossoverTrend::Up) if safe_hold_quantity > 0f64 => {
+                // Move everything in risky asset
+                Ok(Some(MarketOrder::new(
+                    self.safe_asset.clone(),
+                    self.risky_asset.clone(),
+                    safe_hold_quantity,
+                )))
+            }
+            Some(MovingAverageCrossoverTrend::Down) if risky_hold_quantity > 0f64 => {
+                // Move everything in safe asset
+                Ok(Some(MarketOrder::new(
+                    self.risky_asset.clone(),
+                    self.safe_asset.clone(),
+                    risky_hold_quantity,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Without tick_slow_average, the slow average is the one of the last slow_window prices.
+    fn update_trend(&mut self, time: u64, price: Safe, tick_slow_average: Option<Safe>) {
+        let window = self.slow_window.unwrap_or(self.fast_window);
+        if self.prices.len() == window {
+            self.prices.pop_front();
+        }
+        self.prices.push_back(price);
+        let is_window_full = self.prices.len() == window;
+        if !is_window_full {
+            return;
+        }
+
+        let fast_average = self
+            .prices
+            .iter()
+            .rev()
+            .take(self.fast_window)
+            .sum::<Safe>()
+            / self.fast_window as f64;
+        let slow_average =
+            tick_slow_average.unwrap_or_else(|| self.prices.iter().sum::<Safe>() / window as f64);
+        let crossed_trend = match fast_average {
+            i if i > slow_average * (1f64 + self.hysteresis) => MovingAverageCrossoverTrend::Up,
+            i if i < slow_average * (1f64 - self.hysteresis) => MovingAverageCrossoverTrend::Down,
+            _ => {
+                // Inside the hysteresis band, keep the current trend
+                self.pending = None;
+                return;
+            }
+        };
+        if self.trend.as_ref() == Some(&crossed_trend) {
+            self.pending = None;
+            return;
+        }
+
+        let confirmed_tick_count = match self.pending.take() {
+            Some((trend, count)) if trend == crossed_trend => count + 1,
+            _ => 0,
+        };
+        if confirmed_tick_count < self.confirmation_tick_count {
+            self.pending = Some((crossed_trend, confirmed_tick_count));
+            return;
+        }
+
+        self.trend = Some(crossed_trend.clone());
+        self.signals.push(MovingAverageCrossoverSignal {
+            time,
+            price,
+            trend: crossed_trend,
+        });
+    }
+}
+
+impl PortfolioStrategy for MovingAverageCrossover {
+    fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        _risky_price: Safe,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+        MovingAverageCrossover::check_new_order(
+            self,
+            tick,
+            portfolio.risky_quantity,
+            portfolio.safe_quantity,
+        )
+        .map_err(PortfolioStrategyError::MovingAverageCrossover)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+
+    fn _moving_average_crossover_new(
+        confirmation_tick_count: usize,
+        hysteresis: f64,
+    ) -> MovingAverageCrossover {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        MovingAverageCrossover::new(
+            risky_asset,
+            safe_asset,
+            2,
+            None,
+            confirmation_tick_count,
+            hysteresis,
+            0,
+        )
+        .unwrap()
+    }
+
+    fn _tick_new(price: u64, moving_average: Option<u64>, time: u64) -> Tick {
+        Tick::new(
+            U64::from(price),
+            time,
+            U64::one(),
+            true,
+            moving_average.map(U64::from),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn moving_average_crossover_new_incorrect() {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        let crossover = MovingAverageCrossover::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            0,
+            None,
+            0,
+            0f64,
+            0,
+        );
+        assert!(crossover.is_err());
+        assert_eq!(
+            crossover.err().unwrap(),
+            MovingAverageCrossoverError::FastWindowShouldBeGtZero(0)
+        );
+
+        let crossover = MovingAverageCrossover::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            2,
+            Some(2),
+            0,
+            0f64,
+            0,
+        );
+        assert_eq!(
+            crossover.err().unwrap(),
+            MovingAverageCrossoverError::SlowWindowShouldBeGtFastWindow(2, 2)
+        );
+
+        let crossover = MovingAverageCrossover::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            2,
+            None,
+            0,
+            -0.1f64,
+            0,
+        );
+        assert!(crossover.is_err());
+        assert_eq!(
+            crossover.err().unwrap(),
+            MovingAverageCrossoverError::HysteresisIsNegative(-0.1f64)
+        );
+
+        let crossover =
+            MovingAverageCrossover::new(risky_asset, safe_asset, 2, None, 0, f64::NAN, 0);
+        assert!(matches!(
+            crossover.err().unwrap(),
+            MovingAverageCrossoverError::HysteresisNotFinite(_)
+        ));
+    }
+
+    #[test]
+    fn moving_average_crossover_check_new_order_up() {
+        let mut crossover = _moving_average_crossover_new(0, 0f64);
+        for (time, price) in [100, 100, 100].iter().enumerate() {
+            let result = crossover.check_new_order(
+                &_tick_new(*price, Some(100), time as u64),
+                0f64,
+                1_000f64,
+            );
+            assert!(result.is_ok());
+            assert!(result.unwrap().is_none());
+        }
+
+        let result = crossover.check_new_order(&_tick_new(120, Some(105), 3), 0f64, 1_000f64);
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        let order = order.unwrap();
+
+        assert_eq!(order.asset_sell, crossover.safe_asset);
+        assert_eq!(order.asset_buy, crossover.risky_asset);
+        assert_eq!(order.quantity_sell, 1_000f64);
+        assert_eq!(
+            crossover.signals(),
+            &vec![MovingAverageCrossoverSignal {
+                time: 3,
+                price: 120f64,
+                trend: MovingAverageCrossoverTrend::Up,
+            }]
+        );
+    }
+
+    #[test]
+    fn moving_average_crossover_check_new_order_down() {
+        let mut crossover = _moving_average_crossover_new(0, 0f64);
+        for (time, price) in [100, 100, 100].iter().enumerate() {
+            crossover
+                .check_new_order(&_tick_new(*price, Some(100), time as u64), 10f64, 0f64)
+                .unwrap();
+        }
+
+        let result = crossover.check_new_order(&_tick_new(80, Some(95), 3), 10f64, 0f64);
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        let order = order.unwrap();
+
+        assert_eq!(order.asset_sell, crossover.risky_asset);
+        assert_eq!(order.asset_buy, crossover.safe_asset);
+        assert_eq!(order.quantity_sell, 10f64);
+        assert_eq!(crossover.trend(), &Some(MovingAverageCrossoverTrend::Down));
+    }
+
+    #[test]
+    fn moving_average_crossover_check_new_order_hysteresis() {
+        let mut crossover = _moving_average_crossover_new(0, 0.05f64);
+        for (time, (price, moving_average)) in [(100, 100), (100, 100), (100, 100), (104, 101)]
+            .iter()
+            .enumerate()
+        {
+            let result = crossover.check_new_order(
+                &_tick_new(*price, Some(*moving_average), time as u64),
+                0f64,
+                1_000f64,
+            );
+            assert!(result.is_ok());
+            assert!(result.unwrap().is_none());
+        }
+        assert_eq!(crossover.trend(), &None);
+
+        let result = crossover.check_new_order(&_tick_new(120, Some(106), 4), 0f64, 1_000f64);
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_some());
+    }
+
+    #[test]
+    fn moving_average_crossover_check_new_order_confirmation() {
+        let mut crossover = _moving_average_crossover_new(2, 0f64);
+        for (time, (price, moving_average)) in
+            [(100, 100), (100, 100), (100, 100), (120, 105), (130, 110)]
+                .iter()
+                .enumerate()
+        {
+            let result = crossover.check_new_order(
+                &_tick_new(*price, Some(*moving_average), time as u64),
+                0f64,
+                1_000f64,
+            );
+            assert!(result.is_ok());
+            assert!(result.unwrap().is_none());
+        }
+
+        let result = crossover.check_new_order(&_tick_new(140, Some(115), 5), 0f64, 1_000f64);
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_some());
+        assert_eq!(crossover.signals().len(), 1);
+        assert_eq!(crossover.signals().first().unwrap().time, 5);
+    }
+
+    #[test]
+    fn moving_average_crossover_check_new_order_slow_window() {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+        let mut crossover =
+            MovingAverageCrossover::new(risky_asset, safe_asset, 2, Some(4), 0, 0f64, 0).unwrap();
+        // the tick moving average isn't needed with a slow window
+        for (time, price) in [100, 100, 100].iter().enumerate() {
+            let result =
+                crossover.check_new_order(&_tick_new(*price, None, time as u64), 0f64, 1_000f64);
+            assert!(result.unwrap().is_none());
+        }
+
+        // fast (100 + 120) / 2 = 110 above slow (100 + 100 + 100 + 120) / 4 = 105
+        let result = crossover.check_new_order(&_tick_new(120, None, 3), 0f64, 1_000f64);
+        assert!(result.unwrap().is_some());
+        assert_eq!(crossover.trend(), &Some(MovingAverageCrossoverTrend::Up));
+    }
+
+    #[test]
+    fn moving_average_crossover_tick_moving_average_none() {
+        let mut crossover = _moving_average_crossover_new(0, 0f64);
+        let result = crossover.check_new_order(&_tick_new(100, None, 0), 0f64, 1_000f64);
+        assert_eq!(
+            result.unwrap_err(),
+            MovingAverageCrossoverError::TickMovingAverageNone
+        );
+    }
+}
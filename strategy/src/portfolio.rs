@@ -0,0 +1,239 @@
+use crate::asset::Asset;
+use crate::circuit_breaker::CircuitBreakerError;
+use crate::constant_proportion_portfolio_insurance::ConstantProportionPortfolioInsuranceError;
+use crate::dollar_cost_averaging::DollarCostAveragingError;
+use crate::grid_trading::GridTradingError;
+use crate::market::Tick;
+use crate::moving_average_crossover::MovingAverageCrossoverError;
+use crate::order::MarketOrder;
+use crate::strategy_composer::StrategyComposerError;
+use crate::volatility_targeting::VolatilityTargetingError;
+use thiserror::Error;
+
+type Safe = f64;
+type Risky = f64;
+
+/// Relative float error tolerated when an order sells the whole balance.
+const BALANCE_TOLERANCE: f64 = 1e-9;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PortfolioError {
+    #[error("Sell quantity is greater than the balance ({0} > {1})")]
+    SellQuantityGtBalance(f64, f64),
+    #[error("Cost is greater than the balance ({0} > {1})")]
+    CostGtBalance(f64, f64),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum PortfolioStrategyError {
+    #[error("Circuit breaker error {0}")]
+    CircuitBreaker(CircuitBreakerError),
+    #[error("Constant proportion portfolio insurance error {0}")]
+    ConstantProportionPortfolioInsurance(ConstantProportionPortfolioInsuranceError),
+    #[error("Dollar cost averaging error {0}")]
+    DollarCostAveraging(DollarCostAveragingError),
+    #[error("Grid trading error {0}")]
+    GridTrading(GridTradingError),
+    #[error("Moving average crossover error {0}")]
+    MovingAverageCrossover(MovingAverageCrossoverError),
+    #[error("Strategy composer error {0}")]
+    StrategyComposer(Box<StrategyComposerError>),
+    #[error("Volatility targeting error {0}")]
+    VolatilityTargeting(VolatilityTargetingError),
+}
+
+/// Quantity held of a risky and a safe asset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Portfolio {
+    pub risky_asset: Asset,
+    pub safe_asset: Asset,
+    pub risky_quantity: Risky,
+    pub safe_quantity: Safe,
+}
+
+/// Common way to drive any strategy tick by tick on a portfolio,
+/// risky_price is the tick price in safe asset.
+pub trait PortfolioStrategy {
+    fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        risky_price: Safe,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError>;
+}
+
+impl Portfolio {
+    pub fn new(
+        risky_asset: Asset,
+        safe_asset: Asset,
+        risky_quantity: Risky,
+        safe_quantity: Safe,
+    ) -> Self {
+        Self {
+            risky_asset,
+            safe_asset,
+            risky_quantity,
+            safe_quantity,
+        }
+    }
+
+    pub fn quantity(&self, asset: &Asset) -> f64 {
+        match asset {
+            i if i == &self.risky_asset => self.risky_quantity,
+            i if i == &self.safe_asset => self.safe_quantity,
+            _ => 0f64,
+        }
+    }
+
+    pub fn value(&self, risky_price: Safe) -> Safe {
+        self.risky_quantity * risky_price + self.safe_quantity
+    }
+
+    /// Fill the order at risky_price, orders on other assets are ignored.
+    /// Selling more than the balance is rejected, quantities never go negative.
+    pub fn apply_order(
+        &mut self,
+        order: &MarketOrder,
+        risky_price: Safe,
+    ) -> Result<(), PortfolioError> {
+        let is_sell_risky =
+            order.asset_sell == self.risky_asset && order.asset_buy == self.safe_asset;
+        let is_buy_risky =
+            order.asset_sell == self.safe_asset && order.asset_buy == self.risky_asset;
+        if !(is_sell_risky || is_buy_risky) {
+            return Ok(());
+        }
+
+        let balance = self.quantity(&order.asset_sell);
+        if order.quantity_sell > balance * (1f64 + BALANCE_TOLERANCE) {
+            return Err(PortfolioError::SellQuantityGtBalance(
+                order.quantity_sell,
+                balance,
+            ));
+        }
+        if is_sell_risky {
+            self.risky_quantity = (self.risky_quantity - order.quantity_sell).max(0f64);
+            self.safe_quantity += order.quantity_sell * risky_price;
+        }
+        if is_buy_risky {
+            self.safe_quantity = (self.safe_quantity - order.quantity_sell).max(0f64);
+            self.risky_quantity += order.quantity_sell / risky_price;
+        }
+        Ok(())
+    }
+
+    /// Pays a trade cost in asset, costs on other assets are ignored.
+    /// A cost greater than the balance is rejected, quantities never go negative.
+    pub fn pay_cost(&mut self, asset: &Asset, cost: f64) -> Result<(), PortfolioError> {
+        let balance = self.quantity(asset);
+        if cost > balance * (1f64 + BALANCE_TOLERANCE) {
+            return Err(PortfolioError::CostGtBalance(cost, balance));
+        }
+        match asset {
+            i if i == &self.risky_asset => {
+                self.risky_quantity = (self.risky_quantity - cost).max(0f64)
+            }
+            i if i == &self.safe_asset => {
+                self.safe_quantity = (self.safe_quantity - cost).max(0f64)
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _portfolio_new() -> Portfolio {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        Portfolio::new(risky_asset, safe_asset, 2f64, 1_000f64)
+    }
+
+    #[test]
+    fn portfolio_value() {
+        let portfolio = _portfolio_new();
+        assert_eq!(portfolio.value(500f64), 2_000f64);
+        assert_eq!(portfolio.quantity(&portfolio.risky_asset), 2f64);
+        assert_eq!(
+            portfolio.quantity(&Asset::new(String::from("BTC"), String::from("Bitcoin"))),
+            0f64
+        );
+    }
+
+    #[test]
+    fn portfolio_apply_order() {
+        let mut portfolio = _portfolio_new();
+        let result = portfolio.apply_order(
+            &MarketOrder::new(
+                portfolio.safe_asset.clone(),
+                portfolio.risky_asset.clone(),
+                500f64,
+            ),
+            500f64,
+        );
+        assert!(result.is_ok());
+        assert_eq!(portfolio.risky_quantity, 3f64);
+        assert_eq!(portfolio.safe_quantity, 500f64);
+
+        let result = portfolio.apply_order(
+            &MarketOrder::new(
+                portfolio.risky_asset.clone(),
+                portfolio.safe_asset.clone(),
+                1f64,
+            ),
+            400f64,
+        );
+        assert!(result.is_ok());
+        assert_eq!(portfolio.risky_quantity, 2f64);
+        assert_eq!(portfolio.safe_quantity, 900f64);
+    }
+
+    #[test]
+    fn portfolio_apply_order_overdraft() {
+        let mut portfolio = _portfolio_new();
+        let result = portfolio.apply_order(
+            &MarketOrder::new(
+                portfolio.risky_asset.clone(),
+                portfolio.safe_asset.clone(),
+                3f64,
+            ),
+            500f64,
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            PortfolioError::SellQuantityGtBalance(3f64, 2f64)
+        );
+        assert_eq!(portfolio, _portfolio_new());
+
+        // float dust over the balance sells the whole balance
+        let result = portfolio.apply_order(
+            &MarketOrder::new(
+                portfolio.safe_asset.clone(),
+                portfolio.risky_asset.clone(),
+                1_000f64 + 1e-10,
+            ),
+            500f64,
+        );
+        assert!(result.is_ok());
+        assert_eq!(portfolio.safe_quantity, 0f64);
+    }
+
+    #[test]
+    fn portfolio_pay_cost() {
+        let mut portfolio = _portfolio_new();
+        let risky_asset = portfolio.risky_asset.clone();
+        assert!(portfolio.pay_cost(&risky_asset, 0.5f64).is_ok());
+        assert_eq!(portfolio.risky_quantity, 1.5f64);
+
+        let safe_asset = portfolio.safe_asset.clone();
+        assert_eq!(
+            portfolio.pay_cost(&safe_asset, 2_000f64).unwrap_err(),
+            PortfolioError::CostGtBalance(2_000f64, 1_000f64)
+        );
+        assert_eq!(portfolio.safe_quantity, 1_000f64);
+    }
+}
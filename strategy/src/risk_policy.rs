@@ -0,0 +1,417 @@
+use crate::asset::Asset;
+use crate::order::MarketOrder;
+use crate::portfolio::Portfolio;
+use crate::price_impact::PriceImpact;
+use thiserror::Error;
+
+type Safe = f64;
+
+const DAY_MILLISECONDS: u64 = 86_400_000;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RiskPolicyError {
+    #[error("Max order notional should be greater than 0({0})")]
+    MaxOrderNotionalShouldBeGtZero(f64),
+    #[error("Max portfolio share should be between 0 and 1({0})")]
+    MaxPortfolioShareIncorrect(f64),
+    #[error("Max daily turnover should be greater than 0({0})")]
+    MaxDailyTurnoverShouldBeGtZero(f64),
+    #[error("Max slippage should be greater than 0({0})")]
+    MaxSlippageShouldBeGtZero(f64),
+    #[error("Turnover should be greater than 0({0})")]
+    TurnoverShouldBeGtZero(f64),
+}
+
+/// Reason an order breaks the policy, notional values are in safe asset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskViolation {
+    OrderNotionalGtMax { notional: Safe, max: Safe },
+    PortfolioShareGtMax { share: f64, max: f64 },
+    DailyTurnoverGtMax { turnover: Safe, max: Safe },
+    AssetNotAllowed(String),
+    PairNotAllowed(String, String),
+    SlippageGtMax { slippage: f64, max: f64 },
+    PriceImpactNone,
+}
+
+/// What to do with an order breaking a notional limit, other violations are always rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskAction {
+    Reject,
+    Clip,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskDecision {
+    Accepted(MarketOrder),
+    Clipped(MarketOrder, Vec<RiskViolation>),
+    Rejected(Vec<RiskViolation>),
+}
+
+/// Limits checked on every order, None is no limit.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RiskLimits {
+    max_order_notional: Option<Safe>,
+    max_portfolio_share: Option<f64>,
+    max_daily_turnover: Option<Safe>,
+    max_slippage: Option<f64>,
+}
+
+/// Rules between a strategy and the execution, empty allowed_assets or allowed_pairs allow all.
+/// Daily turnover is the notional executed since the start of the day (UTC).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskPolicy {
+    limits: RiskLimits,
+    allowed_assets: Vec<Asset>,
+    allowed_pairs: Vec<(Asset, Asset)>,
+    action: RiskAction,
+    turnover_day: u64,
+    turnover: Safe,
+}
+
+impl RiskLimits {
+    pub fn new(
+        max_order_notional: Option<Safe>,
+        max_portfolio_share: Option<f64>,
+        max_daily_turnover: Option<Safe>,
+        max_slippage: Option<f64>,
+    ) -> Result<Self, RiskPolicyError> {
+        if let Some(i) = max_order_notional.filter(|i| *i <= 0f64) {
+            return Err(RiskPolicyError::MaxOrderNotionalShouldBeGtZero(i));
+        }
+        if let Some(i) = max_portfolio_share.filter(|i| *i <= 0f64 || *i > 1f64) {
+            return Err(RiskPolicyError::MaxPortfolioShareIncorrect(i));
+        }
+        if let Some(i) = max_daily_turnover.filter(|i| *i <= 0f64) {
+            return Err(RiskPolicyError::MaxDailyTurnoverShouldBeGtZero(i));
+        }
+        if let Some(i) = max_slippage.filter(|i| *i <= 0f64) {
+            return Err(RiskPolicyError::MaxSlippageShouldBeGtZero(i));
+        }
+        Ok(Self {
+            max_order_notional,
+            max_portfolio_share,
+            max_daily_turnover,
+            max_slippage,
+        })
+    }
+
+    pub fn max_order_notional(&self) -> Option<Safe> {
+        self.max_order_notional
+    }
+
+    pub fn max_portfolio_share(&self) -> Option<f64> {
+        self.max_portfolio_share
+    }
+
+    pub fn max_daily_turnover(&self) -> Option<Safe> {
+        self.max_daily_turnover
+    }
+
+    pub fn max_slippage(&self) -> Option<f64> {
+        self.max_slippage
+    }
+}
+
+impl RiskPolicy {
+    pub fn new(
+        limits: RiskLimits,
+        allowed_assets: Vec<Asset>,
+        allowed_pairs: Vec<(Asset, Asset)>,
+        action: RiskAction,
+    ) -> Self {
+        Self {
+            limits,
+            allowed_assets,
+            allowed_pairs,
+            action,
+            turnover_day: 0,
+            turnover: 0f64,
+        }
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn allowed_assets(&self) -> &Vec<Asset> {
+        &self.allowed_assets
+    }
+
+    pub fn allowed_pairs(&self) -> &Vec<(Asset, Asset)> {
+        &self.allowed_pairs
+    }
+
+    pub fn action(&self) -> &RiskAction {
+        &self.action
+    }
+
+    /// Turnover already executed the day of time.
+    pub fn daily_turnover(&self, time: u64) -> Safe {
+        if time / DAY_MILLISECONDS == self.turnover_day {
+            self.turnover
+        } else {
+            0f64
+        }
+    }
+
+    /// Adds an executed notional to the daily turnover.
+    pub fn record_execution(&mut self, notional: Safe, time: u64) -> Result<(), RiskPolicyError> {
+        if notional <= 0f64 {
+            return Err(RiskPolicyError::TurnoverShouldBeGtZero(notional));
+        }
+        self.turnover = self.daily_turnover(time) + notional;
+        self.turnover_day = time / DAY_MILLISECONDS;
+        Ok(())
+    }
+
+    /// Checks the order at time, price_impact is the expected execution.
+    /// With a max slippage an order without price_impact is rejected.
+    pub fn check(
+        &self,
+        order: &MarketOrder,
+        time: u64,
+        risky_price: Safe,
+        portfolio: &Portfolio,
+        price_impact: Option<&PriceImpact>,
+    ) -> RiskDecision {
+        let mut violations = vec![];
+        if !self.allowed_assets.is_empty() {
+            for asset in [&order.asset_sell, &order.asset_buy] {
+                if !self.allowed_assets.contains(asset) {
+                    violations.push(RiskViolation::AssetNotAllowed(asset.id.clone()));
+                }
+            }
+        }
+        let is_pair_allowed = self.allowed_pairs.is_empty()
+            || self.allowed_pairs.iter().any(|(i, j)| {
+                (i == &order.asset_sell && j == &order.asset_buy)
+                    || (i == &order.asset_buy && j == &order.asset_sell)
+            });
+        if !is_pair_allowed {
+            violations.push(RiskViolation::PairNotAllowed(
+                order.asset_sell.id.clone(),
+                order.asset_buy.id.clone(),
+            ));
+        }
+        if let Some(max) = self.limits.max_slippage {
+            match price_impact {
+                Some(price_impact) if price_impact.slippage > max => {
+                    violations.push(RiskViolation::SlippageGtMax {
+                        slippage: price_impact.slippage,
+                        max,
+                    });
+                }
+                Some(_) => {}
+                None => violations.push(RiskViolation::PriceImpactNone),
+            }
+        }
+        if !violations.is_empty() {
+            return RiskDecision::Rejected(violations);
+        }
+
+        let notional = if order.asset_sell == portfolio.safe_asset {
+            order.quantity_sell
+        } else {
+            order.quantity_sell * risky_price
+        };
+        let mut max_notional = f64::INFINITY;
+        if let Some(max) = self.limits.max_order_notional {
+            if notional > max {
+                violations.push(RiskViolation::OrderNotionalGtMax { notional, max });
+            }
+            max_notional = max_notional.min(max);
+        }
+        if let Some(max) = self.limits.max_portfolio_share {
+            let portfolio_value = portfolio.value(risky_price);
+            let share = if portfolio_value > 0f64 {
+                notional / portfolio_value
+            } else {
+                f64::INFINITY
+            };
+            if share > max {
+                violations.push(RiskViolation::PortfolioShareGtMax { share, max });
+            }
+            max_notional = max_notional.min(max * portfolio_value);
+        }
+        if let Some(max) = self.limits.max_daily_turnover {
+            let daily_turnover = self.daily_turnover(time);
+            if daily_turnover + notional > max {
+                violations.push(RiskViolation::DailyTurnoverGtMax {
+                    turnover: daily_turnover + notional,
+                    max,
+                });
+            }
+            max_notional = max_notional.min(max - daily_turnover);
+        }
+        if violations.is_empty() {
+            return RiskDecision::Accepted(order.clone());
+        }
+        match self.action {
+            RiskAction::Clip if max_notional > 0f64 => {
+                let mut clipped_order = order.clone();
+                clipped_order.quantity_sell *= max_notional / notional;
+                RiskDecision::Clipped(clipped_order, violations)
+            }
+            _ => RiskDecision::Rejected(violations),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn _eth() -> Asset {
+        Asset::new(String::from("ETH"), String::from("Ether"))
+    }
+
+    fn _lusd() -> Asset {
+        Asset::new(String::from("LUSD"), String::from("Liquity USD"))
+    }
+
+    fn _portfolio_new() -> Portfolio {
+        Portfolio::new(_eth(), _lusd(), 5f64, 5_000f64)
+    }
+
+    fn _price_impact_new(slippage: f64) -> PriceImpact {
+        PriceImpact {
+            reference_price: 1_000f64,
+            average_price: 1_000f64 * (1f64 - slippage),
+            worst_price: 1_000f64 * (1f64 - slippage),
+            slippage,
+            quantity_sell: 1f64,
+            quantity_buy: 1_000f64 * (1f64 - slippage),
+        }
+    }
+
+    #[test]
+    fn risk_limits_new_incorrect() {
+        let limits = RiskLimits::new(None, Some(1.5f64), None, None);
+        assert!(limits.is_err());
+        assert_eq!(
+            limits.unwrap_err(),
+            RiskPolicyError::MaxPortfolioShareIncorrect(1.5f64)
+        );
+    }
+
+    #[test]
+    fn risk_policy_check_reject() {
+        let limits = RiskLimits::new(Some(2_000f64), None, None, Some(0.01f64)).unwrap();
+        let policy = RiskPolicy::new(limits, vec![], vec![], RiskAction::Reject);
+        let portfolio = _portfolio_new();
+
+        let order = MarketOrder::new(_eth(), _lusd(), 1f64);
+        let decision = policy.check(
+            &order,
+            0,
+            1_000f64,
+            &portfolio,
+            Some(&_price_impact_new(0.005f64)),
+        );
+        assert_eq!(decision, RiskDecision::Accepted(order.clone()));
+
+        let decision = policy.check(&order, 0, 1_000f64, &portfolio, None);
+        assert_eq!(
+            decision,
+            RiskDecision::Rejected(vec![RiskViolation::PriceImpactNone])
+        );
+
+        let decision = policy.check(
+            &order,
+            0,
+            1_000f64,
+            &portfolio,
+            Some(&_price_impact_new(0.05f64)),
+        );
+        assert_eq!(
+            decision,
+            RiskDecision::Rejected(vec![RiskViolation::SlippageGtMax {
+                slippage: 0.05f64,
+                max: 0.01f64
+            }])
+        );
+
+        let order = MarketOrder::new(_eth(), _lusd(), 3f64);
+        let decision = policy.check(
+            &order,
+            0,
+            1_000f64,
+            &portfolio,
+            Some(&_price_impact_new(0.005f64)),
+        );
+        assert_eq!(
+            decision,
+            RiskDecision::Rejected(vec![RiskViolation::OrderNotionalGtMax {
+                notional: 3_000f64,
+                max: 2_000f64
+            }])
+        );
+    }
+
+    #[test]
+    fn risk_policy_check_asset_and_pair_not_allowed() {
+        let btc = Asset::new(String::from("BTC"), String::from("Bitcoin"));
+        let policy = RiskPolicy::new(
+            RiskLimits::default(),
+            vec![_eth(), _lusd()],
+            vec![(_lusd(), _eth())],
+            RiskAction::Clip,
+        );
+        let order = MarketOrder::new(_eth(), btc, 1f64);
+        let decision = policy.check(&order, 0, 1_000f64, &_portfolio_new(), None);
+        assert_eq!(
+            decision,
+            RiskDecision::Rejected(vec![
+                RiskViolation::AssetNotAllowed(String::from("BTC")),
+                RiskViolation::PairNotAllowed(String::from("ETH"), String::from("BTC")),
+            ])
+        );
+        let order = MarketOrder::new(_eth(), _lusd(), 1f64);
+        let decision = policy.check(&order, 0, 1_000f64, &_portfolio_new(), None);
+        assert_eq!(decision, RiskDecision::Accepted(order));
+    }
+
+    #[test]
+    fn risk_policy_check_clip() {
+        // portfolio value is 10_000, 20% is 2_000
+        let limits = RiskLimits::new(None, Some(0.2f64), Some(2_500f64), None).unwrap();
+        let mut policy = RiskPolicy::new(limits, vec![], vec![], RiskAction::Clip);
+        let portfolio = _portfolio_new();
+
+        let order = MarketOrder::new(_lusd(), _eth(), 4_000f64);
+        let decision = policy.check(&order, 0, 1_000f64, &portfolio, None);
+        assert_eq!(
+            decision,
+            RiskDecision::Clipped(
+                MarketOrder::new(_lusd(), _eth(), 2_000f64),
+                vec![
+                    RiskViolation::PortfolioShareGtMax {
+                        share: 0.4f64,
+                        max: 0.2f64
+                    },
+                    RiskViolation::DailyTurnoverGtMax {
+                        turnover: 4_000f64,
+                        max: 2_500f64
+                    },
+                ]
+            )
+        );
+        assert!(policy.record_execution(2_000f64, 1_000).is_ok());
+        let order = MarketOrder::new(_eth(), _lusd(), 1f64);
+        let decision = policy.check(&order, 2_000, 1_000f64, &portfolio, None);
+        assert_eq!(
+            decision,
+            RiskDecision::Clipped(
+                MarketOrder::new(_eth(), _lusd(), 0.5f64),
+                vec![RiskViolation::DailyTurnoverGtMax {
+                    turnover: 3_000f64,
+                    max: 2_500f64
+                }]
+            )
+        );
+        // next day the turnover starts again
+        let decision = policy.check(&order, DAY_MILLISECONDS, 1_000f64, &portfolio, None);
+        assert_eq!(decision, RiskDecision::Accepted(order));
+    }
+}
@@ -0,0 +1,551 @@
+use crate::asset::Asset;
+use crate::market::Tick;
+use crate::order::MarketOrder;
+use crate::order_netting::{net_market_orders, OrderNettingError, ReferencePrice};
+use crate::portfolio::{Portfolio, PortfolioError, PortfolioStrategy, PortfolioStrategyError};
+use thiserror::Error;
+
+type Safe = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum StrategyComposerError {
+    #[error("Sleeve weight should be between 0 and 1({0})")]
+    SleeveWeightIncorrect(f64),
+    #[error("Sleeve weights sum should be smaller than 1({0})")]
+    SleeveWeightSumGtOne(f64),
+    #[error("Sleeve amount should be greater than 0({0})")]
+    SleeveAmountShouldBeGtZero(Safe),
+    #[error("Sleeve amounts are greater than the portfolio value ({0} > {1})")]
+    SleeveAmountGtPortfolioValue(Safe, Safe),
+    #[error("Portfolio assets don't match the composer assets.")]
+    PortfolioAssetsIncorrect,
+    #[error("Strategy error {0}")]
+    Strategy(PortfolioStrategyError),
+    #[error("Portfolio error {0}")]
+    Portfolio(PortfolioError),
+    #[error("Order netting error {0}")]
+    OrderNetting(OrderNettingError),
+    #[error("Sleeve orders should net into one order on the composer pair({0} orders)")]
+    NetOrderCountGtOne(usize),
+    #[error("Fill price should be greater than 0({0})")]
+    FillPriceShouldBeGtZero(Safe),
+    #[error("Cost can't be negative({0})")]
+    CostIsNegative(Safe),
+}
+
+/// Part of the portfolio given to a sleeve, a fixed safe value or a weight of what is left
+/// once the fixed values are taken.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SleeveAllocation {
+    Amount(Safe),
+    Weight(f64),
+}
+
+/// A child strategy with its own sub portfolio, contributed_value is the value it was given.
+pub struct Sleeve {
+    pub name: String,
+    pub allocation: SleeveAllocation,
+    pub portfolio: Portfolio,
+    pub contributed_value: Safe,
+    strategy: Box<dyn PortfolioStrategy>,
+}
+
+/// Orders of a tick, each child order with its sleeve index and the net order to execute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComposedOrders {
+    pub sleeve_orders: Vec<(usize, MarketOrder)>,
+    pub net_order: Option<MarketOrder>,
+}
+
+/// Split a portfolio between child strategies, each one only see its own sleeve.
+/// Child orders are filled inside the sleeves at the tick price and netted against each other,
+/// only the net order need to be executed, its fill is then booked with record_net_fill.
+/// As a PortfolioStrategy, the sleeves are allocated from the portfolio on the first tick
+/// when allocate wasn't called before.
+pub struct StrategyComposer {
+    risky_asset: Asset,
+    safe_asset: Asset,
+    sleeves: Vec<Sleeve>,
+    is_allocated: bool,
+}
+
+impl StrategyComposer {
+    pub fn new(risky_asset: Asset, safe_asset: Asset) -> Self {
+        Self {
+            risky_asset,
+            safe_asset,
+            sleeves: Vec::new(),
+            is_allocated: false,
+        }
+    }
+
+    pub fn sleeves(&self) -> &Vec<Sleeve> {
+        &self.sleeves
+    }
+
+    pub fn add_sleeve(
+        &mut self,
+        name: String,
+        allocation: SleeveAllocation,
+        strategy: Box<dyn PortfolioStrategy>,
+    ) -> Result<(), StrategyComposerError> {
+        match allocation {
+            SleeveAllocation::Amount(amount) if amount <= 0f64 => {
+                return Err(StrategyComposerError::SleeveAmountShouldBeGtZero(amount));
+            }
+            SleeveAllocation::Weight(weight) if !(0f64..=1f64).contains(&weight) => {
+                return Err(StrategyComposerError::SleeveWeightIncorrect(weight));
+            }
+            SleeveAllocation::Weight(weight) => {
+                let weight_sum = self._weight_sum() + weight;
+                if weight_sum > 1f64 {
+                    return Err(StrategyComposerError::SleeveWeightSumGtOne(weight_sum));
+                }
+            }
+            _ => {}
+        }
+
+        self.sleeves.push(Sleeve {
+            name,
+            allocation,
+            portfolio: Portfolio::new(
+                self.risky_asset.clone(),
+                self.safe_asset.clone(),
+                0f64,
+                0f64,
+            ),
+            contributed_value: 0f64,
+            strategy,
+        });
+        Ok(())
+    }
+
+    /// Give each sleeve its part of the portfolio, with the same risky/safe mix as the portfolio.
+    pub fn allocate(
+        &mut self,
+        portfolio: &Portfolio,
+        risky_price: Safe,
+    ) -> Result<(), StrategyComposerError> {
+        let is_same_assets =
+            portfolio.risky_asset == self.risky_asset && portfolio.safe_asset == self.safe_asset;
+        if !is_same_assets {
+            return Err(StrategyComposerError::PortfolioAssetsIncorrect);
+        }
+
+        let value = portfolio.value(risky_price);
+        let amount_sum: Safe = self
+            .sleeves
+            .iter()
+            .map(|sleeve| match sleeve.allocation {
+                SleeveAllocation::Amount(amount) => amount,
+                SleeveAllocation::Weight(_) => 0f64,
+            })
+            .sum();
+        if amount_sum > value {
+            return Err(StrategyComposerError::SleeveAmountGtPortfolioValue(
+                amount_sum, value,
+            ));
+        }
+
+        for sleeve in &mut self.sleeves {
+            let sleeve_value = match sleeve.allocation {
+                SleeveAllocation::Amount(amount) => amount,
+                SleeveAllocation::Weight(weight) => (value - amount_sum) * weight,
+            };
+            let share = if value == 0f64 {
+                0f64
+            } else {
+                sleeve_value / value
+            };
+            sleeve.portfolio.risky_quantity = portfolio.risky_quantity * share;
+            sleeve.portfolio.safe_quantity = portfolio.safe_quantity * share;
+            sleeve.contributed_value = sleeve_value;
+        }
+        self.is_allocated = true;
+        Ok(())
+    }
+
+    /// Sleeve portfolios are only changed once every order is filled and netted,
+    /// on an error the child strategies may still have updated their own state.
+    pub fn check_new_orders(
+        &mut self,
+        tick: &Tick,
+        risky_price: Safe,
+    ) -> Result<ComposedOrders, StrategyComposerError> {
+        let mut sleeve_orders: Vec<(usize, MarketOrder)> = Vec::new();
+        let mut portfolios: Vec<Portfolio> = Vec::new();
+        for (i, sleeve) in self.sleeves.iter_mut().enumerate() {
+            let order = sleeve
+                .strategy
+                .check_new_order(tick, risky_price, &sleeve.portfolio)
+                .map_err(StrategyComposerError::Strategy)?;
+            if let Some(order) = order {
+                let mut portfolio = sleeve.portfolio.clone();
+                portfolio
+                    .apply_order(&order, risky_price)
+                    .map_err(StrategyComposerError::Portfolio)?;
+                portfolios.push(portfolio);
+                sleeve_orders.push((i, order));
+            }
+        }
+
+        let net_order = self.net_orders(&sleeve_orders, risky_price)?;
+        for ((i, _), portfolio) in sleeve_orders.iter().zip(portfolios) {
+            self.sleeves[*i].portfolio = portfolio;
+        }
+        Ok(ComposedOrders {
+            sleeve_orders,
+            net_order,
+        })
+    }
+
+    /// Books the execution of the net order on the sleeves trading in its direction,
+    /// pro rata of their sell quantity: the gap between fill_price and the tick price
+    /// the sleeves were filled at, and the cost in safe asset paid from the asset bought.
+    pub fn record_net_fill(
+        &mut self,
+        composed_orders: &ComposedOrders,
+        risky_price: Safe,
+        fill_price: Safe,
+        cost: Safe,
+    ) -> Result<(), StrategyComposerError> {
+        let Some(net_order) = &composed_orders.net_order else {
+            return Ok(());
+        };
+        if fill_price <= 0f64 || fill_price.is_nan() {
+            return Err(StrategyComposerError::FillPriceShouldBeGtZero(fill_price));
+        }
+        if cost < 0f64 || cost.is_nan() {
+            return Err(StrategyComposerError::CostIsNegative(cost));
+        }
+
+        let direction_orders: Vec<&(usize, MarketOrder)> = composed_orders
+            .sleeve_orders
+            .iter()
+            .filter(|(_, i)| {
+                i.asset_sell == net_order.asset_sell && i.asset_buy == net_order.asset_buy
+            })
+            .collect();
+        let quantity_sum: f64 = direction_orders.iter().map(|(_, i)| i.quantity_sell).sum();
+        let is_buy_risky = net_order.asset_buy == self.risky_asset;
+        let mut portfolios: Vec<(usize, Portfolio)> = Vec::new();
+        for (i, order) in direction_orders {
+            let share = order.quantity_sell / quantity_sum;
+            let quantity_sell = net_order.quantity_sell * share;
+            // gap and cost in asset bought, a negative cost is a better fill
+            let (gap, cost) = if is_buy_risky {
+                (
+                    quantity_sell / fill_price - quantity_sell / risky_price,
+                    cost * share / fill_price,
+                )
+            } else {
+                (quantity_sell * (fill_price - risky_price), cost * share)
+            };
+            let mut portfolio = self.sleeves[*i].portfolio.clone();
+            portfolio
+                .pay_cost(&net_order.asset_buy, (cost - gap).max(0f64))
+                .map_err(StrategyComposerError::Portfolio)?;
+            if gap > cost {
+                let gain = gap - cost;
+                if is_buy_risky {
+                    portfolio.risky_quantity += gain;
+                } else {
+                    portfolio.safe_quantity += gain;
+                }
+            }
+            portfolios.push((*i, portfolio));
+        }
+        for (i, portfolio) in portfolios {
+            self.sleeves[i].portfolio = portfolio;
+        }
+        Ok(())
+    }
+
+    /// Value won or lost by each sleeve since its allocation.
+    pub fn profit_and_loss(&self, risky_price: Safe) -> Vec<(String, Safe)> {
+        self.sleeves
+            .iter()
+            .map(|sleeve| {
+                (
+                    sleeve.name.clone(),
+                    sleeve.portfolio.value(risky_price) - sleeve.contributed_value,
+                )
+            })
+            .collect()
+    }
+
+    fn net_orders(
+        &self,
+        sleeve_orders: &[(usize, MarketOrder)],
+        risky_price: Safe,
+    ) -> Result<Option<MarketOrder>, StrategyComposerError> {
+        let orders: Vec<MarketOrder> = sleeve_orders
+            .iter()
+            .map(|(_, order)| order.clone())
+            .collect();
+        let reference_price = ReferencePrice::new(
+            self.risky_asset.clone(),
+            self.safe_asset.clone(),
+            risky_price,
+        )
+        .map_err(StrategyComposerError::OrderNetting)?;
+        let batch = net_market_orders(&orders, &[reference_price])
+            .map_err(StrategyComposerError::OrderNetting)?;
+        if batch.orders.len() > 1 {
+            return Err(StrategyComposerError::NetOrderCountGtOne(
+                batch.orders.len(),
+            ));
+        }
+        Ok(batch.orders.first().cloned())
+    }
+
+    fn _weight_sum(&self) -> f64 {
+        self.sleeves
+            .iter()
+            .map(|sleeve| match sleeve.allocation {
+                SleeveAllocation::Weight(weight) => weight,
+                SleeveAllocation::Amount(_) => 0f64,
+            })
+            .sum()
+    }
+}
+
+impl PortfolioStrategy for StrategyComposer {
+    fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        risky_price: Safe,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+        if !self.is_allocated {
+            self.allocate(portfolio, risky_price)
+                .map_err(|i| PortfolioStrategyError::StrategyComposer(Box::new(i)))?;
+        }
+        let composed_orders = self
+            .check_new_orders(tick, risky_price)
+            .map_err(|i| PortfolioStrategyError::StrategyComposer(Box::new(i)))?;
+        Ok(composed_orders.net_order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant_proportion_portfolio_insurance::ConstantProportionPortfolioInsurance;
+    use crate::dollar_cost_averaging::DollarCostAveraging;
+    use chrono::Duration;
+    use ethers::types::U64;
+
+    fn _strategy_composer_new() -> StrategyComposer {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+        let mut composer = StrategyComposer::new(risky_asset.clone(), safe_asset.clone());
+
+        let dca = DollarCostAveraging::new(
+            safe_asset.clone(),
+            risky_asset.clone(),
+            Duration::days(7),
+            150f64,
+        );
+        composer
+            .add_sleeve(
+                String::from("dca"),
+                SleeveAllocation::Amount(400f64),
+                Box::new(dca),
+            )
+            .unwrap();
+        let cppi = ConstantProportionPortfolioInsurance::new(risky_asset, safe_asset, 2f64, 500f64);
+        composer
+            .add_sleeve(
+                String::from("cppi"),
+                SleeveAllocation::Weight(1f64),
+                Box::new(cppi),
+            )
+            .unwrap();
+        composer
+    }
+
+    struct _SellBitcoinStrategy;
+
+    impl PortfolioStrategy for _SellBitcoinStrategy {
+        fn check_new_order(
+            &mut self,
+            _tick: &Tick,
+            _risky_price: Safe,
+            portfolio: &Portfolio,
+        ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+            Ok(Some(MarketOrder::new(
+                Asset::new(String::from("BTC"), String::from("Bitcoin")),
+                portfolio.safe_asset.clone(),
+                1f64,
+            )))
+        }
+    }
+
+    fn _tick_new(price: u64) -> Tick {
+        Tick::new(U64::from(price), 0, U64::one(), true, None, None).unwrap()
+    }
+
+    #[test]
+    fn strategy_composer_add_sleeve_incorrect() {
+        let mut composer = _strategy_composer_new();
+        let cppi = ConstantProportionPortfolioInsurance::new(
+            composer.risky_asset.clone(),
+            composer.safe_asset.clone(),
+            3f64,
+            500f64,
+        );
+        let result = composer.add_sleeve(
+            String::from("cppi_2"),
+            SleeveAllocation::Weight(0.5f64),
+            Box::new(cppi),
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            StrategyComposerError::SleeveWeightSumGtOne(1.5f64)
+        );
+        assert_eq!(composer.sleeves().len(), 2);
+    }
+
+    #[test]
+    fn strategy_composer_allocate() {
+        let mut composer = _strategy_composer_new();
+        let portfolio = Portfolio::new(
+            composer.risky_asset.clone(),
+            composer.safe_asset.clone(),
+            0f64,
+            1_000f64,
+        );
+        let result = composer.allocate(&portfolio, 10f64);
+        assert!(result.is_ok());
+
+        let dca = composer.sleeves().first().unwrap();
+        assert_eq!(dca.portfolio.safe_quantity, 400f64);
+        assert_eq!(dca.contributed_value, 400f64);
+        let cppi = composer.sleeves().last().unwrap();
+        assert_eq!(cppi.portfolio.safe_quantity, 600f64);
+
+        let portfolio = Portfolio::new(
+            composer.risky_asset.clone(),
+            composer.safe_asset.clone(),
+            0f64,
+            300f64,
+        );
+        let result = composer.allocate(&portfolio, 10f64);
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err(),
+            StrategyComposerError::SleeveAmountGtPortfolioValue(400f64, 300f64)
+        );
+    }
+
+    #[test]
+    fn strategy_composer_check_new_orders_net() {
+        let mut composer = _strategy_composer_new();
+        let portfolio = Portfolio::new(
+            composer.risky_asset.clone(),
+            composer.safe_asset.clone(),
+            50f64,
+            500f64,
+        );
+        composer.allocate(&portfolio, 10f64).unwrap();
+
+        // dca buy 150 LUSD of ETH, cppi sell 300 - 2 * 100 LUSD of ETH
+        let result = composer.check_new_orders(&_tick_new(10), 10f64);
+        assert!(result.is_ok());
+        let orders = result.unwrap();
+        assert_eq!(orders.sleeve_orders.len(), 2);
+        assert!(orders.net_order.is_some());
+        let net_order = orders.net_order.unwrap();
+
+        assert_eq!(net_order.asset_sell, composer.safe_asset);
+        assert_eq!(net_order.asset_buy, composer.risky_asset);
+        assert_eq!(net_order.quantity_sell, 50f64);
+    }
+
+    #[test]
+    fn strategy_composer_check_new_orders_other_pair() {
+        let mut composer = _strategy_composer_new();
+        composer
+            .add_sleeve(
+                String::from("btc"),
+                SleeveAllocation::Amount(100f64),
+                Box::new(_SellBitcoinStrategy),
+            )
+            .unwrap();
+        let portfolio = Portfolio::new(
+            composer.risky_asset.clone(),
+            composer.safe_asset.clone(),
+            0f64,
+            1_000f64,
+        );
+        composer.allocate(&portfolio, 10f64).unwrap();
+
+        let portfolios: Vec<Portfolio> = composer
+            .sleeves()
+            .iter()
+            .map(|i| i.portfolio.clone())
+            .collect();
+        let result = composer.check_new_orders(&_tick_new(10), 10f64);
+        assert_eq!(
+            result.unwrap_err(),
+            StrategyComposerError::NetOrderCountGtOne(2)
+        );
+        let portfolios_after: Vec<Portfolio> = composer
+            .sleeves()
+            .iter()
+            .map(|i| i.portfolio.clone())
+            .collect();
+        assert_eq!(portfolios_after, portfolios);
+    }
+
+    #[test]
+    fn strategy_composer_record_net_fill() {
+        let mut composer = _strategy_composer_new();
+        let portfolio = Portfolio::new(
+            composer.risky_asset.clone(),
+            composer.safe_asset.clone(),
+            50f64,
+            500f64,
+        );
+        composer.allocate(&portfolio, 10f64).unwrap();
+        let orders = composer.check_new_orders(&_tick_new(10), 10f64).unwrap();
+        assert_eq!(composer.sleeves()[0].portfolio.risky_quantity, 35f64);
+        let cppi_portfolio = composer.sleeves()[1].portfolio.clone();
+
+        assert_eq!(
+            composer.record_net_fill(&orders, 10f64, 0f64, 1f64),
+            Err(StrategyComposerError::FillPriceShouldBeGtZero(0f64))
+        );
+        // the net buy of 50 LUSD fills at 12.5 instead of 10, only dca buys
+        let result = composer.record_net_fill(&orders, 10f64, 12.5f64, 1f64);
+        assert!(result.is_ok());
+        let dca = &composer.sleeves()[0].portfolio;
+        assert!((dca.risky_quantity - (35f64 - 1f64 - 0.08f64)).abs() < 1e-12);
+        assert_eq!(dca.safe_quantity, 50f64);
+        assert_eq!(composer.sleeves()[1].portfolio, cppi_portfolio);
+    }
+
+    #[test]
+    fn strategy_composer_profit_and_loss() {
+        let mut composer = _strategy_composer_new();
+        let portfolio = Portfolio::new(
+            composer.risky_asset.clone(),
+            composer.safe_asset.clone(),
+            0f64,
+            1_000f64,
+        );
+        composer.allocate(&portfolio, 10f64).unwrap();
+        composer.check_new_orders(&_tick_new(10), 10f64).unwrap();
+
+        let profit_and_loss = composer.profit_and_loss(20f64);
+        assert_eq!(
+            profit_and_loss,
+            vec![
+                (String::from("dca"), 150f64),
+                (String::from("cppi"), 200f64)
+            ]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::asset::Asset;
+use crate::market::Tick;
+use crate::order::MarketOrder;
+use crate::portfolio::{Portfolio, PortfolioStrategy, PortfolioStrategyError};
+use thiserror::Error;
+
+type Safe = f64;
+type Risky = f64;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum VolatilityTargetingError {
+    #[error("Target volatility should be greater than 0({0})")]
+    TargetVolatilityShouldBeGtZero(f64),
+    #[error("Max leverage should be greater than 0({0})")]
+    MaxLeverageShouldBeGtZero(f64),
+    #[error("Min safe weight should be between 0 and 1({0})")]
+    MinSafeWeightIncorrect(f64),
+    #[error("Window tick count should be greater than 1({0})")]
+    WindowTickCountShouldBeGtOne(usize),
+    #[error("Need the moving average of the tick to compute the volatility.")]
+    TickMovingAverageNone,
+    #[error("Need the variance of the tick to compute the volatility.")]
+    TickVarianceNone,
+    #[error("An unexpected error happen...")]
+    UnexpectedError,
+}
+
+/// Size the risky asset exposure so the portfolio volatility stay near a target,
+/// unlike CPPI which only look at the cushion.
+/// Volatility is the return volatility by tick of Tick::return_volatility, over the
+/// window_tick_count ticks of the simulator moving average and variance.
+/// risky weight = target_volatility / volatility capped by max_leverage and 1 - min_safe_weight,
+/// the portfolio can't borrow so the risky weight never goes above 1.
+pub struct VolatilityTargeting {
+    risky_asset: Asset,
+    safe_asset: Asset,
+    target_volatility: f64,
+    max_leverage: f64,
+    min_safe_weight: f64,
+    window_tick_count: usize,
+    price_decimal_shift: usize,
+}
+
+impl VolatilityTargeting {
+    pub fn new(
+        risky_asset: Asset,
+        safe_asset: Asset,
+        target_volatility: f64,
+        max_leverage: f64,
+        min_safe_weight: f64,
+        window_tick_count: usize,
+        price_decimal_shift: usize,
+    ) -> Result<Self, VolatilityTargetingError> {
+        if !(target_volatility > 0f64 && target_volatility.is_finite()) {
+            return Err(VolatilityTargetingError::TargetVolatilityShouldBeGtZero(
+                target_volatility,
+            ));
+        }
+        if !(max_leverage > 0f64 && max_leverage.is_finite()) {
+            return Err(VolatilityTargetingError::MaxLeverageShouldBeGtZero(
+                max_leverage,
+            ));
+        }
+        if !(0f64..=1f64).contains(&min_safe_weight) {
+            return Err(VolatilityTargetingError::MinSafeWeightIncorrect(
+                min_safe_weight,
+            ));
+        }
+        if window_tick_count < 2 {
+            return Err(VolatilityTargetingError::WindowTickCountShouldBeGtOne(
+                window_tick_count,
+            ));
+        }
+
+        Ok(Self {
+            risky_asset,
+            safe_asset,
+            target_volatility,
+            max_leverage,
+            min_safe_weight,
+            window_tick_count,
+            price_decimal_shift,
+        })
+    }
+
+    pub fn risky_weight(&self, tick: &Tick) -> Result<f64, VolatilityTargetingError> {
+        if tick.moving_average.is_none() {
+            return Err(VolatilityTargetingError::TickMovingAverageNone);
+        }
+        let volatility = tick
+            .return_volatility(self.window_tick_count)
+            .ok_or(VolatilityTargetingError::TickVarianceNone)?;
+        let max_risky_weight = self.max_leverage.min(1f64 - self.min_safe_weight);
+        if volatility == 0f64 {
+            return Ok(max_risky_weight);
+        }
+
+        Ok((self.target_volatility / volatility).min(max_risky_weight))
+    }
+
+    pub fn check_new_order(
+        &self,
+        tick: &Tick,
+        risky_hold_quantity: Risky,
+        safe_hold_quantity: Safe,
+    ) -> Result<Option<MarketOrder>, VolatilityTargetingError> {
+        let risky_price: Safe = tick.price_as_f64(self.price_decimal_shift);
+        let risky_hold_safe_value: Safe = risky_hold_quantity * risky_price;
+        let hold_quantity: Safe = risky_hold_safe_value + safe_hold_quantity;
+        let risky_new_hold_quantity: Safe = hold_quantity * self.risky_weight(tick)?;
+
+        let risky_delta = risky_new_hold_quantity - risky_hold_safe_value;
+        match risky_delta {
+            0f64 => Ok(None),
+            i if i.is_sign_positive() => {
+                // Increase risky asset exposure
+                Ok(Some(MarketOrder::new(
+                    self.safe_asset.clone(),
+                    self.risky_asset.clone(),
+                    risky_delta,
+                )))
+            }
+            i if i.is_sign_negative() => {
+                // Decrease risky asset exposure
+                Ok(Some(MarketOrder::new(
+                    self.risky_asset.clone(),
+                    self.safe_asset.clone(),
+                    risky_delta.abs() / risky_price,
+                )))
+            }
+            _ => Err(VolatilityTargetingError::UnexpectedError),
+        }
+    }
+}
+
+impl PortfolioStrategy for VolatilityTargeting {
+    fn check_new_order(
+        &mut self,
+        tick: &Tick,
+        _risky_price: Safe,
+        portfolio: &Portfolio,
+    ) -> Result<Option<MarketOrder>, PortfolioStrategyError> {
+        VolatilityTargeting::check_new_order(
+            self,
+            tick,
+            portfolio.risky_quantity,
+            portfolio.safe_quantity,
+        )
+        .map_err(PortfolioStrategyError::VolatilityTargeting)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U64;
+
+    fn _volatility_targeting_new() -> VolatilityTargeting {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        VolatilityTargeting::new(risky_asset, safe_asset, 0.1f64, 1f64, 0.2f64, 2, 0).unwrap()
+    }
+
+    fn _tick_new(price: u64, moving_average: Option<u64>, variance: Option<u64>) -> Tick {
+        Tick::new(
+            U64::from(price),
+            0,
+            U64::one(),
+            true,
+            moving_average.map(U64::from),
+            variance.map(U64::from),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn volatility_targeting_new_incorrect() {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+
+        let strategy = VolatilityTargeting::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            0f64,
+            1f64,
+            0.2f64,
+            2,
+            0,
+        );
+        assert!(strategy.is_err());
+        assert_eq!(
+            strategy.err().unwrap(),
+            VolatilityTargetingError::TargetVolatilityShouldBeGtZero(0f64)
+        );
+
+        let strategy = VolatilityTargeting::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            0.05f64,
+            f64::NAN,
+            0.2f64,
+            2,
+            0,
+        );
+        assert!(matches!(
+            strategy.err().unwrap(),
+            VolatilityTargetingError::MaxLeverageShouldBeGtZero(_)
+        ));
+
+        let strategy = VolatilityTargeting::new(
+            risky_asset.clone(),
+            safe_asset.clone(),
+            0.05f64,
+            1f64,
+            0.2f64,
+            1,
+            0,
+        );
+        assert_eq!(
+            strategy.err().unwrap(),
+            VolatilityTargetingError::WindowTickCountShouldBeGtOne(1)
+        );
+
+        let strategy =
+            VolatilityTargeting::new(risky_asset, safe_asset, 0.05f64, 1f64, 1.2f64, 2, 0);
+        assert!(strategy.is_err());
+        assert_eq!(
+            strategy.err().unwrap(),
+            VolatilityTargetingError::MinSafeWeightIncorrect(1.2f64)
+        );
+    }
+
+    #[test]
+    fn volatility_targeting_risky_weight() {
+        let strategy = _volatility_targeting_new();
+
+        let weight = strategy.risky_weight(&_tick_new(100, Some(100), Some(100)));
+        assert!(weight.is_ok());
+        assert_eq!(weight.unwrap(), 0.5f64);
+
+        let weight = strategy.risky_weight(&_tick_new(100, Some(100), Some(1)));
+        assert!(weight.is_ok());
+        assert_eq!(weight.unwrap(), 0.8f64);
+
+        let weight = strategy.risky_weight(&_tick_new(100, Some(100), None));
+        assert_eq!(
+            weight.unwrap_err(),
+            VolatilityTargetingError::TickVarianceNone
+        );
+    }
+
+    #[test]
+    fn volatility_targeting_risky_weight_max_leverage() {
+        let risky_asset = Asset::new(String::from("ETH"), String::from("Ether"));
+        let safe_asset = Asset::new(String::from("LUSD"), String::from("Liquity USD"));
+        let strategy =
+            VolatilityTargeting::new(risky_asset, safe_asset, 0.1f64, 0.6f64, 0.2f64, 2, 0)
+                .unwrap();
+
+        // volatility of 2% would give a weight of 5
+        let weight = strategy.risky_weight(&_tick_new(100, Some(100), Some(1)));
+        assert_eq!(weight.unwrap(), 0.6f64);
+
+        let weight = strategy.risky_weight(&_tick_new(100, Some(100), Some(100)));
+        assert_eq!(weight.unwrap(), 0.5f64);
+    }
+
+    #[test]
+    fn volatility_targeting_check_new_order_increase_exposure() {
+        let strategy = _volatility_targeting_new();
+        let result = strategy.check_new_order(&_tick_new(100, Some(100), Some(100)), 2f64, 800f64);
+
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        let order = order.unwrap();
+
+        assert_eq!(order.asset_sell, strategy.safe_asset);
+        assert_eq!(order.asset_buy, strategy.risky_asset);
+        assert_eq!(order.quantity_sell, 300f64);
+    }
+
+    #[test]
+    fn volatility_targeting_check_new_order_reduce_exposure() {
+        let strategy = _volatility_targeting_new();
+        let result = strategy.check_new_order(&_tick_new(100, Some(100), Some(400)), 10f64, 0f64);
+
+        assert!(result.is_ok());
+        let order = result.unwrap();
+        assert!(order.is_some());
+        let order = order.unwrap();
+
+        assert_eq!(order.asset_sell, strategy.risky_asset);
+        assert_eq!(order.asset_buy, strategy.safe_asset);
+        assert_eq!(order.quantity_sell, 7.5f64);
+    }
+
+    #[test]
+    fn volatility_targeting_check_new_order_on_target() {
+        let strategy = _volatility_targeting_new();
+        let result = strategy.check_new_order(&_tick_new(100, Some(100), Some(100)), 5f64, 500f64);
+
+        assert!(result.is_ok());
+        assert!(result.unwrap().is_none());
+    }
+}